@@ -0,0 +1,128 @@
+//! Adaptive concurrency control
+//!
+//! An AIMD (additive increase, multiplicative decrease) controller that
+//! ramps the in-flight request limit up while p95 latency stays under a
+//! target and halves it when latency rises or requests fail.
+
+use std::time::Duration;
+
+/// Latency samples collected before each adjustment
+const ADJUST_WINDOW: usize = 10;
+
+/// Ceiling for the in-flight limit when none is given
+pub const DEFAULT_MAX_CONCURRENCY: usize = 32;
+
+/// AIMD controller for the replay in-flight limit
+#[derive(Debug, Clone)]
+pub struct AimdController {
+    limit: usize,
+    max: usize,
+    target_p95_ms: u64,
+    window: Vec<u64>,
+}
+
+impl AimdController {
+    /// Start at a limit of 1, never exceeding `max`
+    pub fn new(target_p95: Duration, max: usize) -> Self {
+        Self {
+            limit: 1,
+            max: max.max(1),
+            target_p95_ms: target_p95.as_millis() as u64,
+            window: Vec::with_capacity(ADJUST_WINDOW),
+        }
+    }
+
+    /// Current in-flight limit
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Record a completed request. Failed requests count as over target.
+    ///
+    /// Returns the new limit when it changes.
+    pub fn observe(&mut self, latency_ms: u64, failed: bool) -> Option<usize> {
+        self.window.push(if failed { u64::MAX } else { latency_ms });
+        if self.window.len() < ADJUST_WINDOW {
+            return None;
+        }
+
+        let p95 = percentile(&mut self.window, 95);
+        self.window.clear();
+
+        let previous = self.limit;
+        self.limit = if p95 <= self.target_p95_ms {
+            (self.limit + 1).min(self.max)
+        } else {
+            (self.limit / 2).max(1)
+        };
+        (self.limit != previous).then_some(self.limit)
+    }
+}
+
+/// Nearest-rank percentile of `samples` (sorts in place)
+pub(crate) fn percentile(samples: &mut [u64], pct: usize) -> u64 {
+    samples.sort_unstable();
+    let rank = (pct * samples.len()).div_ceil(100).max(1);
+    samples[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(c: &mut AimdController, latency_ms: u64) -> Option<usize> {
+        let mut last = None;
+        for _ in 0..ADJUST_WINDOW {
+            last = c.observe(latency_ms, false);
+        }
+        last
+    }
+
+    #[test]
+    fn test_increases_while_under_target() {
+        let mut c = AimdController::new(Duration::from_millis(200), 4);
+        assert_eq!(c.limit(), 1);
+        assert_eq!(feed(&mut c, 50), Some(2));
+        assert_eq!(feed(&mut c, 50), Some(3));
+        assert_eq!(feed(&mut c, 50), Some(4));
+        // Capped at max
+        assert_eq!(feed(&mut c, 50), None);
+        assert_eq!(c.limit(), 4);
+    }
+
+    #[test]
+    fn test_halves_when_over_target() {
+        let mut c = AimdController::new(Duration::from_millis(200), 16);
+        for _ in 0..7 {
+            feed(&mut c, 50);
+        }
+        assert_eq!(c.limit(), 8);
+        assert_eq!(feed(&mut c, 500), Some(4));
+        assert_eq!(feed(&mut c, 500), Some(2));
+        assert_eq!(feed(&mut c, 500), Some(1));
+        // Floor of 1
+        assert_eq!(feed(&mut c, 500), None);
+    }
+
+    #[test]
+    fn test_failures_count_as_slow() {
+        let mut c = AimdController::new(Duration::from_millis(200), 16);
+        feed(&mut c, 50);
+        feed(&mut c, 50);
+        assert_eq!(c.limit(), 3);
+        for _ in 0..ADJUST_WINDOW - 1 {
+            c.observe(10, false);
+        }
+        // One failure in the window is enough to push p95 over target
+        assert_eq!(c.observe(0, true), Some(1));
+    }
+
+    #[test]
+    fn test_adjusts_only_per_window() {
+        let mut c = AimdController::new(Duration::from_millis(200), 16);
+        for _ in 0..ADJUST_WINDOW - 1 {
+            assert_eq!(c.observe(10, false), None);
+        }
+        assert_eq!(c.observe(10, false), Some(2));
+    }
+}
@@ -2,16 +2,278 @@
 //!
 //! Ushio's internal format for representing captured HTTP traffic.
 
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// A captured HTTP request for replay
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CapturedRequest {
     pub method: String,
     pub url: String,
     pub headers: Vec<(String, String)>,
-    pub body: Option<String>,
+    pub body: Option<Body>,
+    /// Binary request body, base64-encoded; sent when `body` is absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
     pub expected_status: Option<u16>,
+    /// Expected response headers, checked by `diff --against-capture`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected_headers: Vec<(String, String)>,
+    /// Expected response body, checked by `diff --against-capture`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_body: Option<String>,
+    /// Human-readable label shown in reports, e.g. "Login"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Longer note on what the request is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Exact request bytes (request line, headers, body) sent as-is by `--raw`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<String>,
+    /// Milliseconds since the previous request started, from HAR
+    /// `startedDateTime`; honored by `replay --preserve-timing`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    /// How many identical requests `ushio dedup --count` collapsed into this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// Labels for slicing a capture, e.g. "auth" or "checkout"; see `replay --tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// A request body: inline text, or `{"file": "path"}` to read it from a
+/// file when the request is sent
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Body {
+    Inline(String),
+    /// Relative paths are resolved against the capture file's directory
+    File {
+        file: PathBuf,
+    },
+}
+
+impl Body {
+    /// The inline text; `None` for a file body
+    pub fn inline(&self) -> Option<&str> {
+        match self {
+            Body::Inline(text) => Some(text),
+            Body::File { .. } => None,
+        }
+    }
+
+    /// The body text, reading a file body relative to `base` (or the
+    /// working directory). File bodies must be UTF-8; binary bodies belong
+    /// in `body_base64`.
+    pub fn load(&self, base: Option<&Path>) -> anyhow::Result<Cow<'_, str>> {
+        match self {
+            Body::Inline(text) => Ok(Cow::Borrowed(text)),
+            Body::File { file } => {
+                let path = match base {
+                    Some(base) => base.join(file),
+                    None => file.clone(),
+                };
+                let bytes = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read body file {}", path.display()))?;
+                String::from_utf8(bytes).map(Cow::Owned).map_err(|_| {
+                    anyhow::anyhow!(
+                        "Body file {} is not UTF-8 text; use body_base64 for binary bodies",
+                        path.display()
+                    )
+                })
+            }
+        }
+    }
+
+    /// Identity for deduplication: the text, or the path of a file body
+    fn key(&self) -> String {
+        match self {
+            Body::Inline(text) => text.clone(),
+            Body::File { file } => format!("@{}", file.display()),
+        }
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Body::Inline(text)
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Body::Inline(text.to_string())
+    }
+}
+
+/// Inline every file body, so the requests no longer depend on files next
+/// to the capture (e.g. before exporting to HAR or curl)
+pub fn inline_body_files(
+    requests: &mut [CapturedRequest],
+    base: Option<&Path>,
+) -> anyhow::Result<()> {
+    for request in requests {
+        if let Some(body) = request.body.as_mut() {
+            if let Body::File { .. } = body {
+                *body = Body::Inline(body.load(base)?.into_owned());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Repoint relative file bodies of requests read from the directory `from`
+/// so they resolve from `to` instead, for a capture written somewhere else
+/// (`None` is the working directory). Absolute paths are left alone.
+pub fn rebase_body_files(
+    requests: &mut [CapturedRequest],
+    from: Option<&Path>,
+    to: Option<&Path>,
+) -> anyhow::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let from = cwd.join(from.unwrap_or(Path::new(".")));
+    let to = cwd.join(to.unwrap_or(Path::new(".")));
+    if normalize_path(&from) == normalize_path(&to) {
+        return Ok(());
+    }
+    for request in requests {
+        if let Some(Body::File { file }) = request.body.as_mut() {
+            if file.is_relative() {
+                *file = relative_path(&from.join(&*file), &to);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `path` with `.` and `..` components resolved lexically
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Absolute `path` relative to the absolute directory `base`
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path = normalize_path(path);
+    let base = normalize_path(base);
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Different roots (e.g. Windows drives) have no relative form
+    if common == 0 {
+        return path;
+    }
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+/// Names requests whose URL contains `pattern` (from "pattern=Name")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameRule {
+    pub pattern: String,
+    pub name: String,
+}
+
+/// Parse a naming rule like "/api/login=Login"
+pub fn parse_name_rule(spec: &str) -> anyhow::Result<NameRule> {
+    match spec.rsplit_once('=') {
+        Some((pattern, name)) if !pattern.is_empty() && !name.trim().is_empty() => Ok(NameRule {
+            pattern: pattern.to_string(),
+            name: name.trim().to_string(),
+        }),
+        _ => anyhow::bail!("Invalid name rule '{}', expected 'url-pattern=Name'", spec),
+    }
+}
+
+/// Match `text` against a glob where `*` matches any run of characters
+/// (including `/`) and `?` matches exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            // Let the last `*` swallow one more character
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match a URL against a `tag --url-glob` pattern. Patterns starting with
+/// '/' match the path and query; others match the full URL, like `glob_match`.
+pub fn url_glob_match(pattern: &str, url: &str) -> bool {
+    if !pattern.starts_with('/') {
+        return glob_match(pattern, url);
+    }
+    match url::Url::parse(url) {
+        Ok(parsed) => {
+            let path = match parsed.query() {
+                Some(query) => format!("{}?{}", parsed.path(), query),
+                None => parsed.path().to_string(),
+            };
+            glob_match(pattern, &path)
+        }
+        Err(_) => glob_match(pattern, url),
+    }
+}
+
+/// Add `add` and drop `remove` from a request's tags, keeping the existing
+/// order and no duplicates. Returns whether anything changed.
+pub fn retag(request: &mut CapturedRequest, add: &[String], remove: &[String]) -> bool {
+    let before = request.tags.len();
+    request.tags.retain(|t| !remove.contains(t));
+    let mut changed = request.tags.len() != before;
+    for tag in add {
+        if !request.tags.contains(tag) {
+            request.tags.push(tag.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Give an unnamed request the name of the first rule matching its URL
+pub fn apply_name_rules(request: &mut CapturedRequest, rules: &[NameRule]) {
+    if request.name.is_some() {
+        return;
+    }
+    if let Some(rule) = rules.iter().find(|r| request.url.contains(&r.pattern)) {
+        request.name = Some(rule.name.clone());
+    }
 }
 
 /// A capture file containing multiple requests
@@ -19,6 +281,9 @@ pub struct CapturedRequest {
 pub struct Capture {
     pub version: String,
     pub source: Option<String>,
+    /// Files a merged capture was built from, in order (`ushio merge`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
     pub requests: Vec<CapturedRequest>,
 }
 
@@ -27,6 +292,7 @@ impl Capture {
         Self {
             version: "1.0".to_string(),
             source: None,
+            sources: Vec::new(),
             requests,
         }
     }
@@ -37,13 +303,134 @@ impl Capture {
     }
 }
 
+/// Concatenate captures in the order given, recording each file name in
+/// `sources`.
+///
+/// With `dedup`, only the first of several requests with the same method,
+/// URL, and body is kept.
+pub fn merge_captures(parts: Vec<(String, Vec<CapturedRequest>)>, dedup: bool) -> Capture {
+    let mut sources = Vec::with_capacity(parts.len());
+    let mut requests = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (source, part) in parts {
+        sources.push(source);
+        for request in part {
+            if dedup {
+                let key = (
+                    request.method.clone(),
+                    request.url.clone(),
+                    request.body.clone(),
+                    request.body_base64.clone(),
+                );
+                if !seen.insert(key) {
+                    continue;
+                }
+            }
+            requests.push(request);
+        }
+    }
+
+    let mut capture = Capture::new(requests);
+    capture.sources = sources;
+    capture
+}
+
+/// A request field that makes requests distinct for [`dedup_requests`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupField {
+    Method,
+    /// Normalized URL, including the query string
+    Url,
+    /// Normalized URL without the query string
+    Path,
+    Body,
+}
+
+/// Parse a comma-separated field list like "method,url,body"
+pub fn parse_dedup_fields(spec: &str) -> anyhow::Result<Vec<DedupField>> {
+    let fields = spec
+        .split(',')
+        .map(|f| match f.trim().to_ascii_lowercase().as_str() {
+            "method" => Ok(DedupField::Method),
+            "url" => Ok(DedupField::Url),
+            "path" => Ok(DedupField::Path),
+            "body" => Ok(DedupField::Body),
+            other => anyhow::bail!(
+                "Unknown dedup field '{}' (expected method, url, path, or body)",
+                other
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if fields.contains(&DedupField::Url) && fields.contains(&DedupField::Path) {
+        anyhow::bail!("Dedup by either url or path, not both");
+    }
+    Ok(fields)
+}
+
+/// Normalize a URL for comparison: scheme and host lowercased, default
+/// port dropped, query parameters sorted, fragment removed
+fn normalize_url(url: &str, with_query: bool) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    parsed.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+    if with_query && !pairs.is_empty() {
+        pairs.sort();
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    } else {
+        parsed.set_query(None);
+    }
+    parsed.to_string()
+}
+
+/// Collapse requests that agree on every field in `fields` into the first
+/// of them, keeping capture order. With `count`, each kept request records
+/// how many requests it stands for.
+pub fn dedup_requests(
+    requests: Vec<CapturedRequest>,
+    fields: &[DedupField],
+    count: bool,
+) -> Vec<CapturedRequest> {
+    let mut kept: Vec<CapturedRequest> = Vec::new();
+    let mut index_by_key: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+    for request in requests {
+        let key = fields
+            .iter()
+            .map(|field| match field {
+                DedupField::Method => Some(request.method.to_ascii_uppercase()),
+                DedupField::Url => Some(normalize_url(&request.url, true)),
+                DedupField::Path => Some(normalize_url(&request.url, false)),
+                DedupField::Body => request
+                    .body
+                    .as_ref()
+                    .map(Body::key)
+                    .or(request.body_base64.clone()),
+            })
+            .collect();
+        let occurrences = request.count.unwrap_or(1);
+        match index_by_key.get(&key) {
+            Some(&i) => {
+                if count {
+                    *kept[i].count.get_or_insert(1) += occurrences;
+                }
+            }
+            None => {
+                index_by_key.insert(key, kept.len());
+                kept.push(request);
+            }
+        }
+    }
+    kept
+}
+
 /// Supported capture format versions
 const SUPPORTED_VERSIONS: &[&str] = &["1.0"];
 
 /// Load a capture from a file, validating the format version
 pub fn load_capture(path: &str) -> anyhow::Result<Capture> {
     let content = std::fs::read_to_string(path)?;
-    let capture: Capture = serde_json::from_str(&content)?;
+    let capture = parse_capture(&content, path)?;
     if !SUPPORTED_VERSIONS.contains(&capture.version.as_str()) {
         anyhow::bail!(
             "Unsupported capture format version '{}' (supported: {})",
@@ -54,9 +441,133 @@ pub fn load_capture(path: &str) -> anyhow::Result<Capture> {
     Ok(capture)
 }
 
+/// Parse a capture file's contents.
+///
+/// Hand-written captures may use JSON5 (comments, trailing commas): `.json5`
+/// files are always read as JSON5, and other files fall back to it when
+/// strict JSON rejects them with a syntax error.
+pub fn parse_capture(content: &str, path: &str) -> anyhow::Result<Capture> {
+    if path.ends_with(".json5") {
+        return json5::from_str(content).map_err(|e| anyhow::anyhow!("Invalid JSON5: {}", e));
+    }
+    match serde_json::from_str(content) {
+        Ok(capture) => Ok(capture),
+        Err(e) if e.is_syntax() => json5::from_str(content).map_err(|_| e.into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Save a capture to a file
 pub fn save_capture(capture: &Capture, path: &str) -> anyhow::Result<()> {
     let content = serde_json::to_string_pretty(capture)?;
     std::fs::write(path, content)?;
     Ok(())
 }
+
+/// A request present in both captures whose contents differ
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangedRequest {
+    pub method: String,
+    pub url: String,
+    /// Names of the fields that differ (`headers`, `body`, `expected_status`, ...)
+    pub fields: Vec<String>,
+    pub left: CapturedRequest,
+    pub right: CapturedRequest,
+}
+
+/// Differences between the request sets of two captures
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CaptureDiff {
+    pub unchanged: usize,
+    pub added: Vec<CapturedRequest>,
+    pub removed: Vec<CapturedRequest>,
+    pub changed: Vec<ChangedRequest>,
+}
+
+impl CaptureDiff {
+    /// Whether the two captures send anything different
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two captures' request sets.
+///
+/// Requests are aligned by method + URL. Repeated requests to the same
+/// method + URL are paired in the order they appear in each capture.
+pub fn diff_captures(left: &[CapturedRequest], right: &[CapturedRequest]) -> CaptureDiff {
+    let mut right_by_key: HashMap<(String, &str), Vec<&CapturedRequest>> = HashMap::new();
+    for req in right.iter().rev() {
+        right_by_key
+            .entry((req.method.to_uppercase(), req.url.as_str()))
+            .or_default()
+            .push(req);
+    }
+
+    let mut unchanged = 0;
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+
+    for l in left {
+        let key = (l.method.to_uppercase(), l.url.as_str());
+        match right_by_key.get_mut(&key).and_then(|v| v.pop()) {
+            Some(r) => {
+                let mut fields = Vec::new();
+                if l.headers != r.headers {
+                    fields.push("headers".to_string());
+                }
+                if l.body != r.body {
+                    fields.push("body".to_string());
+                }
+                if l.body_base64 != r.body_base64 {
+                    fields.push("body_base64".to_string());
+                }
+                if l.raw != r.raw {
+                    fields.push("raw".to_string());
+                }
+                if l.expected_status != r.expected_status {
+                    fields.push("expected_status".to_string());
+                }
+                if l.expected_headers != r.expected_headers {
+                    fields.push("expected_headers".to_string());
+                }
+                if l.expected_body != r.expected_body {
+                    fields.push("expected_body".to_string());
+                }
+                if l.tags != r.tags {
+                    fields.push("tags".to_string());
+                }
+                if fields.is_empty() {
+                    unchanged += 1;
+                } else {
+                    changed.push(ChangedRequest {
+                        method: l.method.clone(),
+                        url: l.url.clone(),
+                        fields,
+                        left: l.clone(),
+                        right: r.clone(),
+                    });
+                }
+            }
+            None => removed.push(l.clone()),
+        }
+    }
+
+    // Whatever is left unmatched on the right was added, kept in capture order
+    let added = right
+        .iter()
+        .filter(|r| {
+            right_by_key
+                .get(&(r.method.to_uppercase(), r.url.as_str()))
+                .is_some_and(|v| v.iter().any(|p| std::ptr::eq(*p, *r)))
+        })
+        .cloned()
+        .collect();
+
+    CaptureDiff {
+        unchanged,
+        added,
+        removed,
+        changed,
+    }
+}
@@ -0,0 +1,295 @@
+//! Flag defaults from a `ushio.toml` file
+//!
+//! Top-level keys set global flags (`format = "json"`); a table per
+//! subcommand sets that command's flags:
+//!
+//! ```toml
+//! [replay]
+//! target = ["https://staging.example.com"]
+//! header = ["X-Team: edge"]
+//! timeout = 10
+//!
+//! [diff]
+//! compare-header = ["cf-cache-status", "server"]
+//! ```
+//!
+//! Keys are long flag names, with dashes or underscores. The file is turned
+//! into arguments ahead of the command line, so clap validates its values
+//! like any others. A flag given on the command line replaces the file's
+//! value entirely, lists included.
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Command};
+use std::path::Path;
+
+/// Picked up from the working directory when `--config` isn't given
+pub const DEFAULT_FILE: &str = "ushio.toml";
+
+/// `argv` with the config file's flags added, or unchanged when there is no
+/// config file. `argv[0]` is the program name.
+pub fn expand_args(argv: Vec<String>, command: &Command) -> Result<Vec<String>> {
+    let path = match explicit_path(&argv) {
+        Some(path) => path,
+        None if Path::new(DEFAULT_FILE).is_file() => DEFAULT_FILE.to_string(),
+        None => return Ok(argv),
+    };
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file {}", path))?;
+    expand_args_with(argv, command, &content)
+        .with_context(|| format!("Invalid config file {}", path))
+}
+
+/// `argv` with the flags from `content` (a config file's text) added
+pub fn expand_args_with(
+    argv: Vec<String>,
+    command: &Command,
+    content: &str,
+) -> Result<Vec<String>> {
+    let table: toml::Table = content.parse()?;
+    let subcommand = find_subcommand(&argv, command);
+
+    let mut globals = Vec::new();
+    let mut local = Vec::new();
+    for (key, value) in &table {
+        match value {
+            toml::Value::Table(flags) => {
+                let sub = command
+                    .find_subcommand(key)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown command [{}]", key))?;
+                let mut args = Vec::new();
+                for (key, value) in flags {
+                    let section = format!("[{}]", sub.get_name());
+                    args.extend(flag_args(sub, &section, key, value)?);
+                }
+                // Other commands' tables are still checked, so typos surface early
+                if let Some((index, name)) = &subcommand {
+                    if name == sub.get_name() {
+                        local = without_given(args, sub, &argv[index + 1..]);
+                    }
+                }
+            }
+            _ => {
+                let args = flag_args(command, "top level", key, value)?;
+                globals.extend(without_given(args, command, &argv[1..]));
+            }
+        }
+    }
+
+    let mut expanded = argv;
+    if let Some((index, _)) = subcommand {
+        expanded.splice(index + 1..index + 1, local);
+    }
+    expanded.splice(1..1, globals);
+    Ok(expanded)
+}
+
+/// The value of `--config PATH` or `--config=PATH`
+fn explicit_path(argv: &[String]) -> Option<String> {
+    let mut args = argv.iter().skip(1).take_while(|a| *a != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().cloned();
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// Position and name of the subcommand in `argv`, skipping global options
+/// and their values
+fn find_subcommand(argv: &[String], command: &Command) -> Option<(usize, String)> {
+    let mut i = 1;
+    while i < argv.len() {
+        let arg = &argv[i];
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') || arg == "-" {
+            return command
+                .find_subcommand(arg)
+                .map(|sub| (i, sub.get_name().to_string()));
+        }
+        let takes_value = !arg.contains('=')
+            && command.get_arguments().any(|a| {
+                let long = a.get_long().is_some_and(|l| arg == &format!("--{}", l));
+                let short = a.get_short().is_some_and(|s| arg == &format!("-{}", s));
+                (long || short) && a.get_action().takes_values()
+            });
+        i += if takes_value { 2 } else { 1 };
+    }
+    None
+}
+
+/// Command-line arguments for one config key
+fn flag_args(
+    command: &Command,
+    section: &str,
+    key: &str,
+    value: &toml::Value,
+) -> Result<Vec<String>> {
+    let name = key.replace('_', "-");
+    // A watched replay re-runs itself, and would pick `watch` up again
+    if name == "config" || name == "watch" {
+        anyhow::bail!("'{}' can't be set in a config file ({})", name, section);
+    }
+    let arg = command
+        .get_arguments()
+        .find(|a| a.get_long() == Some(name.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("Unknown option '{}' in {}", key, section))?;
+    let flag = format!("--{}", name);
+
+    if !arg.get_action().takes_values() {
+        return match value {
+            toml::Value::Boolean(true) => Ok(vec![flag]),
+            toml::Value::Boolean(false) => Ok(vec![]),
+            _ => anyhow::bail!(
+                "'{}' in {} is a switch and takes true or false",
+                key,
+                section
+            ),
+        };
+    }
+    let values = match value {
+        toml::Value::Array(items) => {
+            if !matches!(arg.get_action(), ArgAction::Append) {
+                anyhow::bail!("'{}' in {} takes a single value, not a list", key, section);
+            }
+            items
+                .iter()
+                .map(|item| scalar(item, key, section))
+                .collect::<Result<Vec<_>>>()?
+        }
+        _ => vec![scalar(value, key, section)?],
+    };
+    // `--flag=value`, so values starting with '-' aren't read as flags
+    Ok(values
+        .into_iter()
+        .map(|v| format!("{}={}", flag, v))
+        .collect())
+}
+
+fn scalar(value: &toml::Value, key: &str, section: &str) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => anyhow::bail!(
+            "'{}' in {} must be a string, number, or boolean",
+            key,
+            section
+        ),
+    }
+}
+
+/// Drop config arguments for flags the command line already gives
+fn without_given(args: Vec<String>, command: &Command, given: &[String]) -> Vec<String> {
+    let given: Vec<&String> = given.iter().take_while(|a| *a != "--").collect();
+    args.into_iter()
+        .filter(|arg| {
+            let long = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+            let short = command
+                .get_arguments()
+                .find(|a| a.get_long().is_some_and(|l| long == format!("--{}", l)))
+                .and_then(|a| a.get_short())
+                .map(|s| format!("-{}", s));
+            !given.iter().any(|g| {
+                g.as_str() == long
+                    || g.starts_with(&format!("{}=", long))
+                    || short.as_deref().is_some_and(|s| g.starts_with(s))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn command() -> Command {
+        Command::new("ushio")
+            .arg(Arg::new("format").short('f').long("format").global(true))
+            .arg(
+                Arg::new("verbose")
+                    .short('v')
+                    .long("verbose")
+                    .action(ArgAction::SetTrue),
+            )
+            .subcommand(
+                Command::new("replay")
+                    .arg(Arg::new("capture"))
+                    .arg(
+                        Arg::new("target")
+                            .short('t')
+                            .long("target")
+                            .action(ArgAction::Append),
+                    )
+                    .arg(Arg::new("header").long("header").action(ArgAction::Append))
+                    .arg(Arg::new("timeout").long("timeout"))
+                    .arg(
+                        Arg::new("insecure")
+                            .long("insecure")
+                            .action(ArgAction::SetTrue),
+                    ),
+            )
+            .subcommand(Command::new("diff").arg(Arg::new("only-diff").long("only-diff")))
+    }
+
+    fn expand(argv: &str, config: &str) -> Result<String> {
+        let argv = argv.split(' ').map(String::from).collect();
+        expand_args_with(argv, &command(), config).map(|args| args.join(" "))
+    }
+
+    #[test]
+    fn test_config_flags_seed_command_line() {
+        let config = r#"
+            format = "json"
+            [replay]
+            target = ["https://a", "https://b"]
+            header = ["-X-Odd: 1"]
+            timeout = 10
+            insecure = true
+        "#;
+        assert_eq!(
+            expand("ushio replay c.json", config).unwrap(),
+            "ushio --format=json replay --header=-X-Odd: 1 --insecure --target=https://a \
+             --target=https://b --timeout=10 c.json"
+        );
+
+        // Command-line flags replace the file's, lists included
+        assert_eq!(
+            expand(
+                "ushio -f compact replay c.json -t https://c --timeout=5",
+                config
+            )
+            .unwrap(),
+            "ushio -f compact replay --header=-X-Odd: 1 --insecure c.json -t https://c --timeout=5"
+        );
+
+        // Global option values aren't mistaken for the subcommand
+        assert_eq!(
+            expand("ushio -f replay diff", "[diff]\nonly_diff = \"x\"").unwrap(),
+            "ushio -f replay diff --only-diff=x"
+        );
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let err = |config: &str| {
+            expand("ushio replay c.json", config)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(err("[replay]\ntargets = [\"x\"]").contains("Unknown option 'targets' in [replay]"));
+        assert!(err("[diff]\nbogus = 1").contains("Unknown option 'bogus' in [diff]"));
+        assert!(err("[bench]\nx = 1").contains("Unknown command [bench]"));
+        assert!(err("[replay]\ninsecure = \"yes\"").contains("takes true or false"));
+        assert!(err("[replay]\ntimeout = [1, 2]").contains("single value"));
+        assert!(err("[replay]\ncapture = \"c.json\"").contains("Unknown option 'capture'"));
+        assert!(err("config = \"other.toml\"").contains("can't be set"));
+        assert!(err("[replay]\nwatch = true").contains("'watch' can't be set"));
+    }
+}
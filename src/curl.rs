@@ -0,0 +1,109 @@
+//! curl export for `convert --to curl`
+//!
+//! Turns captured requests into standalone `curl` invocations for ad-hoc
+//! debugging in a terminal, without running a replay.
+
+use anyhow::Result;
+use url::Url;
+
+use crate::capture::{Body, CapturedRequest};
+use crate::replay::rewrite_url;
+
+/// Build a single-line curl command for `request`.
+///
+/// With a `target`, the URL's scheme, host, and port are rewritten the same
+/// way replay does. `Host` and `Content-Length` are left to curl, matching
+/// what replay sends. The body uses `--data-raw` so a leading `@` is sent
+/// literally rather than read from a file.
+pub fn curl_command(request: &CapturedRequest, target: Option<&Url>) -> Result<String> {
+    let url = match target {
+        Some(target) => rewrite_url(&request.url, target)?,
+        None => request.url.clone(),
+    };
+
+    let mut args = vec![
+        "curl".to_string(),
+        "-X".to_string(),
+        shell_quote(&request.method),
+    ];
+    for (name, value) in &request.headers {
+        if name.eq_ignore_ascii_case("host") || name.eq_ignore_ascii_case("content-length") {
+            continue;
+        }
+        args.push("-H".to_string());
+        args.push(shell_quote(&format!("{}: {}", name, value)));
+    }
+    match request.body {
+        Some(Body::Inline(ref text)) => {
+            args.push("--data-raw".to_string());
+            args.push(shell_quote(text));
+        }
+        Some(Body::File { ref file }) => {
+            args.push("--data-binary".to_string());
+            args.push(shell_quote(&format!("@{}", file.display())));
+        }
+        None => {}
+    }
+    args.push(shell_quote(&url));
+
+    Ok(args.join(" "))
+}
+
+/// Quote an argument for POSIX shells, leaving plain words untouched
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("GET"), "GET");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_curl_command_rewrites_target() {
+        let request = CapturedRequest {
+            method: "POST".to_string(),
+            url: "https://prod.example.com/api/login?next=/home&x=1".to_string(),
+            headers: vec![
+                ("Host".to_string(), "prod.example.com".to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Content-Length".to_string(), "17".to_string()),
+            ],
+            body: Some(r#"{"user":"o'neil"}"#.into()),
+            ..Default::default()
+        };
+
+        let target = Url::parse("http://localhost:8080").unwrap();
+        assert_eq!(
+            curl_command(&request, Some(&target)).unwrap(),
+            "curl -X POST -H 'Content-Type: application/json' \
+             --data-raw '{\"user\":\"o'\\''neil\"}' \
+             'http://localhost:8080/api/login?next=/home&x=1'"
+        );
+
+        let request = CapturedRequest {
+            body: Some(Body::File {
+                file: "bodies/login.json".into(),
+            }),
+            ..request
+        };
+        assert!(curl_command(&request, None)
+            .unwrap()
+            .contains(" --data-binary @bodies/login.json "));
+    }
+}
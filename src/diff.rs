@@ -4,9 +4,12 @@
 //! in status codes, headers, body content, and WAF decisions.
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use similar::{ChangeTag, TextDiff};
+use std::collections::HashMap;
 
-use crate::replay::{ReplayResult, ReplaySession};
+use crate::capture::{url_glob_match, CapturedRequest};
+use crate::replay::{CompressionReport, ReplayResult, ReplaySession};
 
 /// Difference between two replay results
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,14 +19,125 @@ pub struct RequestDiff {
     pub url: String,
     pub status_diff: Option<StatusDiff>,
     pub header_diffs: Vec<HeaderDiff>,
+    /// Differences in response trailers (e.g. `grpc-status`)
+    #[serde(default)]
+    pub trailer_diffs: Vec<HeaderDiff>,
     pub body_diff: Option<BodyDiff>,
     pub waf_diff: Option<WafDiff>,
+    /// Backend technology headers (`Server`, `X-Powered-By`, `Via`) changed
+    #[serde(default)]
+    pub tech_stack_diff: Option<TechStackDiff>,
+    /// The right side was slower beyond the latency threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_diff: Option<LatencyDiff>,
+    /// Response body size changed beyond the size threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_diff: Option<SizeDiff>,
+    /// The two sides negotiated different HTTP versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_diff: Option<VersionDiff>,
+    /// The two sides negotiated a different TLS version or cipher suite
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_diff: Option<TlsDiff>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StatusDiff {
     pub left: u16,
     pub right: u16,
+    /// How much the change matters, judged by the status classes involved
+    #[serde(default)]
+    pub severity: StatusSeverity,
+}
+
+impl StatusDiff {
+    pub fn new(left: u16, right: u16) -> Self {
+        Self {
+            left,
+            right,
+            severity: StatusSeverity::of(left, right),
+        }
+    }
+
+    /// Class transition label, e.g. "success→server_error"
+    pub fn transition(&self) -> String {
+        format!(
+            "{}→{}",
+            StatusClass::of(self.left).label(),
+            StatusClass::of(self.right).label()
+        )
+    }
+
+    /// The right side got a 5xx where the left side didn't
+    pub fn is_new_server_error(&self) -> bool {
+        StatusClass::of(self.right) == StatusClass::ServerError
+            && StatusClass::of(self.left) != StatusClass::ServerError
+    }
+}
+
+/// Broad class of an HTTP status code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    /// Status 0: the request failed or the side has no result
+    NoResponse,
+    Informational,
+    Success,
+    Redirect,
+    ClientError,
+    ServerError,
+}
+
+impl StatusClass {
+    pub fn of(status: u16) -> Self {
+        match status {
+            0 => StatusClass::NoResponse,
+            1..=199 => StatusClass::Informational,
+            200..=299 => StatusClass::Success,
+            300..=399 => StatusClass::Redirect,
+            400..=499 => StatusClass::ClientError,
+            _ => StatusClass::ServerError,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusClass::NoResponse => "no_response",
+            StatusClass::Informational => "informational",
+            StatusClass::Success => "success",
+            StatusClass::Redirect => "redirect",
+            StatusClass::ClientError => "client_error",
+            StatusClass::ServerError => "server_error",
+        }
+    }
+}
+
+/// Impact of a status change, lowest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusSeverity {
+    /// Same class, e.g. 200 → 204
+    #[default]
+    Cosmetic,
+    /// Different class, but the right side isn't an error (e.g. 302 → 200, or a fixed 500)
+    Notable,
+    /// The right side became a 4xx
+    Major,
+    /// The right side became a 5xx or got no response
+    Critical,
+}
+
+impl StatusSeverity {
+    pub fn of(left: u16, right: u16) -> Self {
+        let (left, right) = (StatusClass::of(left), StatusClass::of(right));
+        if left == right {
+            return StatusSeverity::Cosmetic;
+        }
+        match right {
+            StatusClass::ServerError | StatusClass::NoResponse => StatusSeverity::Critical,
+            StatusClass::ClientError => StatusSeverity::Major,
+            _ => StatusSeverity::Notable,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,12 +163,150 @@ pub struct WafDiff {
     pub right_reason: Option<String>,
 }
 
+/// A latency regression from left to right
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LatencyDiff {
+    pub left_ms: u64,
+    pub right_ms: u64,
+    /// How much slower the right side was
+    pub delta_ms: u64,
+}
+
+/// A change in response body size from left to right
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SizeDiff {
+    pub left_bytes: usize,
+    pub right_bytes: usize,
+    /// Right minus left; negative when the body shrank
+    pub delta_bytes: i64,
+}
+
+/// Protocol versions the two sides responded with, e.g. "HTTP/1.1" vs "HTTP/2"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionDiff {
+    pub left: String,
+    pub right: String,
+}
+
+/// TLS parameters each side's per-host probe negotiated (from
+/// `replay --tls-info`)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TlsDiff {
+    pub left_version: String,
+    pub right_version: String,
+    pub left_cipher: Option<String>,
+    pub right_cipher: Option<String>,
+}
+
+/// Backend technology a response advertised
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TechStack {
+    pub server: Option<String>,
+    pub powered_by: Option<String>,
+    pub via: Option<String>,
+}
+
+impl TechStack {
+    fn from_headers(headers: &[(String, String)], compare_headers: &[String]) -> Self {
+        let get = |name: &str| {
+            if !compare_headers.iter().any(|h| h == name) {
+                return None;
+            }
+            find_header(headers, name)
+        };
+        Self {
+            server: get("server"),
+            powered_by: get("x-powered-by"),
+            via: get("via"),
+        }
+    }
+
+    /// Human-readable summary, e.g. "nginx/1.25, PHP/8.2, via 1.1 varnish"
+    pub fn describe(&self) -> String {
+        let parts: Vec<String> = [
+            self.server.clone(),
+            self.powered_by.clone(),
+            self.via.as_ref().map(|v| format!("via {}", v)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if parts.is_empty() {
+            "unknown".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Product names without versions, e.g. "nginx/1.25.3" -> "nginx"
+    fn products(&self) -> [Option<String>; 3] {
+        [&self.server, &self.powered_by, &self.via].map(|value| value.as_deref().map(product_name))
+    }
+}
+
+/// Product name of a `Server`/`X-Powered-By`/`Via` value, lowercased.
+///
+/// Leading protocol versions in `Via` ("1.1 varnish") are skipped.
+fn product_name(value: &str) -> String {
+    value
+        .split_whitespace()
+        .find(|token| !token.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(value)
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TechChangeKind {
+    /// A different product answered (e.g. nginx -> envoy)
+    BackendChanged,
+    /// Same products, different versions or details
+    VersionChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TechStackDiff {
+    pub kind: TechChangeKind,
+    pub left: TechStack,
+    pub right: TechStack,
+}
+
+/// A technology change seen across one or more requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TechStackChange {
+    pub kind: TechChangeKind,
+    pub left: TechStack,
+    pub right: TechStack,
+    pub requests: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BodyDiff {
     pub left_size: usize,
     pub right_size: usize,
     /// Unified diff of body content (truncated if large)
     pub unified_diff: String,
+    /// Changed JSON paths, when both sides are JSON responses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_changes: Vec<JsonChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JsonChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A value that differs at one JSON path, e.g. `$.data.items[3].price`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonChange {
+    pub path: String,
+    pub kind: JsonChangeKind,
+    pub left: Option<serde_json::Value>,
+    pub right: Option<serde_json::Value>,
 }
 
 /// Summary of differences between two replay sessions
@@ -67,9 +319,275 @@ pub struct DiffSummary {
     pub different: usize,
     pub status_diffs: usize,
     pub header_diffs: usize,
+    #[serde(default)]
+    pub trailer_diffs: usize,
     pub body_diffs: usize,
     pub waf_diffs: usize,
+    #[serde(default)]
+    pub latency_diffs: usize,
+    #[serde(default)]
+    pub size_diffs: usize,
+    #[serde(default)]
+    pub version_diffs: usize,
+    #[serde(default)]
+    pub tls_diffs: usize,
     pub diffs: Vec<RequestDiff>,
+    /// Comparison stopped at the first difference (`--fail-fast`); counts cover only what was compared
+    #[serde(default)]
+    pub stopped_early: bool,
+    /// Headers were only compared within this set, because at least one
+    /// side recorded a restricted set of response headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_scope: Option<Vec<String>>,
+    /// Distinct backend technology changes, most frequent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tech_stack_changes: Vec<TechStackChange>,
+    /// Left results with no matching right result (`--match-by url`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmatched_left: Vec<UnmatchedRequest>,
+    /// Right results with no matching left result (`--match-by url`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unmatched_right: Vec<UnmatchedRequest>,
+    /// Request indices that compared identical, for per-request reports
+    #[serde(skip)]
+    pub identical_indices: Vec<usize>,
+    /// Requests left out by `--ignore-url`; not part of `total_requests`
+    #[serde(default)]
+    pub ignored: usize,
+}
+
+/// Knobs for how two result sets are compared
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Stop at the first differing request instead of comparing everything
+    pub fail_fast: bool,
+    /// How results on the two sides are paired up
+    pub match_by: MatchBy,
+    /// Headers to compare instead of the default WAF/security set
+    pub compare_headers: Vec<String>,
+    /// Headers never compared, e.g. per-request IDs like `cf-ray`
+    pub ignore_headers: Vec<String>,
+    /// How WAF blocks are recognized
+    pub waf: WafRules,
+    /// Report requests that got slower beyond this; `None` ignores timing
+    pub latency: Option<LatencyThreshold>,
+    /// How much a body size may change before it counts as a difference
+    pub size: SizeThreshold,
+    /// Query parameters left out of the `MatchBy::Url` key, e.g. `_ts`
+    pub drop_params: Vec<String>,
+    /// URL globs (as for `tag --url-glob`) of requests left out of the
+    /// diff, e.g. telemetry endpoints
+    pub ignore_urls: Vec<String>,
+}
+
+/// How much a response body may grow or shrink before its size counts as
+/// a difference. The default reports any change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeThreshold {
+    /// Absolute change in bytes
+    Bytes(u64),
+    /// Change relative to the left side, in percent
+    Percent(f64),
+}
+
+impl Default for SizeThreshold {
+    fn default() -> Self {
+        SizeThreshold::Bytes(0)
+    }
+}
+
+impl SizeThreshold {
+    /// Parse `--size-threshold`: a byte count ("512") or a percentage ("10%")
+    pub fn parse(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let parsed = match s.strip_suffix('%') {
+            Some(pct) => pct
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|p| p.is_finite() && *p >= 0.0)
+                .map(SizeThreshold::Percent),
+            None => s.parse::<u64>().ok().map(SizeThreshold::Bytes),
+        };
+        parsed.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid size threshold '{}', expected bytes (e.g. 512) or a percentage (e.g. 10%)",
+                s
+            )
+        })
+    }
+
+    /// Whether going from `left` to `right` bytes is a reportable change
+    pub fn exceeded(&self, left: usize, right: usize) -> bool {
+        let delta = left.abs_diff(right);
+        match *self {
+            SizeThreshold::Bytes(bytes) => delta as u64 > bytes,
+            SizeThreshold::Percent(pct) => delta as f64 > left as f64 * pct / 100.0,
+        }
+    }
+}
+
+/// How much slower the right side must be before latency counts as a
+/// difference. With both limits set, a regression has to exceed both, so
+/// fast requests don't trip the percentage on a few milliseconds of jitter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyThreshold {
+    /// Absolute slowdown in milliseconds (`--latency-threshold`)
+    pub ms: Option<u64>,
+    /// Slowdown relative to the left side, in percent (`--latency-pct`)
+    pub pct: Option<f64>,
+}
+
+impl LatencyThreshold {
+    /// Whether going from `left_ms` to `right_ms` is a regression
+    pub fn exceeded(&self, left_ms: u64, right_ms: u64) -> bool {
+        if right_ms <= left_ms {
+            return false;
+        }
+        let delta = right_ms - left_ms;
+        let over_ms = self.ms.map_or(true, |ms| delta > ms);
+        let over_pct = self
+            .pct
+            .map_or(true, |pct| delta as f64 > left_ms as f64 * pct / 100.0);
+        over_ms && over_pct
+    }
+}
+
+/// Status codes treated as WAF blocks unless `--waf-status` overrides them
+pub const DEFAULT_WAF_STATUSES: &[u16] = &[403, 429, 503];
+
+/// How WAF blocks are recognized
+#[derive(Debug, Clone)]
+pub struct WafRules {
+    /// Status codes that mean a block
+    pub statuses: Vec<u16>,
+    /// Block page patterns matched against response bodies, checked before
+    /// the built-in ones
+    pub body_signatures: Vec<regex::Regex>,
+}
+
+impl Default for WafRules {
+    fn default() -> Self {
+        Self {
+            statuses: DEFAULT_WAF_STATUSES.to_vec(),
+            body_signatures: Vec::new(),
+        }
+    }
+}
+
+impl WafRules {
+    /// Compile `--waf-signature` patterns
+    pub fn with_signatures(patterns: &[String]) -> anyhow::Result<Self> {
+        let body_signatures = patterns
+            .iter()
+            .map(|p| {
+                regex::Regex::new(p)
+                    .map_err(|e| anyhow::anyhow!("Invalid WAF signature '{}': {}", p, e))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            body_signatures,
+            ..Self::default()
+        })
+    }
+
+    /// Replace the blocking status codes (`--waf-status`); empty keeps the defaults
+    pub fn with_statuses(mut self, statuses: &[u16]) -> Self {
+        if !statuses.is_empty() {
+            self.statuses = statuses.to_vec();
+        }
+        self
+    }
+
+    fn is_block_status(&self, status: u16) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// The first body pattern a response matches: a user signature, then the
+    /// built-in block pages
+    fn body_match(&self, body: &str) -> Option<String> {
+        if let Some(signature) = self.body_signatures.iter().find(|s| s.is_match(body)) {
+            return Some(format!("body signature: {}", signature.as_str()));
+        }
+        let body_lower = body.to_lowercase();
+        WAF_BODY_PATTERNS
+            .iter()
+            .find(|pattern| body_lower.contains(*pattern))
+            .map(|pattern| format!("body match: {}", pattern))
+    }
+}
+
+impl DiffOptions {
+    /// Lowercase names of the headers to compare, limited to `scope` if given
+    pub fn compared_headers(&self, scope: Option<&[String]>) -> Vec<String> {
+        let base: Vec<String> = if self.compare_headers.is_empty() {
+            COMPARE_HEADERS.iter().map(|h| h.to_string()).collect()
+        } else {
+            self.compare_headers
+                .iter()
+                .map(|h| h.to_lowercase())
+                .collect()
+        };
+        let mut headers: Vec<String> = Vec::new();
+        for h in base {
+            let ignored = self
+                .ignore_headers
+                .iter()
+                .any(|i| i.eq_ignore_ascii_case(&h));
+            let in_scope = scope.map_or(true, |s| s.contains(&h));
+            if !ignored && in_scope && !headers.contains(&h) {
+                headers.push(h);
+            }
+        }
+        headers
+    }
+}
+
+/// How results on the two sides are paired up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchBy {
+    /// The n-th result on the left is compared with the n-th on the right
+    #[default]
+    Index,
+    /// Results are paired by method and URL path + sorted query, ignoring the host
+    Url,
+}
+
+/// A result with no counterpart on the other side (`MatchBy::Url` only)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnmatchedRequest {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+}
+
+/// Status codes of one request across every compared session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiDiffRow {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    /// One entry per session, in session order; `None` when the session has
+    /// no result for this request or it failed without a response
+    pub statuses: Vec<Option<u16>>,
+    /// Every session returned the same status
+    pub agree: bool,
+    /// The one session that disagrees with all others, which agree among
+    /// themselves (three or more sessions only)
+    pub odd_one_out: Option<usize>,
+}
+
+/// Status code agreement across three or more replay sessions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultiDiffSummary {
+    pub targets: Vec<String>,
+    pub total_requests: usize,
+    pub agreeing: usize,
+    pub diverging: usize,
+    /// Per target, how many requests it was the odd one out for
+    pub odd_one_out_counts: Vec<usize>,
+    pub rows: Vec<MultiDiffRow>,
 }
 
 /// Headers to compare for differences (WAF-related and security headers)
@@ -84,6 +602,8 @@ const COMPARE_HEADERS: &[&str] = &[
     "x-blocked",
     "x-blocked-by",
     "server",
+    "x-powered-by",
+    "via",
     "x-frame-options",
     "content-security-policy",
     "strict-transport-security",
@@ -92,40 +612,408 @@ const COMPARE_HEADERS: &[&str] = &[
 
 /// Compare two replay sessions and produce a diff summary
 pub fn diff_sessions(left: &ReplaySession, right: &ReplaySession) -> DiffSummary {
+    diff_sessions_with(left, right, &DiffOptions::default())
+}
+
+/// Compare the status codes of any number of sessions, request by request.
+///
+/// Results are paired by `request_index` like the two-way diff. A request
+/// diverges when any two sessions returned different statuses; when all but
+/// one session agree, that one is reported as the odd one out.
+pub fn diff_sessions_multi(sessions: &[ReplaySession]) -> MultiDiffSummary {
+    let targets: Vec<String> = sessions.iter().map(|s| s.target.clone()).collect();
+    let sets: Vec<&[ReplayResult]> = sessions.iter().map(|s| s.results.as_slice()).collect();
+    diff_result_sets_multi(targets, &sets)
+}
+
+/// Compare the statuses of any number of result lists, paired by index
+fn diff_result_sets_multi(targets: Vec<String>, sets: &[&[ReplayResult]]) -> MultiDiffSummary {
+    let rows: Vec<MultiDiffRow> = pair_by_index_multi(sets)
+        .into_iter()
+        .map(|(request_index, results)| {
+            let first = results.iter().flatten().next();
+            let statuses: Vec<Option<u16>> = results
+                .iter()
+                .map(|r| r.filter(|r| r.error.is_none()).map(|r| r.status))
+                .collect();
+            let agree = statuses.windows(2).all(|w| w[0] == w[1]);
+            MultiDiffRow {
+                request_index,
+                method: first.map(|r| r.method.clone()).unwrap_or_default(),
+                url: first.map(|r| r.url.clone()).unwrap_or_default(),
+                odd_one_out: if agree { None } else { odd_one_out(&statuses) },
+                statuses,
+                agree,
+            }
+        })
+        .collect();
+
+    let mut odd_one_out_counts = vec![0; sets.len()];
+    for i in rows.iter().filter_map(|r| r.odd_one_out) {
+        odd_one_out_counts[i] += 1;
+    }
+    let agreeing = rows.iter().filter(|r| r.agree).count();
+    MultiDiffSummary {
+        targets,
+        total_requests: rows.len(),
+        agreeing,
+        diverging: rows.len() - agreeing,
+        odd_one_out_counts,
+        rows,
+    }
+}
+
+/// Position of the single value that differs when all others are equal
+fn odd_one_out<T: PartialEq>(values: &[T]) -> Option<usize> {
+    if values.len() < 3 {
+        return None;
+    }
+    let mut odd = (0..values.len()).filter(|&i| {
+        let others: Vec<&T> = (0..values.len())
+            .filter(|&j| j != i)
+            .map(|j| &values[j])
+            .collect();
+        others.windows(2).all(|w| w[0] == w[1]) && *others[0] != values[i]
+    });
+    match (odd.next(), odd.next()) {
+        (Some(i), None) => Some(i),
+        _ => None,
+    }
+}
+
+/// Compare two replay sessions with explicit options
+pub fn diff_sessions_with(
+    left: &ReplaySession,
+    right: &ReplaySession,
+    options: &DiffOptions,
+) -> DiffSummary {
+    let scope = header_scope(
+        left.captured_headers.as_deref(),
+        right.captured_headers.as_deref(),
+    );
+    diff_result_sets(
+        &left.target,
+        &right.target,
+        &left.results,
+        &right.results,
+        options,
+        scope,
+    )
+}
+
+/// Headers both sides actually recorded; `None` when both kept everything
+pub fn header_scope(left: Option<&[String]>, right: Option<&[String]>) -> Option<Vec<String>> {
+    let mut scope: Vec<String> = match (left, right) {
+        (None, None) => return None,
+        (Some(only), None) | (None, Some(only)) => only.to_vec(),
+        (Some(l), Some(r)) => l.iter().filter(|h| r.contains(h)).cloned().collect(),
+    };
+    scope.sort();
+    scope.dedup();
+    Some(scope)
+}
+
+/// Compare a live session against the expectations stored in its capture.
+///
+/// The left side is synthesized from each request's `expected_status`,
+/// `expected_headers`, and `expected_body`; anything the capture doesn't
+/// specify is taken from the live result so it never shows up as a diff.
+/// The capture must be the full one the session was replayed from, without
+/// filters: requests are matched by `request_index`, which is always the
+/// capture index.
+pub fn diff_against_capture(
+    capture_label: &str,
+    requests: &[CapturedRequest],
+    session: &ReplaySession,
+) -> DiffSummary {
+    diff_against_capture_with(capture_label, requests, session, &DiffOptions::default())
+}
+
+/// Compare a live session against its capture's expectations with explicit options
+pub fn diff_against_capture_with(
+    capture_label: &str,
+    requests: &[CapturedRequest],
+    session: &ReplaySession,
+    options: &DiffOptions,
+) -> DiffSummary {
+    let expected: Vec<ReplayResult> = session
+        .results
+        .iter()
+        .map(|live| match requests.get(live.request_index) {
+            Some(req) => expected_result(req, live),
+            None => live.clone(),
+        })
+        .collect();
+
+    // The capture's expected headers are complete; only the session may be restricted
+    let scope = header_scope(None, session.captured_headers.as_deref());
+    // Expectations are built per result, so the pairing is always by index
+    let options = DiffOptions {
+        match_by: MatchBy::Index,
+        ..options.clone()
+    };
+    diff_result_sets(
+        capture_label,
+        &session.target,
+        &expected,
+        &session.results,
+        &options,
+        scope,
+    )
+}
+
+/// Build the result a request was expected to produce
+fn expected_result(request: &CapturedRequest, live: &ReplayResult) -> ReplayResult {
+    let mut expected = live.clone();
+    if let Some(status) = request.expected_status {
+        expected.status = status;
+    }
+    if !request.expected_headers.is_empty() {
+        expected.headers = request.expected_headers.clone();
+    }
+    if let Some(ref body) = request.expected_body {
+        expected.body = Some(body.clone());
+        expected.body_base64 = None;
+        expected.body_size = body.len();
+        expected.body_hash = if body.is_empty() {
+            None
+        } else {
+            Some(format!("{:x}", Sha256::digest(body.as_bytes())))
+        };
+    }
+    expected
+}
+
+/// Key results are paired on under `MatchBy::Url`: method plus path and
+/// sorted query string, without scheme, host, port, or `drop_params`
+pub fn match_key(method: &str, url: &str, drop_params: &[String]) -> (String, String) {
+    let target = match url::Url::parse(url) {
+        Ok(parsed) => {
+            let mut query: Vec<(String, String)> = parsed
+                .query_pairs()
+                .into_owned()
+                .filter(|(name, _)| !drop_params.contains(name))
+                .collect();
+            query.sort();
+            if query.is_empty() {
+                parsed.path().to_string()
+            } else {
+                let query = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(query)
+                    .finish();
+                format!("{}?{}", parsed.path(), query)
+            }
+        }
+        Err(_) => url.to_string(),
+    };
+    (method.to_uppercase(), target)
+}
+
+/// Pair results by `match_key`; repeated keys pair in order of appearance.
+///
+/// Returns the matched pairs in left order plus the unmatched results of each side.
+fn pair_by_url<'a>(
+    left: &'a [ReplayResult],
+    right: &'a [ReplayResult],
+    drop_params: &[String],
+) -> (
+    Vec<(&'a ReplayResult, &'a ReplayResult)>,
+    Vec<&'a ReplayResult>,
+    Vec<&'a ReplayResult>,
+) {
+    let mut right_by_key: HashMap<(String, String), Vec<&ReplayResult>> = HashMap::new();
+    for r in right.iter().rev() {
+        right_by_key
+            .entry(match_key(&r.method, &r.url, drop_params))
+            .or_default()
+            .push(r);
+    }
+
+    let mut pairs = Vec::new();
+    let mut unmatched_left = Vec::new();
+    for l in left {
+        match right_by_key
+            .get_mut(&match_key(&l.method, &l.url, drop_params))
+            .and_then(|v| v.pop())
+        {
+            Some(r) => pairs.push((l, r)),
+            None => unmatched_left.push(l),
+        }
+    }
+
+    let unmatched_right = right
+        .iter()
+        .filter(|r| {
+            right_by_key
+                .get(&match_key(&r.method, &r.url, drop_params))
+                .is_some_and(|v| v.iter().any(|p| std::ptr::eq(*p, *r)))
+        })
+        .collect();
+    (pairs, unmatched_left, unmatched_right)
+}
+
+/// Pair results by `request_index`, so a filtered run lines up with a full
+/// one by capture position. Falls back to position in the list when a side
+/// repeats an index (e.g. Accept sweeps).
+fn pair_by_index<'a>(
+    left: &'a [ReplayResult],
+    right: &'a [ReplayResult],
+) -> Vec<(usize, Option<&'a ReplayResult>, Option<&'a ReplayResult>)> {
+    pair_by_index_multi(&[left, right])
+        .into_iter()
+        .map(|(i, row)| (i, row[0], row[1]))
+        .collect()
+}
+
+/// [`pair_by_index`] across any number of result sets: one row per index,
+/// with one slot per set
+fn pair_by_index_multi<'a>(
+    sets: &[&'a [ReplayResult]],
+) -> Vec<(usize, Vec<Option<&'a ReplayResult>>)> {
+    let by_index = |results: &'a [ReplayResult]| {
+        let map: HashMap<usize, &ReplayResult> =
+            results.iter().map(|r| (r.request_index, r)).collect();
+        (map.len() == results.len()).then_some(map)
+    };
+    let maps: Option<Vec<_>> = sets.iter().map(|results| by_index(results)).collect();
+    match maps {
+        Some(maps) => {
+            let mut indices: Vec<usize> = maps.iter().flat_map(|m| m.keys()).copied().collect();
+            indices.sort_unstable();
+            indices.dedup();
+            indices
+                .into_iter()
+                .map(|i| (i, maps.iter().map(|m| m.get(&i).copied()).collect()))
+                .collect()
+        }
+        None => {
+            let max_len = sets.iter().map(|s| s.len()).max().unwrap_or(0);
+            (0..max_len)
+                .map(|i| (i, sets.iter().map(|s| s.get(i)).collect()))
+                .collect()
+        }
+    }
+}
+
+fn unmatched(result: &ReplayResult) -> UnmatchedRequest {
+    UnmatchedRequest {
+        request_index: result.request_index,
+        method: result.method.clone(),
+        url: result.url.clone(),
+    }
+}
+
+/// Compare two lists of results, paired according to `options.match_by`
+fn diff_result_sets(
+    left_target: &str,
+    right_target: &str,
+    left_results: &[ReplayResult],
+    right_results: &[ReplayResult],
+    options: &DiffOptions,
+    header_scope: Option<Vec<String>>,
+) -> DiffSummary {
     let mut diffs = Vec::new();
     let mut identical = 0;
     let mut different = 0;
     let mut status_diffs_count = 0;
     let mut header_diffs_count = 0;
+    let mut trailer_diffs_count = 0;
     let mut body_diffs_count = 0;
     let mut waf_diffs_count = 0;
+    let mut latency_diffs_count = 0;
+    let mut size_diffs_count = 0;
+    let mut version_diffs_count = 0;
+    let mut tls_diffs_count = 0;
+
+    let (pairs, unmatched_left, unmatched_right) = match options.match_by {
+        MatchBy::Index => (pair_by_index(left_results, right_results), vec![], vec![]),
+        MatchBy::Url => {
+            let (matched, left_only, right_only) =
+                pair_by_url(left_results, right_results, &options.drop_params);
+            let pairs: Vec<_> = matched
+                .into_iter()
+                .map(|(l, r)| (l.request_index, Some(l), Some(r)))
+                .collect();
+            (pairs, left_only, right_only)
+        }
+    };
+    // Ignored requests drop out after pairing, so a pair counts once even
+    // when only one side's URL matches (e.g. a full-URL glob)
+    let is_ignored = |r: Option<&ReplayResult>| {
+        r.is_some_and(|r| {
+            options
+                .ignore_urls
+                .iter()
+                .any(|pattern| url_glob_match(pattern, &r.url))
+        })
+    };
+    let before = pairs.len() + unmatched_left.len() + unmatched_right.len();
+    let pairs: Vec<_> = pairs
+        .into_iter()
+        .filter(|&(_, l, r)| !is_ignored(l) && !is_ignored(r))
+        .collect();
+    let unmatched_left: Vec<_> = unmatched_left
+        .into_iter()
+        .filter(|&l| !is_ignored(Some(l)))
+        .collect();
+    let unmatched_right: Vec<_> = unmatched_right
+        .into_iter()
+        .filter(|&r| !is_ignored(Some(r)))
+        .collect();
+    let ignored = before - pairs.len() - unmatched_left.len() - unmatched_right.len();
 
-    // Match requests by index
-    let max_len = left.results.len().max(right.results.len());
+    let compare_headers = options.compared_headers(header_scope.as_deref());
+    let mut compared = 0;
+    let mut identical_indices = Vec::new();
 
-    for i in 0..max_len {
-        let left_result = left.results.get(i);
-        let right_result = right.results.get(i);
+    for &(i, left_result, right_result) in &pairs {
+        if options.fail_fast && different > 0 {
+            break;
+        }
+        compared += 1;
 
         match (left_result, right_result) {
             (Some(l), Some(r)) => {
-                if let Some(diff) = diff_results(l, r) {
+                if let Some(diff) = diff_results_with_headers(
+                    l,
+                    r,
+                    &compare_headers,
+                    &options.waf,
+                    options.latency.as_ref(),
+                    &options.size,
+                ) {
                     if diff.status_diff.is_some() {
                         status_diffs_count += 1;
                     }
                     if !diff.header_diffs.is_empty() {
                         header_diffs_count += 1;
                     }
+                    if !diff.trailer_diffs.is_empty() {
+                        trailer_diffs_count += 1;
+                    }
                     if diff.body_diff.is_some() {
                         body_diffs_count += 1;
                     }
                     if diff.waf_diff.is_some() {
                         waf_diffs_count += 1;
                     }
+                    if diff.latency_diff.is_some() {
+                        latency_diffs_count += 1;
+                    }
+                    if diff.size_diff.is_some() {
+                        size_diffs_count += 1;
+                    }
+                    if diff.version_diff.is_some() {
+                        version_diffs_count += 1;
+                    }
+                    if diff.tls_diff.is_some() {
+                        tls_diffs_count += 1;
+                    }
                     different += 1;
                     diffs.push(diff);
                 } else {
                     identical += 1;
+                    identical_indices.push(i);
                 }
             }
             (Some(l), None) => {
@@ -135,13 +1023,16 @@ pub fn diff_sessions(left: &ReplaySession, right: &ReplaySession) -> DiffSummary
                     request_index: i,
                     method: l.method.clone(),
                     url: l.url.clone(),
-                    status_diff: Some(StatusDiff {
-                        left: l.status,
-                        right: 0,
-                    }),
+                    status_diff: Some(StatusDiff::new(l.status, 0)),
                     header_diffs: vec![],
+                    trailer_diffs: vec![],
                     body_diff: None,
                     waf_diff: None,
+                    tech_stack_diff: None,
+                    latency_diff: None,
+                    size_diff: None,
+                    version_diff: None,
+                    tls_diff: None,
                 });
             }
             (None, Some(r)) => {
@@ -151,13 +1042,16 @@ pub fn diff_sessions(left: &ReplaySession, right: &ReplaySession) -> DiffSummary
                     request_index: i,
                     method: r.method.clone(),
                     url: r.url.clone(),
-                    status_diff: Some(StatusDiff {
-                        left: 0,
-                        right: r.status,
-                    }),
+                    status_diff: Some(StatusDiff::new(0, r.status)),
                     header_diffs: vec![],
+                    trailer_diffs: vec![],
                     body_diff: None,
                     waf_diff: None,
+                    tech_stack_diff: None,
+                    latency_diff: None,
+                    size_diff: None,
+                    version_diff: None,
+                    tls_diff: None,
                 });
             }
             (None, None) => {
@@ -166,37 +1060,154 @@ pub fn diff_sessions(left: &ReplaySession, right: &ReplaySession) -> DiffSummary
         }
     }
 
+    let stopped_early = compared < pairs.len();
+    // Unmatched results count as differences, unless --fail-fast already stopped
+    let (unmatched_left, unmatched_right): (Vec<_>, Vec<_>) = if stopped_early {
+        (vec![], vec![])
+    } else {
+        (
+            unmatched_left.into_iter().map(unmatched).collect(),
+            unmatched_right.into_iter().map(unmatched).collect(),
+        )
+    };
+    let unmatched_count = unmatched_left.len() + unmatched_right.len();
+
     DiffSummary {
-        left_target: left.target.clone(),
-        right_target: right.target.clone(),
-        total_requests: max_len,
+        left_target: left_target.to_string(),
+        right_target: right_target.to_string(),
+        total_requests: compared + unmatched_count,
         identical,
-        different,
+        different: different + unmatched_count,
         status_diffs: status_diffs_count,
         header_diffs: header_diffs_count,
+        trailer_diffs: trailer_diffs_count,
         body_diffs: body_diffs_count,
         waf_diffs: waf_diffs_count,
+        latency_diffs: latency_diffs_count,
+        size_diffs: size_diffs_count,
+        version_diffs: version_diffs_count,
+        tls_diffs: tls_diffs_count,
+        tech_stack_changes: summarize_tech_changes(&diffs),
         diffs,
+        stopped_early,
+        header_scope,
+        unmatched_left,
+        unmatched_right,
+        identical_indices,
+        ignored,
     }
 }
 
+/// Group per-request technology changes into distinct before/after pairs
+fn summarize_tech_changes(diffs: &[RequestDiff]) -> Vec<TechStackChange> {
+    let mut grouped: std::collections::BTreeMap<(TechChangeKind, &TechStack, &TechStack), usize> =
+        std::collections::BTreeMap::new();
+    for tech in diffs.iter().filter_map(|d| d.tech_stack_diff.as_ref()) {
+        *grouped
+            .entry((tech.kind, &tech.left, &tech.right))
+            .or_default() += 1;
+    }
+
+    let mut changes: Vec<TechStackChange> = grouped
+        .into_iter()
+        .map(|((kind, left, right), requests)| TechStackChange {
+            kind,
+            left: left.clone(),
+            right: right.clone(),
+            requests,
+        })
+        .collect();
+    // Stable sort keeps backend changes ahead of version changes on ties
+    changes.sort_by_key(|c| std::cmp::Reverse(c.requests));
+    changes
+}
+
 /// Compare two individual replay results
 pub fn diff_results(left: &ReplayResult, right: &ReplayResult) -> Option<RequestDiff> {
+    let headers = DiffOptions::default().compared_headers(None);
+    diff_results_with_headers(
+        left,
+        right,
+        &headers,
+        &WafRules::default(),
+        None,
+        &SizeThreshold::default(),
+    )
+}
+
+/// Compare two results, comparing only the given (lowercase) headers
+fn diff_results_with_headers(
+    left: &ReplayResult,
+    right: &ReplayResult,
+    compare_headers: &[String],
+    waf: &WafRules,
+    latency: Option<&LatencyThreshold>,
+    size: &SizeThreshold,
+) -> Option<RequestDiff> {
     let status_diff = if left.status != right.status {
-        Some(StatusDiff {
-            left: left.status,
-            right: right.status,
-        })
+        Some(StatusDiff::new(left.status, right.status))
     } else {
         None
     };
 
-    let header_diffs = diff_headers(&left.headers, &right.headers);
+    let header_diffs = diff_headers(&left.headers, &right.headers, compare_headers);
+    let trailer_diffs = diff_trailers(&left.trailers, &right.trailers);
     let body_diff = diff_bodies(left, right);
-    let waf_diff = detect_waf_diff(left, right);
+    let waf_diff = detect_waf_diff(left, right, waf);
+    let tech_stack_diff = diff_tech_stack(&left.headers, &right.headers, compare_headers);
+    // Timings of failed requests say nothing about the target
+    let latency_diff = latency
+        .filter(|_| left.error.is_none() && right.error.is_none())
+        .filter(|threshold| threshold.exceeded(left.duration_ms, right.duration_ms))
+        .map(|_| LatencyDiff {
+            left_ms: left.duration_ms,
+            right_ms: right.duration_ms,
+            delta_ms: right.duration_ms - left.duration_ms,
+        });
+    // A failed request has no body to measure, and captured bodies that
+    // compared equal (e.g. reformatted JSON) aren't a size regression
+    let bodies_equal = body_diff.is_none() && left.body.is_some() && right.body.is_some();
+    let size_diff = Some(size)
+        .filter(|_| left.error.is_none() && right.error.is_none() && !bodies_equal)
+        .filter(|threshold| threshold.exceeded(left.body_size, right.body_size))
+        .map(|_| SizeDiff {
+            left_bytes: left.body_size,
+            right_bytes: right.body_size,
+            delta_bytes: right.body_size as i64 - left.body_size as i64,
+        });
+
+    // Sessions recorded before versions were tracked have none to compare
+    let version_diff = match (&left.http_version, &right.http_version) {
+        (Some(l), Some(r)) if l != r => Some(VersionDiff {
+            left: l.clone(),
+            right: r.clone(),
+        }),
+        _ => None,
+    };
+
+    // Only comparable when both sessions were recorded with --tls-info
+    let tls_diff = match (&left.probe_tls_version, &right.probe_tls_version) {
+        (Some(lv), Some(rv)) if lv != rv || left.probe_tls_cipher != right.probe_tls_cipher => {
+            Some(TlsDiff {
+                left_version: lv.clone(),
+                right_version: rv.clone(),
+                left_cipher: left.probe_tls_cipher.clone(),
+                right_cipher: right.probe_tls_cipher.clone(),
+            })
+        }
+        _ => None,
+    };
 
     // Only return a diff if there are actual differences
-    if status_diff.is_none() && header_diffs.is_empty() && body_diff.is_none() && waf_diff.is_none()
+    if status_diff.is_none()
+        && header_diffs.is_empty()
+        && trailer_diffs.is_empty()
+        && body_diff.is_none()
+        && waf_diff.is_none()
+        && latency_diff.is_none()
+        && size_diff.is_none()
+        && version_diff.is_none()
+        && tls_diff.is_none()
     {
         return None;
     }
@@ -207,11 +1218,37 @@ pub fn diff_results(left: &ReplayResult, right: &ReplayResult) -> Option<Request
         url: left.url.clone(),
         status_diff,
         header_diffs,
+        trailer_diffs,
         body_diff,
         waf_diff,
+        tech_stack_diff,
+        latency_diff,
+        size_diff,
+        version_diff,
+        tls_diff,
     })
 }
 
+/// Classify a change in backend technology headers
+fn diff_tech_stack(
+    left: &[(String, String)],
+    right: &[(String, String)],
+    compare_headers: &[String],
+) -> Option<TechStackDiff> {
+    let left = TechStack::from_headers(left, compare_headers);
+    let right = TechStack::from_headers(right, compare_headers);
+    if left == right {
+        return None;
+    }
+
+    let kind = if left.products() == right.products() {
+        TechChangeKind::VersionChanged
+    } else {
+        TechChangeKind::BackendChanged
+    };
+    Some(TechStackDiff { kind, left, right })
+}
+
 /// Maximum unified diff output size (8 KB)
 const MAX_DIFF_OUTPUT: usize = 8 * 1024;
 
@@ -232,14 +1269,32 @@ fn diff_bodies(left: &ReplayResult, right: &ReplayResult) -> Option<BodyDiff> {
         return None;
     }
 
+    // JSON on both sides: compare values, so key order and whitespace don't count
+    let json_changes = match (json_body(left), json_body(right)) {
+        (Some(l), Some(r)) => {
+            let changes = diff_json(&l, &r);
+            if changes.is_empty() {
+                return None;
+            }
+            changes
+        }
+        _ => vec![],
+    };
+
     // Only diff if at least one side has a body
     if left.body.is_none() && right.body.is_none() {
         // Hashes differ but no body text — report size-only diff
         if left.body_hash != right.body_hash && (left.body_size > 0 || right.body_size > 0) {
+            let note = if left.body_base64.is_some() || right.body_base64.is_some() {
+                "(binary body; hashes differ)"
+            } else {
+                "(body content not captured; hashes differ)"
+            };
             return Some(BodyDiff {
                 left_size: left.body_size,
                 right_size: right.body_size,
-                unified_diff: "(body content not captured; hashes differ)".to_string(),
+                unified_diff: note.to_string(),
+                json_changes: vec![],
             });
         }
         return None;
@@ -269,14 +1324,107 @@ fn diff_bodies(left: &ReplayResult, right: &ReplayResult) -> Option<BodyDiff> {
         left_size: left.body_size,
         right_size: right.body_size,
         unified_diff: unified,
+        json_changes,
     })
 }
 
+/// Parsed body of a response declared as JSON (`application/json`, `*+json`)
+fn json_body(result: &ReplayResult) -> Option<serde_json::Value> {
+    let content_type = find_header(&result.headers, "content-type")?;
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    if mime != "application/json" && !mime.ends_with("+json") {
+        return None;
+    }
+    serde_json::from_str(result.body.as_deref()?).ok()
+}
+
+/// Paths at which two JSON documents differ
+pub fn diff_json(left: &serde_json::Value, right: &serde_json::Value) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    diff_json_at("$", left, right, &mut changes);
+    changes
+}
+
+fn diff_json_at(
+    path: &str,
+    left: &serde_json::Value,
+    right: &serde_json::Value,
+    changes: &mut Vec<JsonChange>,
+) {
+    use serde_json::Value;
+
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            for (key, lv) in l {
+                let child = json_key_path(path, key);
+                match r.get(key) {
+                    Some(rv) => diff_json_at(&child, lv, rv, changes),
+                    None => changes.push(JsonChange {
+                        path: child,
+                        kind: JsonChangeKind::Removed,
+                        left: Some(lv.clone()),
+                        right: None,
+                    }),
+                }
+            }
+            for (key, rv) in r.iter().filter(|(k, _)| !l.contains_key(*k)) {
+                changes.push(JsonChange {
+                    path: json_key_path(path, key),
+                    kind: JsonChangeKind::Added,
+                    left: None,
+                    right: Some(rv.clone()),
+                });
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (l.get(i), r.get(i)) {
+                    (Some(lv), Some(rv)) => diff_json_at(&child, lv, rv, changes),
+                    (lv, rv) => changes.push(JsonChange {
+                        path: child,
+                        kind: if lv.is_some() {
+                            JsonChangeKind::Removed
+                        } else {
+                            JsonChangeKind::Added
+                        },
+                        left: lv.cloned(),
+                        right: rv.cloned(),
+                    }),
+                }
+            }
+        }
+        (l, r) if l != r => changes.push(JsonChange {
+            path: path.to_string(),
+            kind: JsonChangeKind::Changed,
+            left: Some(l.clone()),
+            right: Some(r.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// `$.key` for identifier-like keys, `$["odd key"]` otherwise
+fn json_key_path(parent: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}[{}]", parent, serde_json::Value::from(key))
+    }
+}
+
 /// Compare headers between two responses
-fn diff_headers(left: &[(String, String)], right: &[(String, String)]) -> Vec<HeaderDiff> {
+fn diff_headers(
+    left: &[(String, String)],
+    right: &[(String, String)],
+    compare_headers: &[String],
+) -> Vec<HeaderDiff> {
     let mut diffs = Vec::new();
 
-    for header_name in COMPARE_HEADERS {
+    for header_name in compare_headers {
         let left_value = find_header(left, header_name);
         let right_value = find_header(right, header_name);
 
@@ -312,6 +1460,38 @@ fn diff_headers(left: &[(String, String)], right: &[(String, String)]) -> Vec<He
     diffs
 }
 
+/// Compare response trailers. Unlike headers every trailer is compared:
+/// servers send few, and each one (e.g. `grpc-status`) carries meaning.
+fn diff_trailers(left: &[(String, String)], right: &[(String, String)]) -> Vec<HeaderDiff> {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in left.iter().chain(right) {
+        let name = name.to_lowercase();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let l = find_header(left, &name);
+            let r = find_header(right, &name);
+            let diff_type = match (&l, &r) {
+                (Some(a), Some(b)) if a != b => HeaderDiffType::Changed,
+                (Some(_), None) => HeaderDiffType::Removed,
+                (None, Some(_)) => HeaderDiffType::Added,
+                _ => return None,
+            };
+            Some(HeaderDiff {
+                name,
+                left: l,
+                right: r,
+                diff_type,
+            })
+        })
+        .collect()
+}
+
 /// Find a header value by name (case-insensitive)
 fn find_header(headers: &[(String, String)], name: &str) -> Option<String> {
     headers
@@ -320,10 +1500,11 @@ fn find_header(headers: &[(String, String)], name: &str) -> Option<String> {
         .map(|(_, v)| v.clone())
 }
 
-/// Detect WAF-related differences based on status codes and headers
-fn detect_waf_diff(left: &ReplayResult, right: &ReplayResult) -> Option<WafDiff> {
-    let left_blocked = is_waf_block(left);
-    let right_blocked = is_waf_block(right);
+/// Detect WAF-related differences based on status codes, headers, and
+/// block page bodies
+fn detect_waf_diff(left: &ReplayResult, right: &ReplayResult, waf: &WafRules) -> Option<WafDiff> {
+    let left_blocked = is_waf_block(left, waf);
+    let right_blocked = is_waf_block(right, waf);
 
     // Only report if blocking status differs
     if left_blocked == right_blocked {
@@ -333,8 +1514,8 @@ fn detect_waf_diff(left: &ReplayResult, right: &ReplayResult) -> Option<WafDiff>
     Some(WafDiff {
         left_blocked,
         right_blocked,
-        left_reason: get_waf_reason(left),
-        right_reason: get_waf_reason(right),
+        left_reason: get_waf_reason(left, waf),
+        right_reason: get_waf_reason(right, waf),
     })
 }
 
@@ -370,10 +1551,20 @@ const WAF_BODY_PATTERNS: &[&str] = &[
     "barracuda networks",
 ];
 
+/// Indices (into the replayed capture) of requests a WAF blocked
+pub fn waf_blocked_indices(session: &ReplaySession, waf: &WafRules) -> Vec<usize> {
+    session
+        .results
+        .iter()
+        .filter(|r| r.error.is_none() && is_waf_block(r, waf))
+        .map(|r| r.request_index)
+        .collect()
+}
+
 /// Check if a response indicates a WAF block
-fn is_waf_block(result: &ReplayResult) -> bool {
-    // Status codes that typically indicate blocking
-    if matches!(result.status, 403 | 429 | 503) {
+fn is_waf_block(result: &ReplayResult, waf: &WafRules) -> bool {
+    // Status codes that indicate blocking
+    if waf.is_block_status(result.status) {
         return true;
     }
 
@@ -390,20 +1581,14 @@ fn is_waf_block(result: &ReplayResult) -> bool {
     }
 
     // Check response body for WAF block page patterns
-    if let Some(ref body) = result.body {
-        let body_lower = body.to_lowercase();
-        for pattern in WAF_BODY_PATTERNS {
-            if body_lower.contains(pattern) {
-                return true;
-            }
-        }
-    }
-
-    false
+    result
+        .body
+        .as_deref()
+        .is_some_and(|body| waf.body_match(body).is_some())
 }
 
 /// Extract WAF reason from headers or body
-fn get_waf_reason(result: &ReplayResult) -> Option<String> {
+fn get_waf_reason(result: &ReplayResult, waf: &WafRules) -> Option<String> {
     // Try common WAF reason headers
     let reason_headers = ["x-waf-rule", "x-waf-action", "x-blocked-by", "x-blocked"];
 
@@ -414,21 +1599,70 @@ fn get_waf_reason(result: &ReplayResult) -> Option<String> {
     }
 
     // Fall back to status code
-    if matches!(result.status, 403 | 429 | 503) {
+    if waf.is_block_status(result.status) {
         return Some(format!("HTTP {}", result.status));
     }
 
     // Check body for WAF signatures
-    if let Some(ref body) = result.body {
-        let body_lower = body.to_lowercase();
-        for pattern in WAF_BODY_PATTERNS {
-            if body_lower.contains(pattern) {
-                return Some(format!("body match: {}", pattern));
-            }
-        }
-    }
+    result.body.as_deref().and_then(|body| waf.body_match(body))
+}
+
+/// Compression ratios differing by more than this (absolute) are reported
+pub const COMPRESSION_RATIO_TOLERANCE: f64 = 0.1;
+
+/// A target whose overall compression ratio differs from the baseline's
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompressionDrift {
+    pub baseline_target: String,
+    pub target: String,
+    pub baseline_ratio: Option<f64>,
+    pub ratio: Option<f64>,
+    /// Requests whose own ratio also moved beyond the tolerance
+    pub request_indices: Vec<usize>,
+}
+
+/// Compression results for all targets, compared against the first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompressionComparison {
+    pub targets: Vec<CompressionReport>,
+    pub drifts: Vec<CompressionDrift>,
+}
+
+/// Compare every target's compression ratio with the first target's.
+///
+/// A target drifts when its overall ratio differs by more than `tolerance`,
+/// or when one side compressed nothing at all.
+pub fn compare_compression(
+    targets: Vec<CompressionReport>,
+    tolerance: f64,
+) -> CompressionComparison {
+    let differs = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => (a - b).abs() > tolerance,
+        (a, b) => a.is_some() != b.is_some(),
+    };
+
+    let drifts = match targets.split_first() {
+        Some((baseline, rest)) => rest
+            .iter()
+            .filter(|report| differs(baseline.ratio, report.ratio))
+            .map(|report| CompressionDrift {
+                baseline_target: baseline.target.clone(),
+                target: report.target.clone(),
+                baseline_ratio: baseline.ratio,
+                ratio: report.ratio,
+                request_indices: baseline
+                    .requests
+                    .iter()
+                    .zip(&report.requests)
+                    .filter(|(l, r)| differs(l.ratio, r.ratio))
+                    .map(|(l, _)| l.request_index)
+                    .collect(),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
 
-    None
+    CompressionComparison { targets, drifts }
 }
 
 #[cfg(test)]
@@ -455,13 +1689,31 @@ mod tests {
                 .map(|(k, v)| (k.to_string(), v.to_string()))
                 .collect(),
             body: body.map(|s| s.to_string()),
+            body_base64: None,
             body_hash: None,
             body_size: body.map(|s| s.len()).unwrap_or(0),
             duration_ms: 100,
+            start_offset_ms: 0,
             expected_status: Some(200),
             status_match: status == 200,
             error: None,
             error_kind: None,
+            truncated_stream: false,
+            body_truncated: false,
+            http_version: None,
+            probe_tls_version: None,
+            probe_tls_cipher: None,
+            target: None,
+            faults: vec![],
+            trailers: vec![],
+            content_encoding: None,
+            attempts: 1,
+            repeat_index: None,
+            timing: None,
+            accept: None,
+            name: None,
+            peer_addr: None,
+            geo: None,
         }
     }
 
@@ -482,12 +1734,434 @@ mod tests {
         assert_eq!(diff.status_diff.as_ref().unwrap().right, 403);
     }
 
+    #[test]
+    fn test_fail_fast_stops_at_first_diff() {
+        let left: Vec<_> = (0..5).map(|i| make_result(i, 200, vec![])).collect();
+        let right: Vec<_> = (0..5)
+            .map(|i| make_result(i, if i == 1 || i == 3 { 500 } else { 200 }, vec![]))
+            .collect();
+
+        let full = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(full.different, 2);
+        assert!(!full.stopped_early);
+
+        let options = DiffOptions {
+            fail_fast: true,
+            ..Default::default()
+        };
+        let fast = diff_result_sets("l", "r", &left, &right, &options, None);
+        assert_eq!(fast.diffs.len(), 1);
+        assert_eq!(fast.diffs[0].request_index, 1);
+        assert_eq!(fast.total_requests, 2);
+        assert_eq!(fast.identical, 1);
+        assert!(fast.stopped_early);
+    }
+
+    #[test]
+    fn test_match_key_ignores_host_and_query_order() {
+        assert_eq!(
+            match_key(
+                "get",
+                "https://staging.example.com:8443/api/items?b=2&a=1",
+                &[]
+            ),
+            match_key("GET", "https://prod.example.com/api/items?a=1&b=2", &[])
+        );
+        assert_ne!(
+            match_key("GET", "https://example.com/api/items", &[]),
+            match_key("POST", "https://example.com/api/items", &[])
+        );
+        // Cache busters only line up once dropped
+        let drop = ["_ts".to_string()];
+        assert_ne!(
+            match_key("GET", "https://example.com/a?_ts=1&b=2", &[]),
+            match_key("GET", "https://example.com/a?b=2&_ts=7", &[])
+        );
+        assert_eq!(
+            match_key("GET", "https://example.com/a?_ts=1&b=2", &drop),
+            match_key("GET", "https://example.com/a?b=2&_ts=7", &drop)
+        );
+    }
+
+    #[test]
+    fn test_match_by_url_pairs_reordered_results() {
+        let at = |index: usize, status: u16, url: &str| {
+            let mut r = make_result(index, status, vec![]);
+            r.url = url.to_string();
+            r
+        };
+        let left = vec![
+            at(0, 200, "https://a.example.com/one"),
+            at(1, 200, "https://a.example.com/two"),
+            at(2, 200, "https://a.example.com/gone"),
+        ];
+        let right = vec![
+            at(0, 500, "https://b.example.com/two"),
+            at(1, 200, "https://b.example.com/one"),
+            at(2, 200, "https://b.example.com/new"),
+        ];
+        let options = DiffOptions {
+            match_by: MatchBy::Url,
+            ..Default::default()
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &options, None);
+
+        assert_eq!(summary.identical, 1);
+        assert_eq!(summary.diffs.len(), 1);
+        assert_eq!(summary.diffs[0].url, "https://a.example.com/two");
+        assert_eq!(summary.unmatched_left[0].url, "https://a.example.com/gone");
+        assert_eq!(summary.unmatched_right[0].url, "https://b.example.com/new");
+        assert_eq!(summary.different, 3);
+        assert_eq!(summary.total_requests, 4);
+
+        // By index, /one is compared with /two and the added/removed pair looks identical
+        let by_index = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(by_index.different, 1);
+        assert_eq!(by_index.diffs[0].url, "https://a.example.com/one");
+        assert!(by_index.unmatched_left.is_empty());
+    }
+
+    #[test]
+    fn test_index_pairing_uses_request_index() {
+        // A filtered run kept requests 1 and 3 of a four-request capture
+        let full: Vec<_> = (0..4).map(|i| make_result(i, 200, vec![])).collect();
+        let filtered = vec![make_result(1, 200, vec![]), make_result(3, 500, vec![])];
+
+        let summary = diff_result_sets("l", "r", &full, &filtered, &DiffOptions::default(), None);
+        assert_eq!(summary.identical_indices, vec![1]);
+        let indices: Vec<_> = summary.diffs.iter().map(|d| d.request_index).collect();
+        assert_eq!(indices, vec![0, 2, 3]);
+        assert_eq!(summary.diffs[2].status_diff.as_ref().unwrap().right, 500);
+    }
+
+    #[test]
+    fn test_multi_diff_finds_odd_one_out() {
+        let a: Vec<_> = (0..3).map(|i| make_result(i, 200, vec![])).collect();
+        let mut b = a.clone();
+        let mut c = a.clone();
+        // Request 1: c alone is blocked; request 2: all three disagree
+        c[1].status = 403;
+        b[2].status = 404;
+        c[2].status = 500;
+
+        let targets = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let summary = diff_result_sets_multi(targets, &[&a, &b, &c]);
+        assert_eq!((summary.agreeing, summary.diverging), (1, 2));
+        assert!(summary.rows[0].agree);
+        assert_eq!(
+            summary.rows[1].statuses,
+            vec![Some(200), Some(200), Some(403)]
+        );
+        assert_eq!(summary.rows[1].odd_one_out, Some(2));
+        assert_eq!(summary.rows[2].odd_one_out, None);
+        assert_eq!(summary.odd_one_out_counts, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_multi_diff_missing_result_diverges() {
+        let a: Vec<_> = (0..2).map(|i| make_result(i, 200, vec![])).collect();
+        let b = vec![make_result(0, 200, vec![])];
+        let c = a.clone();
+
+        let targets = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let summary = diff_result_sets_multi(targets, &[&a, &b, &c]);
+        assert_eq!(summary.rows[1].statuses, vec![Some(200), None, Some(200)]);
+        assert_eq!(summary.rows[1].odd_one_out, Some(1));
+    }
+
+    #[test]
+    fn test_compare_and_ignore_headers() {
+        let left = vec![make_result(
+            0,
+            200,
+            vec![("cf-ray", "1"), ("X-Tenant-Id", "a"), ("server", "nginx")],
+        )];
+        let right = vec![make_result(
+            0,
+            200,
+            vec![("cf-ray", "2"), ("x-tenant-id", "b"), ("server", "nginx")],
+        )];
+        let names = |s: &DiffSummary| -> Vec<String> {
+            s.diffs
+                .iter()
+                .flat_map(|d| d.header_diffs.iter().map(|h| h.name.clone()))
+                .collect()
+        };
+
+        let defaults = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(names(&defaults), vec!["cf-ray"]);
+
+        let ignored = DiffOptions {
+            ignore_headers: vec!["CF-Ray".to_string()],
+            ..Default::default()
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &ignored, None);
+        assert!(summary.diffs.is_empty());
+
+        // An explicit list replaces the defaults entirely
+        let explicit = DiffOptions {
+            compare_headers: vec!["X-Tenant-Id".to_string()],
+            ..Default::default()
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &explicit, None);
+        assert_eq!(names(&summary), vec!["x-tenant-id"]);
+    }
+
+    #[test]
+    fn test_header_scope_intersection() {
+        let all: Option<&[String]> = None;
+        let a = vec!["server".to_string(), "x-cache".to_string()];
+        let b = vec!["x-cache".to_string(), "cf-ray".to_string()];
+
+        assert_eq!(header_scope(all, all), None);
+        assert_eq!(header_scope(Some(&a), all), Some(a.clone()));
+        assert_eq!(
+            header_scope(Some(&a), Some(&b)),
+            Some(vec!["x-cache".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_header_scope_restricts_compared_headers() {
+        // Left recorded everything, right only kept x-cache
+        let left = vec![make_result(
+            0,
+            200,
+            vec![("server", "nginx"), ("x-cache", "HIT")],
+        )];
+        let right = vec![make_result(0, 200, vec![("x-cache", "MISS")])];
+
+        let unscoped = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(unscoped.diffs[0].header_diffs.len(), 2);
+
+        let scope = header_scope(None, Some(&["x-cache".to_string()]));
+        let scoped = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), scope);
+        let header_diffs = &scoped.diffs[0].header_diffs;
+        assert_eq!(header_diffs.len(), 1);
+        assert_eq!(header_diffs[0].name, "x-cache");
+        assert_eq!(scoped.header_scope, Some(vec!["x-cache".to_string()]));
+    }
+
     #[test]
     fn test_waf_block_detection() {
         let blocked = make_result(0, 403, vec![("x-waf-rule", "942100")]);
         let allowed = make_result(0, 200, vec![]);
-        assert!(is_waf_block(&blocked));
-        assert!(!is_waf_block(&allowed));
+        assert!(is_waf_block(&blocked, &WafRules::default()));
+        assert!(!is_waf_block(&allowed, &WafRules::default()));
+    }
+
+    #[test]
+    fn test_status_severity_by_class_transition() {
+        let cases = [
+            (200, 204, StatusSeverity::Cosmetic, "success→success"),
+            (302, 200, StatusSeverity::Notable, "redirect→success"),
+            (500, 200, StatusSeverity::Notable, "server_error→success"),
+            (200, 403, StatusSeverity::Major, "success→client_error"),
+            (200, 502, StatusSeverity::Critical, "success→server_error"),
+            (200, 0, StatusSeverity::Critical, "success→no_response"),
+        ];
+        for (left, right, severity, transition) in cases {
+            let diff = StatusDiff::new(left, right);
+            assert_eq!(diff.severity, severity, "{} → {}", left, right);
+            assert_eq!(diff.transition(), transition);
+        }
+        assert!(StatusDiff::new(200, 503).is_new_server_error());
+        assert!(!StatusDiff::new(500, 503).is_new_server_error());
+        assert!(!StatusDiff::new(200, 0).is_new_server_error());
+    }
+
+    #[test]
+    fn test_http_version_diff() {
+        let versioned = |version: Option<&str>| ReplayResult {
+            http_version: version.map(String::from),
+            ..make_result(0, 200, vec![])
+        };
+        let diff = diff_results(&versioned(Some("HTTP/1.1")), &versioned(Some("HTTP/2")))
+            .unwrap()
+            .version_diff
+            .unwrap();
+        assert_eq!(
+            (diff.left.as_str(), diff.right.as_str()),
+            ("HTTP/1.1", "HTTP/2")
+        );
+
+        // Older sessions without versions compare as before
+        assert!(diff_results(&versioned(None), &versioned(Some("HTTP/2"))).is_none());
+        assert!(diff_results(&versioned(Some("HTTP/2")), &versioned(Some("HTTP/2"))).is_none());
+    }
+
+    #[test]
+    fn test_tls_diff() {
+        let tls = |version: Option<&str>, cipher: &str| ReplayResult {
+            probe_tls_version: version.map(String::from),
+            probe_tls_cipher: version.map(|_| cipher.to_string()),
+            ..make_result(0, 200, vec![])
+        };
+        let modern = tls(Some("TLS 1.3"), "TLS13_AES_256_GCM_SHA384");
+        let legacy = tls(Some("TLS 1.2"), "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256");
+        let diff = diff_results(&legacy, &modern).unwrap().tls_diff.unwrap();
+        assert_eq!(
+            (diff.left_version.as_str(), diff.right_version.as_str()),
+            ("TLS 1.2", "TLS 1.3")
+        );
+
+        // A cipher change alone counts too
+        let other_cipher = tls(Some("TLS 1.3"), "TLS13_CHACHA20_POLY1305_SHA256");
+        assert!(diff_results(&modern, &other_cipher)
+            .unwrap()
+            .tls_diff
+            .is_some());
+        assert!(diff_results(&modern, &tls(None, "")).is_none());
+    }
+
+    #[test]
+    fn test_size_threshold() {
+        let sized = |sizes: &[usize]| -> Vec<ReplayResult> {
+            sizes
+                .iter()
+                .enumerate()
+                .map(|(i, &body_size)| ReplayResult {
+                    body_size,
+                    ..make_result(i, 200, vec![])
+                })
+                .collect()
+        };
+        let left = sized(&[1000, 1000, 1000]);
+        let right = sized(&[1000, 1040, 400]);
+
+        let summary = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(summary.size_diffs, 2);
+        let size = summary.diffs[1].size_diff.as_ref().unwrap();
+        assert_eq!(
+            (size.left_bytes, size.right_bytes, size.delta_bytes),
+            (1000, 400, -600)
+        );
+
+        let options = DiffOptions {
+            size: SizeThreshold::parse("10%").unwrap(),
+            ..Default::default()
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &options, None);
+        assert_eq!(summary.size_diffs, 1);
+        assert_eq!(summary.diffs[0].request_index, 2);
+
+        assert_eq!(
+            SizeThreshold::parse("512").unwrap(),
+            SizeThreshold::Bytes(512)
+        );
+        assert!(SizeThreshold::Bytes(50).exceeded(1000, 1051));
+        assert!(!SizeThreshold::Bytes(50).exceeded(1000, 950));
+        assert!(SizeThreshold::parse("-5%").is_err());
+        assert!(SizeThreshold::parse("big").is_err());
+    }
+
+    #[test]
+    fn test_ignore_urls_excluded_from_pairing_and_counts() {
+        let at = |i: usize, status: u16, url: &str| ReplayResult {
+            url: url.to_string(),
+            ..make_result(i, status, vec![])
+        };
+        let left = vec![
+            at(0, 200, "https://a.example/api/users"),
+            at(1, 200, "https://a.example/telemetry?t=1"),
+            at(2, 200, "https://a.example/health"),
+            at(3, 200, "https://a.example/beacon"),
+        ];
+        let right = vec![
+            at(0, 200, "https://b.example/api/users"),
+            at(1, 500, "https://b.example/telemetry?t=2"),
+            at(2, 503, "https://b.example/health"),
+        ];
+        let options = DiffOptions {
+            ignore_urls: vec![
+                "/telemetry*".to_string(),
+                "https://a.example/health".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        let summary = diff_result_sets("l", "r", &left, &right, &options, None);
+        // The health pair goes even though only the left URL matches
+        assert_eq!(summary.ignored, 2);
+        assert_eq!(summary.total_requests, 2);
+        assert_eq!((summary.identical, summary.different), (1, 1));
+        assert_eq!(summary.diffs[0].request_index, 3);
+
+        let options = DiffOptions {
+            match_by: MatchBy::Url,
+            ignore_urls: vec!["/beacon".to_string(), "/telemetry*".to_string()],
+            ..options
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &options, None);
+        // The telemetry queries differ, so its two sides never paired and count apart
+        assert_eq!(summary.ignored, 3);
+        assert_eq!(summary.total_requests, 2);
+        assert!(summary.unmatched_left.is_empty() && summary.unmatched_right.is_empty());
+        assert_eq!(summary.status_diffs, 1);
+    }
+
+    #[test]
+    fn test_latency_threshold_tolerates_noise() {
+        let timed = |durations: &[u64]| -> Vec<ReplayResult> {
+            durations
+                .iter()
+                .enumerate()
+                .map(|(i, &ms)| ReplayResult {
+                    duration_ms: ms,
+                    ..make_result(i, 200, vec![])
+                })
+                .collect()
+        };
+        let left = timed(&[100, 100, 10, 100]);
+        let right = timed(&[105, 400, 40, 90]);
+
+        // Timing is ignored unless a threshold is given
+        let summary = diff_result_sets("l", "r", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(summary.different, 0);
+
+        let options = DiffOptions {
+            latency: Some(LatencyThreshold {
+                ms: Some(50),
+                pct: None,
+            }),
+            ..Default::default()
+        };
+        let summary = diff_result_sets("l", "r", &left, &right, &options, None);
+        assert_eq!(summary.latency_diffs, 1);
+        let latency = summary.diffs[0].latency_diff.as_ref().unwrap();
+        assert_eq!(summary.diffs[0].request_index, 1);
+        assert_eq!(
+            (latency.left_ms, latency.right_ms, latency.delta_ms),
+            (100, 400, 300)
+        );
+
+        // The percentage alone flags the fast request; with both, it must clear both
+        let pct = LatencyThreshold {
+            ms: None,
+            pct: Some(100.0),
+        };
+        assert!(pct.exceeded(10, 40));
+        assert!(!LatencyThreshold {
+            ms: Some(50),
+            ..pct
+        }
+        .exceeded(10, 40));
+        assert!(!pct.exceeded(100, 90));
+    }
+
+    #[test]
+    fn test_waf_status_overrides_defaults() {
+        let waf = WafRules::default().with_statuses(&[403, 406, 429]);
+        let outage = make_result(0, 503, vec![]);
+        let not_acceptable = make_result(0, 406, vec![]);
+        assert!(is_waf_block(&outage, &WafRules::default()));
+        assert!(!is_waf_block(&outage, &waf));
+        assert!(is_waf_block(&not_acceptable, &waf));
+        assert_eq!(
+            get_waf_reason(&not_acceptable, &waf).as_deref(),
+            Some("HTTP 406")
+        );
+        assert!(detect_waf_diff(&outage, &not_acceptable, &waf).is_some());
     }
 
     #[test]
@@ -519,6 +2193,55 @@ mod tests {
         assert!(body.unified_diff.contains('+'));
     }
 
+    #[test]
+    fn test_json_body_diff_reports_paths() {
+        let json = vec![("content-type", "application/json; charset=utf-8")];
+        let left = make_result_with_body(
+            0,
+            200,
+            json.clone(),
+            Some(r#"{"data":{"items":[{"price":10},{"price":20}]},"legacy":1}"#),
+        );
+        let right = make_result_with_body(
+            0,
+            200,
+            json,
+            Some(r#"{"data":{"items":[{"price":10},{"price":25},{"price":5}]},"odd key":true}"#),
+        );
+        let body = diff_results(&left, &right).unwrap().body_diff.unwrap();
+        let paths: Vec<(&str, JsonChangeKind)> = body
+            .json_changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("$.data.items[1].price", JsonChangeKind::Changed),
+                ("$.data.items[2]", JsonChangeKind::Added),
+                ("$.legacy", JsonChangeKind::Removed),
+                ("$[\"odd key\"]", JsonChangeKind::Added),
+            ]
+        );
+        assert_eq!(body.json_changes[0].left, Some(serde_json::json!(20)));
+        assert_eq!(body.json_changes[0].right, Some(serde_json::json!(25)));
+    }
+
+    #[test]
+    fn test_json_body_diff_ignores_formatting() {
+        let json = vec![("content-type", "application/json")];
+        let left = make_result_with_body(0, 200, json.clone(), Some(r#"{"a":1,"b":[1,2]}"#));
+        let right = make_result_with_body(0, 200, json, Some("{\n  \"b\": [1, 2],\n  \"a\": 1\n}"));
+        assert!(diff_results(&left, &right).is_none());
+
+        // Without a JSON content type the bytes are compared
+        let left = make_result_with_body(0, 200, vec![], Some(r#"{"a":1}"#));
+        let right = make_result_with_body(0, 200, vec![], Some(r#"{ "a": 1 }"#));
+        let body = diff_results(&left, &right).unwrap().body_diff.unwrap();
+        assert!(body.json_changes.is_empty());
+        assert_eq!((body.left_size, body.right_size), (7, 10));
+    }
+
     #[test]
     fn test_body_diff_one_missing() {
         let left = make_result_with_body(0, 200, vec![], Some("hello"));
@@ -535,7 +2258,7 @@ mod tests {
             vec![],
             Some("<html>Attention Required! | Cloudflare</html>"),
         );
-        assert!(is_waf_block(&result));
+        assert!(is_waf_block(&result, &WafRules::default()));
     }
 
     #[test]
@@ -546,21 +2269,161 @@ mod tests {
             vec![],
             Some("<h1>Access Denied</h1><p>Your request was blocked.</p>"),
         );
-        assert!(is_waf_block(&result));
+        assert!(is_waf_block(&result, &WafRules::default()));
     }
 
     #[test]
     fn test_waf_block_body_no_false_positive() {
         let result = make_result_with_body(0, 200, vec![], Some("{\"status\":\"ok\",\"data\":[]}"));
-        assert!(!is_waf_block(&result));
+        assert!(!is_waf_block(&result, &WafRules::default()));
     }
 
     #[test]
     fn test_waf_reason_from_body() {
         let result =
             make_result_with_body(0, 200, vec![], Some("<html>Powered by Incapsula</html>"));
-        let reason = get_waf_reason(&result);
+        let reason = get_waf_reason(&result, &WafRules::default());
         assert!(reason.is_some());
         assert!(reason.unwrap().contains("incapsula"));
     }
+
+    #[test]
+    fn test_waf_signature_flags_custom_block_page() {
+        let waf = WafRules::with_signatures(&["(?i)bot check #\\d+".to_string()]).unwrap();
+        let challenge = make_result_with_body(
+            0,
+            200,
+            vec![],
+            Some("<html>Please complete the Bot Check #4711</html>"),
+        );
+        let normal = make_result_with_body(0, 200, vec![], Some("<html>Welcome</html>"));
+        assert!(!is_waf_block(&challenge, &WafRules::default()));
+        assert!(is_waf_block(&challenge, &waf));
+
+        let diff = detect_waf_diff(&normal, &challenge, &waf).unwrap();
+        assert!(!diff.left_blocked && diff.right_blocked);
+        assert_eq!(
+            diff.right_reason.as_deref(),
+            Some("body signature: (?i)bot check #\\d+")
+        );
+
+        assert!(WafRules::with_signatures(&["(unclosed".to_string()]).is_err());
+    }
+
+    fn make_compression_report(target: &str, sizes: &[(usize, usize)]) -> CompressionReport {
+        let requests: Vec<_> = sizes
+            .iter()
+            .enumerate()
+            .map(
+                |(i, &(compressed, uncompressed))| crate::replay::CompressionMeasurement {
+                    request_index: i,
+                    method: "GET".to_string(),
+                    url: format!("{}/{}", target, i),
+                    content_encoding: Some("gzip".to_string()),
+                    compressed_size: compressed,
+                    uncompressed_size: uncompressed,
+                    ratio: crate::replay::compression_ratio(compressed, uncompressed),
+                },
+            )
+            .collect();
+        let compressed: usize = sizes.iter().map(|s| s.0).sum();
+        let uncompressed: usize = sizes.iter().map(|s| s.1).sum();
+        CompressionReport {
+            target: target.to_string(),
+            total_requests: requests.len(),
+            compressed_size: compressed,
+            uncompressed_size: uncompressed,
+            ratio: crate::replay::compression_ratio(compressed, uncompressed),
+            requests,
+        }
+    }
+
+    #[test]
+    fn test_grpc_status_trailer_diff() {
+        let mut left = make_result_with_body(0, 200, vec![], None);
+        left.trailers = vec![("grpc-status".to_string(), "0".to_string())];
+        let mut right = make_result_with_body(0, 200, vec![], None);
+        right.trailers = vec![
+            ("grpc-status".to_string(), "7".to_string()),
+            ("grpc-message".to_string(), "permission denied".to_string()),
+        ];
+
+        let diff = diff_results(&left, &right).unwrap();
+        assert!(diff.header_diffs.is_empty());
+        assert_eq!(diff.trailer_diffs.len(), 2);
+        assert_eq!(diff.trailer_diffs[0].name, "grpc-status");
+        assert!(matches!(
+            diff.trailer_diffs[0].diff_type,
+            HeaderDiffType::Changed
+        ));
+        assert!(matches!(
+            diff.trailer_diffs[1].diff_type,
+            HeaderDiffType::Added
+        ));
+
+        let summary = diff_result_sets("a", "b", &[left], &[right], &DiffOptions::default(), None);
+        assert_eq!(summary.trailer_diffs, 1);
+    }
+
+    #[test]
+    fn test_server_change_classified_as_backend_change() {
+        let left = make_result_with_body(0, 200, vec![("Server", "nginx/1.24.0")], None);
+        let right = make_result_with_body(0, 200, vec![("Server", "envoy")], None);
+
+        let diff = diff_results(&left, &right).unwrap();
+        let tech = diff.tech_stack_diff.unwrap();
+        assert_eq!(tech.kind, TechChangeKind::BackendChanged);
+        assert_eq!(tech.left.server.as_deref(), Some("nginx/1.24.0"));
+        assert_eq!(tech.right.server.as_deref(), Some("envoy"));
+    }
+
+    #[test]
+    fn test_server_upgrade_classified_as_version_change() {
+        let left = make_result_with_body(
+            0,
+            200,
+            vec![("Server", "nginx/1.24.0"), ("Via", "1.1 varnish")],
+            None,
+        );
+        let right = make_result_with_body(
+            0,
+            200,
+            vec![("Server", "nginx/1.25.3"), ("Via", "1.1 varnish")],
+            None,
+        );
+
+        let tech = diff_results(&left, &right)
+            .unwrap()
+            .tech_stack_diff
+            .unwrap();
+        assert_eq!(tech.kind, TechChangeKind::VersionChanged);
+    }
+
+    #[test]
+    fn test_tech_changes_aggregated_per_session() {
+        let left: Vec<_> = (0..3)
+            .map(|i| make_result_with_body(i, 200, vec![("Server", "nginx")], None))
+            .collect();
+        let right: Vec<_> = (0..3)
+            .map(|i| make_result_with_body(i, 200, vec![("Server", "envoy")], None))
+            .collect();
+
+        let summary = diff_result_sets("a", "b", &left, &right, &DiffOptions::default(), None);
+        assert_eq!(summary.tech_stack_changes.len(), 1);
+        assert_eq!(summary.tech_stack_changes[0].requests, 3);
+        assert_eq!(summary.tech_stack_changes[0].left.describe(), "nginx");
+        assert_eq!(summary.tech_stack_changes[0].right.describe(), "envoy");
+    }
+
+    #[test]
+    fn test_compression_drift_flags_uncompressed_target() {
+        let old = make_compression_report("old", &[(300, 1000), (100, 100)]);
+        let same = make_compression_report("same", &[(320, 1000), (100, 100)]);
+        let new = make_compression_report("new", &[(1000, 1000), (100, 100)]);
+
+        let comparison = compare_compression(vec![old, same, new], COMPRESSION_RATIO_TOLERANCE);
+        assert_eq!(comparison.drifts.len(), 1);
+        assert_eq!(comparison.drifts[0].target, "new");
+        assert_eq!(comparison.drifts[0].request_indices, vec![0]);
+    }
 }
@@ -0,0 +1,155 @@
+//! Fault injection
+//!
+//! Deterministically corrupts or delays a subset of replayed requests so
+//! operators can observe how targets behave under adverse traffic.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::time::Duration;
+
+/// A fault that can be applied to a single request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Don't send the request at all
+    Drop,
+    /// Wait before sending the request
+    Delay(Duration),
+    /// Send only the first half of the request body
+    TruncateBody,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Drop => write!(f, "drop"),
+            Fault::Delay(d) => write!(f, "delay:{}ms", d.as_millis()),
+            Fault::TruncateBody => write!(f, "truncate-body"),
+        }
+    }
+}
+
+/// A fault and the percentage of requests it applies to
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultSpec {
+    pub fault: Fault,
+    pub percent: f64,
+}
+
+/// Parse a fault spec: "drop:10%", "delay:500ms:5%", or "truncate-body:2%"
+pub fn parse_fault(spec: &str) -> Result<FaultSpec> {
+    let parts: Vec<&str> = spec.split(':').collect();
+    let (fault, rate) = match parts.as_slice() {
+        ["drop", rate] => (Fault::Drop, rate),
+        ["truncate-body", rate] => (Fault::TruncateBody, rate),
+        ["delay", delay, rate] => (Fault::Delay(crate::replay::parse_duration(delay)?), rate),
+        _ => anyhow::bail!(
+            "Invalid fault '{}' (expected drop:N%, delay:DURATION:N%, or truncate-body:N%)",
+            spec
+        ),
+    };
+
+    let percent: f64 = rate
+        .trim_end_matches('%')
+        .parse()
+        .context(format!("Invalid fault rate: '{}'", rate))?;
+    if !(0.0..=100.0).contains(&percent) {
+        anyhow::bail!("Fault rate must be between 0% and 100%, got '{}'", rate);
+    }
+
+    Ok(FaultSpec { fault, percent })
+}
+
+/// Faults to apply to the request at `index`.
+///
+/// Each spec rolls independently, seeded by `seed`, the request index, and
+/// the spec's position, so the same seed always faults the same requests.
+pub fn faults_for(index: usize, seed: u64, specs: &[FaultSpec]) -> Vec<Fault> {
+    specs
+        .iter()
+        .enumerate()
+        .filter(|(slot, spec)| {
+            let roll = crate::replay::mix_index(
+                seed ^ crate::replay::mix_index(
+                    (index as u64).wrapping_mul(31).wrapping_add(*slot as u64),
+                ),
+            );
+            // Uniform in [0, 100) at 0.01% resolution
+            ((roll % 10_000) as f64 / 100.0) < spec.percent
+        })
+        .map(|(_, spec)| spec.fault.clone())
+        .collect()
+}
+
+/// Truncate a body to half its length, on a character boundary
+pub fn truncate_body(body: &str) -> String {
+    let mut end = body.len() / 2;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fault() {
+        assert_eq!(
+            parse_fault("drop:10%").unwrap(),
+            FaultSpec {
+                fault: Fault::Drop,
+                percent: 10.0
+            }
+        );
+        assert_eq!(
+            parse_fault("delay:500ms:5%").unwrap().fault,
+            Fault::Delay(Duration::from_millis(500))
+        );
+        assert_eq!(
+            parse_fault("truncate-body:2%").unwrap().fault,
+            Fault::TruncateBody
+        );
+        assert!(parse_fault("explode:10%").is_err());
+        assert!(parse_fault("drop:150%").is_err());
+    }
+
+    #[test]
+    fn test_fault_rate_matches_percentage() {
+        let specs = vec![
+            parse_fault("drop:10%").unwrap(),
+            parse_fault("truncate-body:2%").unwrap(),
+        ];
+        let n = 20_000;
+        let mut drops = 0;
+        let mut truncates = 0;
+        for i in 0..n {
+            for fault in faults_for(i, 7, &specs) {
+                match fault {
+                    Fault::Drop => drops += 1,
+                    Fault::TruncateBody => truncates += 1,
+                    Fault::Delay(_) => {}
+                }
+            }
+        }
+        // 10% and 2% of 20k, with slack for hash distribution
+        assert!((1800..2200).contains(&drops), "drops: {}", drops);
+        assert!((320..480).contains(&truncates), "truncates: {}", truncates);
+    }
+
+    #[test]
+    fn test_faults_are_deterministic_per_seed() {
+        let specs = vec![parse_fault("drop:50%").unwrap()];
+        let a: Vec<_> = (0..100).map(|i| faults_for(i, 1, &specs)).collect();
+        let b: Vec<_> = (0..100).map(|i| faults_for(i, 1, &specs)).collect();
+        let c: Vec<_> = (0..100).map(|i| faults_for(i, 2, &specs)).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_truncate_body_multibyte() {
+        assert_eq!(truncate_body("abcd"), "ab");
+        assert_eq!(truncate_body("aé"), "a");
+    }
+}
@@ -0,0 +1,271 @@
+//! Obsolete header line folding (RFC 7230 §3.2.4)
+//!
+//! hyper refuses header values containing CR/LF, so a folded header can only
+//! be sent by writing the request bytes ourselves. Building the raw request is
+//! always available; sending it over a plain TCP socket needs the `obs-fold`
+//! feature. The raw path speaks HTTP/1.1 only, has no TLS, and ignores
+//! `--proxy`, so only `http://` targets can be probed.
+
+use anyhow::{bail, Result};
+use std::time::Duration;
+use url::Url;
+
+/// A header sent as a first line plus continuation lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldedHeader {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+/// Parse "Name:first|second|..." into a folded header
+pub fn parse_folded_header(spec: &str) -> Result<FoldedHeader> {
+    let Some((name, value)) = spec.split_once(':') else {
+        bail!(
+            "Invalid folded header '{}', expected 'Name:first|second'",
+            spec
+        );
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Invalid folded header '{}': empty name", spec);
+    }
+    let lines: Vec<String> = value.split('|').map(|l| l.trim().to_string()).collect();
+    if lines.len() < 2 {
+        bail!(
+            "Folded header '{}' needs at least one continuation line (separate lines with '|')",
+            spec
+        );
+    }
+    Ok(FoldedHeader {
+        name: name.to_string(),
+        lines,
+    })
+}
+
+impl FoldedHeader {
+    /// Wire form: continuation lines start with a single space
+    pub fn render(&self) -> String {
+        let mut out = format!("{}: {}\r\n", self.name, self.lines[0]);
+        for line in &self.lines[1..] {
+            out.push_str(&format!(" {}\r\n", line));
+        }
+        out
+    }
+}
+
+/// Headers the raw path manages itself
+const MANAGED_HEADERS: &[&str] = &["host", "content-length", "transfer-encoding", "connection"];
+
+/// Build an HTTP/1.1 request carrying `folded` alongside the captured headers
+pub fn build_raw_request(
+    method: &str,
+    url: &Url,
+    headers: &[(String, String)],
+    folded: &FoldedHeader,
+    body: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut out = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, target, host);
+    for (name, value) in headers {
+        if MANAGED_HEADERS.contains(&name.to_lowercase().as_str())
+            || name.eq_ignore_ascii_case(&folded.name)
+        {
+            continue;
+        }
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&folded.render());
+    if let Some(body) = body {
+        out.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    out.push_str("Connection: close\r\n\r\n");
+
+    let mut bytes = out.into_bytes();
+    if let Some(body) = body {
+        bytes.extend_from_slice(body);
+    }
+    bytes
+}
+
+/// Response read back over the raw socket
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Send a pre-built request over plain TCP and read until the server closes
+#[cfg(feature = "obs-fold")]
+pub async fn send_raw(url: &Url, raw: &[u8], timeout: Duration) -> Result<RawResponse> {
+    use anyhow::Context;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    if url.scheme() != "http" {
+        bail!("Folded headers can only be sent to http:// targets (no TLS on the raw path)");
+    }
+    let host = url.host_str().context("Target URL has no host")?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let exchange = async {
+        let mut stream = tokio::net::TcpStream::connect((host, port))
+            .await
+            .context("Failed to connect")?;
+        stream
+            .write_all(raw)
+            .await
+            .context("Failed to send request")?;
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .await
+            .context("Failed to read response")?;
+        parse_raw_response(&response)
+    };
+    tokio::time::timeout(timeout, exchange)
+        .await
+        .context("Request timed out")?
+}
+
+/// Without the `obs-fold` feature folded headers cannot be sent
+#[cfg(not(feature = "obs-fold"))]
+pub async fn send_raw(_url: &Url, _raw: &[u8], _timeout: Duration) -> Result<RawResponse> {
+    bail!("ushio was built without the 'obs-fold' feature")
+}
+
+/// Parse a complete HTTP/1.1 response, de-chunking the body if needed
+pub fn parse_raw_response(data: &[u8]) -> Result<RawResponse> {
+    let Some(split) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        bail!("Incomplete response: no end of headers");
+    };
+    let head = String::from_utf8_lossy(&data[..split]);
+    let mut lines = head.split("\r\n");
+
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid status line '{}'", status_line))?;
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+
+    let raw_body = &data[split + 4..];
+    let body = if header("transfer-encoding").is_some_and(|te| te.contains("chunked")) {
+        dechunk(raw_body)?
+    } else if let Some(len) = header("content-length").and_then(|l| l.parse::<usize>().ok()) {
+        raw_body[..len.min(raw_body.len())].to_vec()
+    } else {
+        raw_body.to_vec()
+    };
+
+    Ok(RawResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// Decode a chunked transfer-encoded body (trailers are ignored)
+pub(crate) fn dechunk(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let Some(eol) = data.windows(2).position(|w| w == b"\r\n") else {
+            bail!("Malformed chunked body");
+        };
+        let size_line = String::from_utf8_lossy(&data[..eol]);
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| anyhow::anyhow!("Invalid chunk size '{}'", size_hex))?;
+        data = &data[eol + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            bail!("Truncated chunked body");
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_folded_header() {
+        let h = parse_folded_header("X-Test: first | second|third").unwrap();
+        assert_eq!(h.name, "X-Test");
+        assert_eq!(h.lines, vec!["first", "second", "third"]);
+
+        assert!(parse_folded_header("X-Test:only-one").is_err());
+        assert!(parse_folded_header("no-colon").is_err());
+        assert!(parse_folded_header(":a|b").is_err());
+    }
+
+    #[test]
+    fn test_render_uses_continuation_lines() {
+        let h = parse_folded_header("X-Test:first|second").unwrap();
+        assert_eq!(h.render(), "X-Test: first\r\n second\r\n");
+    }
+
+    #[test]
+    fn test_build_raw_request() {
+        let url = Url::parse("http://127.0.0.1:8080/api/users?id=1").unwrap();
+        let folded = parse_folded_header("X-Test:a|b").unwrap();
+        let headers = vec![
+            ("Host".to_string(), "example.com".to_string()),
+            ("Accept".to_string(), "application/json".to_string()),
+            ("X-Test".to_string(), "captured".to_string()),
+        ];
+        let raw = build_raw_request("POST", &url, &headers, &folded, Some(b"{}"));
+        assert_eq!(
+            String::from_utf8(raw).unwrap(),
+            "POST /api/users?id=1 HTTP/1.1\r\n\
+             Host: 127.0.0.1:8080\r\n\
+             Accept: application/json\r\n\
+             X-Test: a\r\n b\r\n\
+             Content-Length: 2\r\n\
+             Connection: close\r\n\r\n{}"
+        );
+    }
+
+    #[test]
+    fn test_parse_raw_response() {
+        let r = parse_raw_response(
+            b"HTTP/1.1 400 Bad Request\r\nContent-Length: 3\r\nX-A: b\r\n\r\nbad",
+        )
+        .unwrap();
+        assert_eq!(r.status, 400);
+        assert_eq!(r.headers[1], ("x-a".to_string(), "b".to_string()));
+        assert_eq!(r.body, b"bad");
+
+        let chunked = parse_raw_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(chunked.body, b"hello world");
+
+        assert!(parse_raw_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+}
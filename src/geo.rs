@@ -0,0 +1,94 @@
+//! Peer IP enrichment
+//!
+//! With `--annotate-geo`, each result records the IP it actually connected
+//! to. Country and ASN lookups from MaxMind databases (`--geo-db`) need the
+//! `geoip` feature; the peer address alone works without it.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::ReplaySession;
+
+/// Where a peer IP is located, as far as the databases know
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+    pub as_org: Option<String>,
+}
+
+/// A set of MaxMind databases (e.g. GeoLite2-Country and GeoLite2-ASN)
+#[cfg(feature = "geoip")]
+pub struct GeoDb {
+    readers: Vec<maxminddb::Reader<Vec<u8>>>,
+}
+
+#[cfg(feature = "geoip")]
+impl GeoDb {
+    /// Open one or more `.mmdb` files
+    pub fn open(paths: &[String]) -> Result<Self> {
+        use anyhow::Context;
+
+        let readers = paths
+            .iter()
+            .map(|p| {
+                maxminddb::Reader::open_readfile(p)
+                    .with_context(|| format!("Failed to open geo database {}", p))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { readers })
+    }
+
+    /// Look up an IP in every database, merging what each one knows
+    pub fn lookup(&self, ip: std::net::IpAddr) -> GeoInfo {
+        use maxminddb::geoip2;
+
+        let mut info = GeoInfo::default();
+        for reader in &self.readers {
+            if let Ok(country) = reader.lookup::<geoip2::Country>(ip) {
+                if let Some(code) = country.country.and_then(|c| c.iso_code) {
+                    info.country.get_or_insert_with(|| code.to_string());
+                }
+            }
+            if let Ok(asn) = reader.lookup::<geoip2::Asn>(ip) {
+                if info.asn.is_none() {
+                    info.asn = asn.autonomous_system_number;
+                }
+                if let Some(org) = asn.autonomous_system_organization {
+                    info.as_org.get_or_insert_with(|| org.to_string());
+                }
+            }
+        }
+        info
+    }
+}
+
+/// Without the `geoip` feature no database can be opened
+#[cfg(not(feature = "geoip"))]
+pub struct GeoDb;
+
+#[cfg(not(feature = "geoip"))]
+impl GeoDb {
+    pub fn open(_paths: &[String]) -> Result<Self> {
+        anyhow::bail!("ushio was built without the 'geoip' feature; --geo-db is unavailable")
+    }
+
+    pub fn lookup(&self, _ip: std::net::IpAddr) -> GeoInfo {
+        GeoInfo::default()
+    }
+}
+
+/// Fill in `geo` for every result that recorded a peer address
+pub fn annotate_session(session: &mut ReplaySession, db: &GeoDb) {
+    for result in &mut session.results {
+        let ip = result
+            .peer_addr
+            .as_deref()
+            .and_then(|addr| addr.parse::<std::net::SocketAddr>().ok())
+            .map(|addr| addr.ip());
+        if let Some(ip) = ip {
+            result.geo = Some(db.lookup(ip));
+        }
+    }
+}
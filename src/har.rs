@@ -1,20 +1,29 @@
 //! HAR (HTTP Archive) parsing
 //!
-//! Parses HAR 1.2 format files into ushio's internal capture format.
+//! Parses HAR 1.2 format files into ushio's internal capture format, and
+//! exports captures back to HAR for browser devtools and other HAR tools.
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::capture::{apply_name_rules, Body, CapturedRequest, NameRule};
 
 /// HAR 1.2 root structure
 ///
 /// These structs model the full HAR 1.2 spec. Not all fields are consumed
 /// by the conversion logic, but they must be present for deserialization.
-#[derive(Debug, Deserialize)]
+/// Fields ushio only writes (cookies, sizes, timings, ...) default when absent.
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Har {
     pub log: HarLog,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[allow(dead_code)]
 pub struct HarLog {
     pub version: String,
@@ -22,14 +31,14 @@ pub struct HarLog {
     pub entries: Vec<HarEntry>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[allow(dead_code)]
 pub struct HarCreator {
     pub name: String,
     pub version: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct HarEntry {
@@ -37,27 +46,50 @@ pub struct HarEntry {
     pub request: HarRequest,
     pub response: HarResponse,
     pub time: f64,
+    #[serde(default)]
+    pub cache: HarCache,
+    #[serde(default)]
+    pub timings: HarTimings,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct HarRequest {
     pub method: String,
     pub url: String,
     pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarCookie>,
     pub headers: Vec<HarHeader>,
     pub query_string: Vec<HarQueryParam>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub post_data: Option<HarPostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct HarResponse {
     pub status: u16,
-    #[serde(rename = "statusText")]
     pub status_text: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<HarCookie>,
     pub headers: Vec<HarHeader>,
+    #[serde(default)]
+    pub content: HarContent,
+    #[serde(default, rename = "redirectURL")]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -66,19 +98,72 @@ pub struct HarHeader {
     pub value: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HarCookie {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 #[allow(dead_code)]
 pub struct HarQueryParam {
     pub name: String,
     pub value: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct HarPostData {
     pub mime_type: String,
     pub text: Option<String>,
+    /// "base64" when `text` holds an encoded binary body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Form fields, which some exporters record instead of `text`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Vec<HarParam>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HarParam {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct HarContent {
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// "base64" when `text` holds an encoded binary body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HarCache {}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[allow(dead_code)]
+pub struct HarTimings {
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+}
+
+/// HAR uses -1 for sizes that aren't known
+fn unknown_size() -> i64 {
+    -1
 }
 
 /// Parse a HAR file from JSON string
@@ -88,21 +173,400 @@ pub fn parse_har(content: &str) -> Result<Har> {
 }
 
 /// Convert HAR entries to ushio capture format
-pub fn har_to_capture(har: Har) -> Vec<crate::capture::CapturedRequest> {
+pub fn har_to_capture(har: Har) -> Vec<CapturedRequest> {
+    let mut previous: Option<String> = None;
     har.log
         .entries
         .into_iter()
-        .map(|entry| crate::capture::CapturedRequest {
-            method: entry.request.method,
-            url: entry.request.url,
-            headers: entry
-                .request
-                .headers
-                .into_iter()
-                .map(|h| (h.name, h.value))
-                .collect(),
-            body: entry.request.post_data.and_then(|p| p.text),
-            expected_status: Some(entry.response.status),
+        .map(|entry| {
+            let started = entry.started_date_time.clone();
+            let mut request = entry_to_request(entry);
+            request.delay_ms = previous.as_deref().and_then(|p| think_time_ms(p, &started));
+            previous = Some(started);
+            request
         })
         .collect()
 }
+
+/// Milliseconds between two `startedDateTime` values, if both parse.
+///
+/// Entries that started earlier than their predecessor get 0.
+fn think_time_ms(previous: &str, current: &str) -> Option<u64> {
+    let previous = chrono::DateTime::parse_from_rfc3339(previous).ok()?;
+    let current = chrono::DateTime::parse_from_rfc3339(current).ok()?;
+    Some((current - previous).num_milliseconds().max(0) as u64)
+}
+
+/// Split HAR post data into a text body or a base64 binary body.
+///
+/// Base64 bodies that decode to UTF-8 become plain text; text that isn't
+/// valid base64 is kept as-is. Without `text`, `params` are encoded as an
+/// `application/x-www-form-urlencoded` body.
+fn post_data_body(post_data: HarPostData) -> (Option<String>, Option<String>) {
+    let Some(text) = post_data.text.filter(|t| !t.is_empty()) else {
+        let form = post_data.params.filter(|p| !p.is_empty()).map(|params| {
+            url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params.iter().map(|p| (&p.name, &p.value)))
+                .finish()
+        });
+        return (form, None);
+    };
+    if post_data.encoding.as_deref() != Some("base64") {
+        return (Some(text), None);
+    }
+    match BASE64.decode(text.trim()) {
+        Ok(bytes) => match String::from_utf8(bytes) {
+            Ok(decoded) => (Some(decoded), None),
+            Err(e) => (None, Some(BASE64.encode(e.as_bytes()))),
+        },
+        Err(_) => (Some(text), None),
+    }
+}
+
+/// The recorded response body, if the HAR kept a text one.
+///
+/// Browsers often leave `text` out or empty for bodies they didn't keep, so
+/// an empty `text` only counts when `size` says the body was empty. Base64
+/// bodies are decoded; binary ones are dropped, since `expected_body` is text.
+fn response_body(content: HarContent) -> Option<String> {
+    let text = content.text?;
+    if text.is_empty() {
+        return (content.size == 0).then_some(text);
+    }
+    if content.encoding.as_deref() != Some("base64") {
+        return Some(text);
+    }
+    match BASE64.decode(text.trim()) {
+        Ok(bytes) => String::from_utf8(bytes).ok(),
+        Err(_) => Some(text),
+    }
+}
+
+/// Convert a single HAR entry to a captured request
+fn entry_to_request(entry: HarEntry) -> CapturedRequest {
+    let (body, body_base64) = entry
+        .request
+        .post_data
+        .map(post_data_body)
+        .unwrap_or_default();
+    CapturedRequest {
+        method: entry.request.method,
+        url: entry.request.url,
+        headers: entry
+            .request
+            .headers
+            .into_iter()
+            .map(|h| (h.name, h.value))
+            .collect(),
+        body: body.map(Body::Inline),
+        body_base64,
+        expected_status: Some(entry.response.status),
+        // HTTP/2 pseudo-headers like `:status` aren't response headers
+        expected_headers: entry
+            .response
+            .headers
+            .into_iter()
+            .filter(|h| !h.name.starts_with(':'))
+            .map(|h| (h.name, h.value))
+            .collect(),
+        expected_body: response_body(entry.response.content),
+        name: None,
+        description: None,
+        raw: None,
+        delay_ms: None,
+        count: None,
+        tags: vec![],
+    }
+}
+
+/// Convert captured requests to a HAR 1.2 document.
+///
+/// Responses are stubs built from `expected_status`, `expected_headers`, and
+/// `expected_body`, and all timings are zero. Entries start at `started`,
+/// spaced by each request's `delay_ms`.
+pub fn capture_to_har(requests: &[CapturedRequest], started: chrono::DateTime<chrono::Utc>) -> Har {
+    let mut at = started;
+    let entries = requests
+        .iter()
+        .map(|request| {
+            at += chrono::Duration::milliseconds(request.delay_ms.unwrap_or(0) as i64);
+            request_to_entry(request, at)
+        })
+        .collect();
+
+    Har {
+        log: HarLog {
+            version: "1.2".to_string(),
+            creator: HarCreator {
+                name: "ushio".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries,
+        },
+    }
+}
+
+/// Convert a single captured request to a HAR entry
+fn request_to_entry(request: &CapturedRequest, started: chrono::DateTime<chrono::Utc>) -> HarEntry {
+    let headers = |pairs: &[(String, String)]| -> Vec<HarHeader> {
+        pairs
+            .iter()
+            .map(|(name, value)| HarHeader {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect()
+    };
+    let content_type = |pairs: &[(String, String)]| {
+        pairs
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("content-type"))
+            .map(|(_, v)| v.clone())
+            .unwrap_or_default()
+    };
+
+    let query_string = url::Url::parse(&request.url)
+        .map(|u| {
+            u.query_pairs()
+                .map(|(name, value)| HarQueryParam {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let status = request.expected_status.unwrap_or(0);
+    let body_size = |body: &Option<String>| body.as_ref().map_or(0, |b| b.len() as i64);
+    // File bodies are left out; inline them first with `inline_body_files`
+    let inline_body = request
+        .body
+        .as_ref()
+        .and_then(Body::inline)
+        .map(str::to_string);
+
+    HarEntry {
+        started_date_time: started.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        request: HarRequest {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            http_version: "HTTP/1.1".to_string(),
+            cookies: vec![],
+            headers: headers(&request.headers),
+            query_string,
+            post_data: match (&inline_body, &request.body_base64) {
+                (Some(text), _) => Some(HarPostData {
+                    mime_type: content_type(&request.headers),
+                    text: Some(text.clone()),
+                    encoding: None,
+                    params: None,
+                }),
+                (None, Some(encoded)) => Some(HarPostData {
+                    mime_type: content_type(&request.headers),
+                    text: Some(encoded.clone()),
+                    encoding: Some("base64".to_string()),
+                    params: None,
+                }),
+                (None, None) => None,
+            },
+            headers_size: -1,
+            body_size: match (&inline_body, &request.body_base64) {
+                (None, Some(encoded)) => BASE64.decode(encoded).map_or(-1, |b| b.len() as i64),
+                _ => body_size(&inline_body),
+            },
+        },
+        response: HarResponse {
+            status,
+            status_text: http::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("")
+                .to_string(),
+            http_version: "HTTP/1.1".to_string(),
+            cookies: vec![],
+            headers: headers(&request.expected_headers),
+            content: HarContent {
+                size: body_size(&request.expected_body),
+                mime_type: content_type(&request.expected_headers),
+                text: request.expected_body.clone(),
+                encoding: None,
+            },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: body_size(&request.expected_body),
+        },
+        time: 0.0,
+        cache: HarCache {},
+        timings: HarTimings::default(),
+    }
+}
+
+/// Convert a HAR document to a capture without holding it in memory.
+///
+/// Entries are parsed one at a time from `reader` and written to `writer`
+/// as they arrive, so memory use is bounded by the largest single entry.
+/// The output is byte-for-byte what `serde_json::to_string_pretty` would
+/// produce for the equivalent `Capture`. Returns the number of requests written.
+pub fn convert_har_streaming<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    source: Option<&str>,
+) -> Result<usize> {
+    convert_har_streaming_named(reader, writer, source, &[])
+}
+
+/// Streaming conversion that also applies naming rules to each request
+pub fn convert_har_streaming_named<R: Read, W: Write>(
+    reader: R,
+    mut writer: W,
+    source: Option<&str>,
+    names: &[NameRule],
+) -> Result<usize> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"version\": \"1.0\",")?;
+    writeln!(writer, "  \"source\": {},", serde_json::to_string(&source)?)?;
+    write!(writer, "  \"requests\": [")?;
+
+    let mut count = 0;
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    HarSeed {
+        writer: &mut writer,
+        count: &mut count,
+        names,
+    }
+    .deserialize(&mut de)?;
+    de.end()?;
+
+    if count > 0 {
+        write!(writer, "\n  ")?;
+    }
+    write!(writer, "]\n}}")?;
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Streaming sink shared by the HAR visitors
+struct HarSeed<'a, W> {
+    writer: &'a mut W,
+    count: &'a mut usize,
+    names: &'a [NameRule],
+}
+
+impl<'de, W: Write> DeserializeSeed<'de> for HarSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for HarSeed<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a HAR document")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut seen_log = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "log" {
+                map.next_value_seed(LogSeed {
+                    writer: &mut *self.writer,
+                    count: &mut *self.count,
+                    names: self.names,
+                })?;
+                seen_log = true;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        if !seen_log {
+            return Err(de::Error::missing_field("log"));
+        }
+        Ok(())
+    }
+}
+
+/// Visits the `log` object, streaming its `entries` array
+struct LogSeed<'a, W> {
+    writer: &'a mut W,
+    count: &'a mut usize,
+    names: &'a [NameRule],
+}
+
+impl<'de, W: Write> DeserializeSeed<'de> for LogSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for LogSeed<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a HAR log object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut seen_entries = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "entries" {
+                map.next_value_seed(EntriesSeed {
+                    writer: &mut *self.writer,
+                    count: &mut *self.count,
+                    names: self.names,
+                })?;
+                seen_entries = true;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        if !seen_entries {
+            return Err(de::Error::missing_field("entries"));
+        }
+        Ok(())
+    }
+}
+
+/// Visits the `entries` array, converting and writing one entry at a time
+struct EntriesSeed<'a, W> {
+    writer: &'a mut W,
+    count: &'a mut usize,
+    names: &'a [NameRule],
+}
+
+impl<'de, W: Write> DeserializeSeed<'de> for EntriesSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for EntriesSeed<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of HAR entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut previous: Option<String> = None;
+        while let Some(entry) = seq.next_element::<HarEntry>()? {
+            let started = entry.started_date_time.clone();
+            let mut request = entry_to_request(entry);
+            request.delay_ms = previous.as_deref().and_then(|p| think_time_ms(p, &started));
+            previous = Some(started);
+            apply_name_rules(&mut request, self.names);
+            let json = serde_json::to_string_pretty(&request).map_err(de::Error::custom)?;
+            let separator = if *self.count == 0 { "" } else { "," };
+            // Indent to sit inside the "requests" array
+            let indented = json.replace('\n', "\n    ");
+            write!(self.writer, "{}\n    {}", separator, indented).map_err(de::Error::custom)?;
+            *self.count += 1;
+        }
+        Ok(())
+    }
+}
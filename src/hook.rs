@@ -0,0 +1,112 @@
+//! Pre-request hooks
+//!
+//! Runs a user-supplied shell command before each request so external tools
+//! can mint per-request credentials (cloud IAM signatures, short-lived
+//! tokens). The command gets request metadata as JSON on stdin and prints a
+//! JSON object of headers to set on stdout.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// What the hook sees on stdin
+#[derive(Debug, Serialize)]
+pub struct HookInput<'a> {
+    pub index: usize,
+    pub method: &'a str,
+    pub url: &'a str,
+    pub headers: &'a [(String, String)],
+    pub body: Option<&'a str>,
+}
+
+/// Run `command` through the shell and return the headers it asks to set
+pub async fn run_pre_request_hook(
+    command: &str,
+    input: &HookInput<'_>,
+) -> Result<Vec<(String, String)>> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Pre-request hook failed to start")?;
+
+    let payload = serde_json::to_vec(input)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin may exit before reading it
+        let _ = stdin.write_all(&payload).await;
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("Pre-request hook failed")?;
+    if !output.status.success() {
+        bail!(
+            "Pre-request hook failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let headers: BTreeMap<String, String> = serde_json::from_str(&stdout)
+        .context("Pre-request hook must print a JSON object of header names to values")?;
+    Ok(headers.into_iter().collect())
+}
+
+/// Set each hook header, replacing any existing header of the same name
+pub fn merge_hook_headers(headers: &mut Vec<(String, String)>, added: Vec<(String, String)>) {
+    for (name, value) in added {
+        headers.retain(|(n, _)| !n.eq_ignore_ascii_case(&name));
+        headers.push((name, value));
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_replaces_case_insensitively() {
+        let mut headers = vec![
+            ("authorization".to_string(), "Bearer stale".to_string()),
+            ("Accept".to_string(), "*/*".to_string()),
+        ];
+        merge_hook_headers(
+            &mut headers,
+            vec![
+                ("Authorization".to_string(), "Bearer fresh".to_string()),
+                ("X-Signature".to_string(), "abc".to_string()),
+            ],
+        );
+        assert_eq!(
+            headers,
+            vec![
+                ("Accept".to_string(), "*/*".to_string()),
+                ("Authorization".to_string(), "Bearer fresh".to_string()),
+                ("X-Signature".to_string(), "abc".to_string()),
+            ]
+        );
+    }
+}
@@ -1,6 +1,20 @@
+pub mod adaptive;
 pub mod capture;
+pub mod config;
+pub mod curl;
 pub mod diff;
+pub mod fault;
+pub mod fold;
+pub mod geo;
 pub mod har;
+pub mod hook;
 pub mod output;
 pub mod proxy;
+pub mod raw;
+pub mod record;
 pub mod replay;
+pub mod template;
+pub mod timing;
+pub mod tls;
+pub mod validate;
+pub mod watch;
@@ -1,10 +1,16 @@
 use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
-use std::io::{IsTerminal, Read as _};
+use std::io::{IsTerminal, Read as _, Write as _};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use ushio::{capture, diff, har, output, replay};
+use ushio::{capture, curl, diff, har, output, replay, template};
+
+/// HAR files larger than this are converted in streaming mode (64 MB)
+const STREAMING_CONVERT_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Leading bytes of a gzip stream, e.g. a `.har.gz`
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
 #[derive(Parser, Debug)]
 #[command(name = "ushio")]
@@ -15,7 +21,8 @@ use ushio::{capture, diff, har, output, replay};
     ushio convert session.har -o capture.json     Convert HAR to ushio format
     ushio replay capture.json -t https://staging  Replay against staging
     ushio replay capture.json -t https://prod     Replay against production
-    ushio diff staging.json prod.json             Compare replay results")]
+    ushio diff staging.json prod.json             Compare replay results
+    ushio capture-diff old.json new.json          Compare two captures")]
 struct Args {
     #[command(subcommand)]
     command: Command,
@@ -27,20 +34,47 @@ struct Args {
     /// Verbose output
     #[arg(short, long, default_value = "false", global = true)]
     verbose: bool,
+
+    /// When to color output; `auto` colors only a terminal stdout without NO_COLOR set
+    #[arg(
+        long,
+        default_value = "auto",
+        global = true,
+        value_enum,
+        value_name = "WHEN"
+    )]
+    color: ColorChoice,
+
+    /// Read flag defaults from this file instead of ./ushio.toml
+    // Read by `config::expand_args` before parsing
+    #[allow(dead_code)]
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<String>,
 }
 
+// Parsed once at startup, so the size of the `Replay` variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Replay captured traffic against one or more targets
     Replay {
-        /// Path to HAR file or ushio capture file
-        #[arg(required = true)]
-        capture: String,
+        /// Path to HAR file or ushio capture file ("-" for stdin)
+        #[arg(required_unless_present = "plan_in", conflicts_with = "plan_in")]
+        capture: Option<String>,
 
         /// Target URL(s) to replay against (can specify multiple)
-        #[arg(short, long, required = true)]
+        #[arg(
+            short,
+            long,
+            required_unless_present_any = ["split", "plan_in"],
+            conflicts_with_all = ["split", "plan_in"]
+        )]
         target: Vec<String>,
 
+        /// Split requests across weighted targets (e.g. "https://old=90,https://new=10")
+        #[arg(long)]
+        split: Option<String>,
+
         /// Output file for results (default: print to stdout)
         #[arg(short, long)]
         output: Option<String>,
@@ -49,39 +83,200 @@ enum Command {
         #[arg(long, default_value = "30")]
         timeout: u64,
 
+        /// Time budget for the whole replay (seconds, or e.g. "5m"); requests not started by then are skipped
+        #[arg(long, value_name = "SECS")]
+        deadline: Option<String>,
+
+        /// Per-method timeouts in seconds, overriding --timeout (e.g. "POST=60,default=10")
+        #[arg(long)]
+        method_timeout: Option<String>,
+
         /// Number of concurrent requests (default: 1 for deterministic ordering)
         #[arg(long, default_value = "1")]
         concurrency: usize,
 
+        /// Adjust concurrency during the run with an AIMD controller (--concurrency sets the ceiling)
+        #[arg(long, default_value = "false")]
+        adaptive_concurrency: bool,
+
+        /// p95 latency the adaptive controller aims for (e.g. "200ms", "1s")
+        #[arg(long, requires = "adaptive_concurrency")]
+        target_p95: Option<String>,
+
         /// Mutate headers (format: "Header-Name:value" or "Header-Name:" to remove)
         #[arg(long)]
         header: Vec<String>,
 
+        /// Add a header to requests that don't already carry it (format: "Header-Name:value", repeatable)
+        #[arg(long, value_name = "NAME:VALUE")]
+        default_header: Vec<String>,
+
+        /// Send this User-Agent on every request, replacing captured ones (e.g. to tag replay traffic in logs)
+        #[arg(long, value_name = "STRING")]
+        user_agent: Option<String>,
+
+        /// Rewrite each URL after the host swap, sed-style: 's/REGEX/REPLACEMENT/[g]' (repeatable, applied in order)
+        #[arg(long, value_name = "s/REGEX/REPLACEMENT/")]
+        rewrite: Vec<String>,
+
+        /// Sort each URL's query parameters by name before sending
+        #[arg(long, default_value = "false")]
+        normalize_query: bool,
+
+        /// Remove this query parameter from every URL before sending, e.g. _ts (repeatable)
+        #[arg(long, value_name = "NAME")]
+        drop_param: Vec<String>,
+
+        /// Send "Authorization: Bearer TOKEN", replacing any captured one; "@FILE" reads the token from a file
+        #[arg(long, value_name = "TOKEN|@FILE", conflicts_with = "basic")]
+        bearer: Option<String>,
+
+        /// Send HTTP Basic credentials, replacing any captured Authorization header
+        #[arg(long, value_name = "USER:PASS")]
+        basic: Option<String>,
+
+        /// Fill `${NAME}` placeholders in URLs, header values, and bodies (repeatable)
+        #[arg(long, value_name = "NAME=VALUE")]
+        var: Vec<String>,
+
+        /// JSON object of variables; --var values take precedence
+        #[arg(long, value_name = "FILE")]
+        var_file: Option<String>,
+
         /// Strip cookies from requests
         #[arg(long, default_value = "false")]
         strip_cookies: bool,
 
+        /// Send the captured Host header instead of the target's (virtual-host and WAF host rule testing)
+        #[arg(long, default_value = "false")]
+        keep_host: bool,
+
+        /// Keep a cookie jar for the session: Set-Cookie responses are sent on later requests, replacing captured cookies of the same name
+        #[arg(long, default_value = "false")]
+        cookie_jar: bool,
+
+        /// Send all requests with an empty body (probe routing/WAF without submitting data)
+        #[arg(long, default_value = "false")]
+        strip_bodies: bool,
+
+        /// Force a cookie onto every request so A/B edges bucket consistently (format: "name=value")
+        #[arg(long)]
+        sticky_cookie: Option<String>,
+
+        /// Shell command run before each request: gets request JSON on stdin, prints a JSON object of headers to set (runs with your privileges)
+        #[arg(long)]
+        pre_request_hook: Option<String>,
+
+        /// Record only these response headers (comma-separated, e.g. "server,x-cache")
+        #[arg(long)]
+        keep_headers: Option<String>,
+
         /// Disable response body capture (reduces memory for large replays)
         #[arg(long, default_value = "false")]
         no_body: bool,
 
+        /// Largest response body to store, in bytes; larger bodies are only hashed
+        #[arg(long, value_name = "BYTES", default_value_t = replay::DEFAULT_MAX_BODY_BYTES, conflicts_with = "no_body")]
+        max_body_bytes: usize,
+
+        /// Stop reading each response body after this many bytes, recording the size read and body_truncated
+        #[arg(long, value_name = "BYTES")]
+        body_read_limit: Option<usize>,
+
         /// Delay between requests in milliseconds (for rate limiting)
         #[arg(long, default_value = "0")]
         delay: u64,
 
+        /// Cap the request rate across all in-flight requests (requests per second, e.g. 5 or 0.5)
+        #[arg(long)]
+        rate_limit: Option<f64>,
+
+        /// Retry connection errors, timeouts, and --retry-status responses up to N times
+        #[arg(long, value_name = "N", default_value = "0")]
+        retries: u32,
+
+        /// Wait before the first retry in milliseconds, doubling for each further one
+        #[arg(long, value_name = "MS", default_value = "100")]
+        retry_backoff: u64,
+
+        /// Also retry these response statuses (comma-separated, e.g. "502,503,504")
+        #[arg(
+            long,
+            value_name = "CODES",
+            value_delimiter = ',',
+            requires = "retries"
+        )]
+        retry_status: Vec<u16>,
+
+        /// Reproduce the captured spacing between requests (HAR startedDateTime)
+        #[arg(long, default_value = "false", conflicts_with = "delay")]
+        preserve_timing: bool,
+
+        /// Longest gap to wait between requests with --preserve-timing, in milliseconds
+        #[arg(long, value_name = "MS", requires = "preserve_timing")]
+        max_delay: Option<u64>,
+
+        /// Read streaming responses (SSE, open-ended chunked) for at most this many milliseconds
+        #[arg(long)]
+        stream_timeout: Option<u64>,
+
         /// Accept invalid TLS certificates (for staging with self-signed certs)
-        #[arg(long, default_value = "false")]
+        #[arg(short = 'k', long, default_value = "false")]
         insecure: bool,
 
+        /// Don't decode gzip/deflate/brotli responses, so body_size is the on-wire size
+        #[arg(long, default_value = "false")]
+        no_decompress: bool,
+
+        /// Record where each request's time went: DNS, connect, time to first byte, and download
+        #[arg(long, default_value = "false")]
+        timing: bool,
+
+        /// Probe the TLS version and cipher suite each HTTPS host negotiates (one extra handshake per host)
+        #[arg(long, default_value = "false", conflicts_with_all = ["proxy", "client_pkcs12"])]
+        tls_info: bool,
+
+        /// Client certificate for mutual TLS (PEM; use with --client-key)
+        #[arg(long, value_name = "FILE", requires = "client_key")]
+        client_cert: Option<String>,
+
+        /// Private key for --client-cert (PEM: PKCS#8, PKCS#1 or SEC1)
+        #[arg(long, value_name = "FILE", requires = "client_cert")]
+        client_key: Option<String>,
+
+        /// Client certificate and key for mutual TLS as one PKCS#12 file (needs the pkcs12 feature)
+        #[arg(long, value_name = "FILE", conflicts_with = "client_cert")]
+        client_pkcs12: Option<String>,
+
+        /// Password for --client-pkcs12
+        #[arg(long, value_name = "PASSWORD", requires = "client_pkcs12")]
+        client_pass: Option<String>,
+
+        /// Record the IP each request connected to (peer_addr), e.g. to tell edge POPs apart
+        #[arg(long, default_value = "false")]
+        annotate_geo: bool,
+
+        /// MaxMind database(s) for country/ASN of peer IPs (repeatable; needs the geoip feature)
+        #[arg(long, requires = "annotate_geo")]
+        geo_db: Vec<String>,
+
         /// Filter requests by URL pattern (substring match)
         #[arg(long)]
         filter: Option<String>,
 
+        /// Filter requests by URL glob over the full URL (`*` any characters, `?` one), e.g. "*/api/checkout*"
+        #[arg(long, value_name = "GLOB")]
+        filter_url: Option<String>,
+
+        /// Only replay requests carrying this tag (repeatable; any listed tag matches)
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+
         /// Filter requests by HTTP method (comma-separated, e.g. "GET,POST")
-        #[arg(long)]
+        #[arg(long, visible_alias = "filter-method")]
         method: Option<String>,
 
-        /// Replay only a range of requests (e.g. "0-9", "5-", "-10")
+        /// Replay only a range of requests, both ends inclusive (e.g. "0-9", "5-", "-10", "50..75", "..10")
         #[arg(long)]
         range: Option<String>,
 
@@ -89,35 +284,447 @@ enum Command {
         #[arg(long)]
         proxy: Option<String>,
 
+        /// Connect to IP for HOST, keeping Host and SNI (curl-style, repeatable)
+        #[arg(long, value_name = "HOST:PORT:IP")]
+        resolve: Vec<String>,
+
+        /// Send a header with obsolete line folding over a raw socket, http:// only (format: "Name:first|second")
+        #[cfg(feature = "obs-fold")]
+        #[arg(long)]
+        obs_fold: Option<String>,
+
+        /// Send requests that have a `raw` blob byte-for-byte over a socket (HTTP/1.1 only)
+        #[arg(long, default_value = "false")]
+        raw: bool,
+
+        /// Pin the HTTP version instead of negotiating it ("2" uses prior knowledge, no fallback)
+        #[arg(long, value_name = "VERSION")]
+        http_version: Option<HttpVersion>,
+
+        /// Inject faults into a subset of requests: "drop:10%", "delay:500ms:5%", "truncate-body:2%"
+        #[arg(long)]
+        fault: Vec<String>,
+
+        /// Seed deciding which requests get faults (default: random, recorded in the session)
+        #[arg(long)]
+        fault_seed: Option<u64>,
+
+        /// Send requests in a seeded random order; results keep their capture positions (most useful with --concurrency > 1)
+        #[arg(long, default_value = "false", conflicts_with_all = ["order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check"])]
+        shuffle: bool,
+
+        /// Seed for --shuffle (default: random, recorded in the session)
+        #[arg(long, requires = "shuffle")]
+        shuffle_seed: Option<u64>,
+
+        /// Replay each request once per Accept value and report requests whose status or content type changes (e.g. "application/json,text/html,*/*")
+        #[arg(long, conflicts_with_all = ["split", "order_sensitivity"])]
+        accept_sweep: Option<String>,
+
+        /// Replay twice (warm, then verify) and fail if the second pass's cache HIT ratio is too low
+        #[arg(long, default_value = "false", conflicts_with_all = ["split", "order_sensitivity", "accept_sweep"])]
+        warm_and_verify: bool,
+
+        /// Minimum cache HIT ratio for --warm-and-verify, 0.0-1.0 [default: 0.9]
+        #[arg(long, requires = "warm_and_verify")]
+        min_hit_ratio: Option<f64>,
+
+        /// Send each request with Accept-Encoding gzip and identity, reporting compression ratios per target
+        #[arg(long, default_value = "false", conflicts_with_all = ["split", "order_sensitivity", "accept_sweep", "warm_and_verify"])]
+        compression_check: bool,
+
+        /// Write a capture containing only the requests a WAF blocked (on any target)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check"])]
+        extract_waf_boundary: Option<String>,
+
+        /// Regex marking a response body as a WAF block page, for --extract-waf-boundary (repeatable)
+        #[arg(long, value_name = "REGEX", requires = "extract_waf_boundary")]
+        waf_signature: Vec<String>,
+
+        /// Statuses meaning a WAF block, for --extract-waf-boundary (comma-separated; default "403,429,503")
+        #[arg(
+            long,
+            value_name = "CODES",
+            value_delimiter = ',',
+            requires = "extract_waf_boundary"
+        )]
+        waf_status: Vec<u16>,
+
+        /// Replay forward and reversed, reporting requests whose status depends on order
+        #[arg(long, default_value = "false", conflicts_with = "split")]
+        order_sensitivity: bool,
+
+        /// Write per-request timing in Chrome Trace Event format (for chrome://tracing / Perfetto)
+        #[arg(long)]
+        trace_events: Option<String>,
+
+        /// Also write the run's counts as JSON to this file, whatever --format is (one file per target)
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+
+        /// Print one comparison table for all targets instead of a block per target
+        #[arg(long, default_value = "false")]
+        summary_table: bool,
+
+        /// Pretty output: print only mismatches and errors, and nothing for a clean run
+        #[arg(long, default_value = "false", conflicts_with = "summary_table")]
+        only_errors: bool,
+
+        /// Print a command that reproduces this replay exactly (seeds included) when done
+        #[arg(long, default_value = "false")]
+        print_command: bool,
+
         /// Exit with code 2 if any status mismatches are detected (for CI)
         #[arg(long, default_value = "false")]
         assert_no_mismatch: bool,
+
+        /// Send each request this many times in a row, reporting requests whose status varies
+        #[arg(long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check"])]
+        repeat: u32,
+
+        /// Stop at the first request that fails with an error and exit with code 2
+        #[arg(long, default_value = "false", conflicts_with_all = ["order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check"])]
+        abort_on_error: bool,
+
+        /// Stop at the first status mismatch and exit with code 2
+        #[arg(long, default_value = "false", conflicts_with_all = ["order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check"])]
+        abort_on_mismatch: bool,
+
+        /// Record the fully resolved requests (final URL, headers, body) per target before sending
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["split", "plan_in", "order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check", "cookie_jar"])]
+        plan_out: Option<String>,
+
+        /// Print each request as it would be sent (final URL, headers) without sending anything
+        #[arg(long, default_value = "false", conflicts_with_all = ["split", "plan_in", "plan_out", "order_sensitivity", "accept_sweep", "warm_and_verify", "compression_check", "cookie_jar", "watch"])]
+        dry_run: bool,
+
+        /// Replay a recorded plan exactly, instead of a capture (target comes from the plan)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["split", "header", "default_header", "user_agent", "strip_cookies", "cookie_jar", "strip_bodies", "sticky_cookie", "pre_request_hook", "normalize_query", "drop_param"])]
+        plan_in: Option<String>,
+
+        /// Re-run the replay whenever the capture file changes (Ctrl-C to stop)
+        #[arg(long, default_value = "false")]
+        watch: bool,
+
+        /// Skip the confirmation prompt for mutating replays against production
+        #[arg(short, long, default_value = "false")]
+        yes: bool,
+
+        /// Target host substring that marks a production target (empty to disable)
+        #[arg(long, default_value = "prod")]
+        production_pattern: String,
     },
 
     /// Compare replay results between two targets
     Diff {
-        /// First replay result file
+        /// First replay result file ("-" for stdin)
         #[arg(required = true)]
         left: String,
 
-        /// Second replay result file
+        /// Second replay result file ("-" for stdin, if LEFT isn't)
+        #[arg(required_unless_present = "against_capture")]
+        right: Option<String>,
+
+        /// Compare LEFT against the expectations stored in this capture instead of a second session ("-" for stdin)
+        #[arg(long, conflicts_with = "right")]
+        against_capture: Option<String>,
+
+        /// Only show differences
+        #[arg(long, default_value = "false")]
+        only_diff: bool,
+
+        /// Print only the summary counts, not each differing request (pretty output; JSON keeps full detail)
+        #[arg(long, default_value = "false")]
+        summary: bool,
+
+        /// Stop at the first differing request and exit 1 (pretty/compact output only)
+        #[arg(long, default_value = "false")]
+        fail_fast: bool,
+
+        /// Pair results by position, or by method + URL path and query (ignoring the host)
+        #[arg(
+            long,
+            value_enum,
+            default_value = "index",
+            conflicts_with = "against_capture"
+        )]
+        match_by: MatchBy,
+
+        /// Leave this query parameter out when pairing with --match-by url, e.g. _ts (repeatable)
+        #[arg(long, value_name = "NAME")]
+        drop_param: Vec<String>,
+
+        /// Compare this response header (repeatable); replaces the default WAF/security set
+        #[arg(long, value_name = "NAME")]
+        compare_header: Vec<String>,
+
+        /// Difference categories that make diff exit 1 (repeatable or comma-separated)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "CATEGORY",
+            value_delimiter = ',',
+            default_value = "any"
+        )]
+        fail_on: Vec<FailOn>,
+
+        /// Report requests that got more than this many ms slower on the right
+        #[arg(long, value_name = "MS")]
+        latency_threshold: Option<u64>,
+
+        /// Report requests that got more than this percentage slower on the right
+        #[arg(long, value_name = "PERCENT")]
+        latency_pct: Option<f64>,
+
+        /// Only report body size changes larger than this: bytes ("512") or a percentage ("10%")
+        #[arg(long, value_name = "BYTES|PERCENT")]
+        size_threshold: Option<String>,
+
+        /// Never compare this response header (repeatable), e.g. cf-ray
+        #[arg(long, value_name = "NAME")]
+        ignore_header: Vec<String>,
+
+        /// Leave requests whose URL matches this glob out of the diff (repeatable); a leading '/' matches path and query
+        #[arg(long, value_name = "GLOB")]
+        ignore_url: Vec<String>,
+
+        /// Write the report to this file instead of stdout, e.g. with `-f html`
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Regex marking a response body as a WAF block page (repeatable), on top of the built-in ones
+        #[arg(long, value_name = "REGEX")]
+        waf_signature: Vec<String>,
+
+        /// Statuses meaning a WAF block (comma-separated; default "403,429,503")
+        #[arg(long, value_name = "CODES", value_delimiter = ',')]
+        waf_status: Vec<u16>,
+
+        /// Also write the diff counts as JSON to this file, whatever --format is
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+    },
+
+    /// Replay a capture and compare each response against the status, headers, and body it recorded
+    Verify {
+        /// Path to HAR file or ushio capture file ("-" for stdin)
         #[arg(required = true)]
-        right: String,
+        capture: String,
+
+        /// Target URL to replay against
+        #[arg(short, long, required = true)]
+        target: String,
+
+        /// Request timeout in seconds
+        #[arg(long, default_value = "30")]
+        timeout: u64,
+
+        /// Number of concurrent in-flight requests
+        #[arg(long, default_value = "1")]
+        concurrency: usize,
+
+        /// Add or override a header (format: "Header-Name:value", repeatable)
+        #[arg(long)]
+        header: Vec<String>,
+
+        /// Accept invalid TLS certificates
+        #[arg(short = 'k', long, default_value = "false")]
+        insecure: bool,
+
+        /// Also save the replay session to this file
+        #[arg(long, value_name = "FILE")]
+        save_session: Option<String>,
 
         /// Only show differences
         #[arg(long, default_value = "false")]
         only_diff: bool,
+
+        /// Print only the summary counts, not each differing request (pretty output; JSON keeps full detail)
+        #[arg(long, default_value = "false")]
+        summary: bool,
+
+        /// Compare this response header (repeatable); replaces the default WAF/security set
+        #[arg(long, value_name = "NAME")]
+        compare_header: Vec<String>,
+
+        /// Never compare this response header (repeatable), e.g. cf-ray
+        #[arg(long, value_name = "NAME")]
+        ignore_header: Vec<String>,
+
+        /// Difference categories that make verify exit 1 (repeatable or comma-separated)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "CATEGORY",
+            value_delimiter = ',',
+            default_value = "any"
+        )]
+        fail_on: Vec<FailOn>,
+
+        /// Only report body size changes larger than this: bytes ("512") or a percentage ("10%")
+        #[arg(long, value_name = "BYTES|PERCENT")]
+        size_threshold: Option<String>,
+
+        /// Write the report to this file instead of stdout, e.g. with `-f html`
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Also write the diff counts as JSON to this file, whatever --format is
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+
+        /// Skip the confirmation prompt for mutating replays against production
+        #[arg(short, long, default_value = "false")]
+        yes: bool,
+
+        /// Target host substring that marks a production target (empty to disable)
+        #[arg(long, default_value = "prod")]
+        production_pattern: String,
+    },
+
+    /// Compare status codes across three or more replay result files
+    DiffMulti {
+        /// Replay result files, one per target
+        #[arg(long, required = true, num_args = 2.., value_name = "FILE")]
+        targets: Vec<String>,
+
+        /// Only show requests where the targets disagree
+        #[arg(long, default_value = "false")]
+        only_diff: bool,
+    },
+
+    /// Compare the request sets of two captures before replaying them
+    CaptureDiff {
+        /// First capture or HAR file
+        #[arg(required = true)]
+        left: String,
+
+        /// Second capture or HAR file
+        #[arg(required = true)]
+        right: String,
+    },
+
+    /// Merge capture or HAR files into one capture, in argument order
+    Merge {
+        /// Capture or HAR files to merge
+        #[arg(required = true, num_args = 1..)]
+        inputs: Vec<String>,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Drop requests with the same method, URL, and body as an earlier one
+        #[arg(long, default_value = "false")]
+        dedup: bool,
+    },
+
+    /// Check a capture file for problems that would fail loading or replay
+    Validate {
+        /// Capture file
+        #[arg(required = true)]
+        input: String,
+    },
+
+    /// Collapse identical requests in a capture into one
+    Dedup {
+        /// Capture or HAR file
+        #[arg(required = true)]
+        input: String,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Fields that make requests identical (comma-separated: method, url, path, body).
+        /// `path` is the URL without its query string.
+        #[arg(long, default_value = "method,url,body")]
+        dedup_by: String,
+
+        /// Record on each kept request how many requests it stands for
+        #[arg(long, default_value = "false")]
+        count: bool,
+    },
+
+    /// Add or remove tags on the requests in a capture
+    Tag {
+        /// Capture or HAR file
+        #[arg(required = true)]
+        input: String,
+
+        /// Only change requests whose URL matches this glob; a pattern starting with '/' matches the path and query
+        #[arg(long, value_name = "GLOB")]
+        url_glob: Option<String>,
+
+        /// Tag to add (repeatable)
+        #[arg(long, value_name = "TAG", required_unless_present = "remove")]
+        add: Vec<String>,
+
+        /// Tag to remove (repeatable)
+        #[arg(long, value_name = "TAG")]
+        remove: Vec<String>,
+
+        /// Output file (default: stdout); may be the input file
+        #[arg(short, long)]
+        output: Option<String>,
     },
 
     /// Convert HAR file to ushio capture format
     Convert {
-        /// Input HAR file (use "-" for stdin)
+        /// Input file (use "-" for stdin): a HAR file, or a capture with --to har/curl
         #[arg(required = true)]
         input: String,
 
         /// Output file (default: stdout)
         #[arg(short, long)]
         output: Option<String>,
+
+        /// Output format: ushio (HAR to capture), har (capture to HAR), or curl (capture to curl commands)
+        #[arg(long, value_enum, default_value = "ushio")]
+        to: ConvertFormat,
+
+        /// Rewrite request URLs to this target, like replay does (--to curl only)
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Name requests whose URL contains a pattern (format: "/api/login=Login", repeatable; first match wins)
+        #[arg(long)]
+        name: Vec<String>,
+    },
+
+    /// Send one URL N times and report latency percentiles and status codes
+    Bench {
+        /// URL to request
+        #[arg(required = true)]
+        url: String,
+
+        /// Number of requests to send
+        #[arg(short = 'n', long, default_value = "100")]
+        requests: usize,
+
+        /// Number of concurrent requests
+        #[arg(short = 'c', long, default_value = "1")]
+        concurrency: usize,
+
+        /// HTTP method
+        #[arg(short = 'X', long, default_value = "GET")]
+        method: String,
+
+        /// Add a header (format: "Header-Name:value", repeatable)
+        #[arg(long)]
+        header: Vec<String>,
+
+        /// Request timeout in seconds
+        #[arg(long, default_value = "30")]
+        timeout: u64,
+
+        /// Accept invalid TLS certificates
+        #[arg(long, default_value = "false")]
+        insecure: bool,
+
+        /// Save the full session (every request) to this file
+        #[arg(short, long)]
+        output: Option<String>,
     },
 
     /// Generate shell completions
@@ -149,6 +756,29 @@ enum Command {
         #[arg(long, default_value = "false")]
         insecure: bool,
     },
+
+    /// Record traffic through a forward proxy (HTTPS via a local CA)
+    Record {
+        /// Listen address for the proxy
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Capture file written on Ctrl-C
+        #[arg(short, long, default_value = "capture.json")]
+        output: String,
+
+        /// CA certificate clients must trust for HTTPS (created if missing)
+        #[arg(long, default_value = "ushio-ca.pem")]
+        ca_cert: String,
+
+        /// CA private key (created if missing)
+        #[arg(long, default_value = "ushio-ca-key.pem")]
+        ca_key: String,
+
+        /// Accept invalid TLS certificates from upstream servers
+        #[arg(long, default_value = "false")]
+        insecure: bool,
+    },
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -157,11 +787,139 @@ enum OutputFormat {
     Json,
     Compact,
     Junit,
+    Jsonl,
+    Html,
+    Csv,
+}
+
+impl OutputFormat {
+    /// How a replay session is serialized in this format. Pretty output is
+    /// for terminals only, so files get JSON.
+    fn session_format(&self) -> output::SessionFormat {
+        match self {
+            OutputFormat::Pretty | OutputFormat::Json => output::SessionFormat::Json,
+            OutputFormat::Compact => output::SessionFormat::Compact,
+            OutputFormat::Junit => output::SessionFormat::Junit,
+            OutputFormat::Jsonl => output::SessionFormat::Jsonl,
+            OutputFormat::Csv => output::SessionFormat::Csv,
+            OutputFormat::Html => unreachable!("replay rejects HTML output"),
+        }
+    }
+
+    /// Name for error messages
+    fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Pretty => "Pretty",
+            OutputFormat::Json => "JSON",
+            OutputFormat::Compact => "Compact",
+            OutputFormat::Junit => "JUnit",
+            OutputFormat::Jsonl => "JSONL",
+            OutputFormat::Html => "HTML",
+            OutputFormat::Csv => "CSV",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output should be colored. An explicit choice wins over NO_COLOR.
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ConvertFormat {
+    Ushio,
+    Har,
+    Curl,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum HttpVersion {
+    #[value(name = "1.1")]
+    Http1,
+    #[value(name = "2")]
+    Http2,
+}
+
+impl From<HttpVersion> for replay::HttpVersion {
+    fn from(v: HttpVersion) -> Self {
+        match v {
+            HttpVersion::Http1 => replay::HttpVersion::Http1,
+            HttpVersion::Http2 => replay::HttpVersion::Http2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum MatchBy {
+    Index,
+    Url,
+}
+
+impl From<MatchBy> for diff::MatchBy {
+    fn from(m: MatchBy) -> Self {
+        match m {
+            MatchBy::Index => diff::MatchBy::Index,
+            MatchBy::Url => diff::MatchBy::Url,
+        }
+    }
+}
+
+/// Difference categories for `diff --fail-on`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum FailOn {
+    Any,
+    Status,
+    /// A status change into 5xx on the right side
+    #[value(name = "status:server")]
+    StatusServer,
+    Header,
+    Body,
+    Waf,
+    Latency,
+    Size,
+}
+
+impl FailOn {
+    /// Whether the diff has differences in this category
+    fn triggered(self, summary: &diff::DiffSummary) -> bool {
+        match self {
+            FailOn::Any => summary.different > 0,
+            FailOn::Status => summary.status_diffs > 0,
+            FailOn::StatusServer => summary
+                .diffs
+                .iter()
+                .filter_map(|d| d.status_diff.as_ref())
+                .any(|s| s.is_new_server_error()),
+            FailOn::Header => summary.header_diffs > 0,
+            FailOn::Body => summary.body_diffs > 0,
+            FailOn::Waf => summary.waf_diffs > 0,
+            FailOn::Latency => summary.latency_diffs > 0,
+            FailOn::Size => summary.size_diffs > 0,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    // Flags from ushio.toml go in ahead of the command line's own
+    let argv = ushio::config::expand_args(std::env::args().collect(), &Args::command())?;
+    let args = Args::parse_from(&argv);
 
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -172,50 +930,209 @@ async fn main() -> Result<()> {
             }),
         )
         .init();
+    colored::control::set_override(args.color.enabled());
 
     match args.command {
         Command::Replay {
             capture,
             target,
+            split,
             output,
             timeout,
+            deadline,
+            method_timeout,
             concurrency,
+            adaptive_concurrency,
+            target_p95,
             header,
+            default_header,
+            user_agent,
+            rewrite,
+            normalize_query,
+            drop_param,
+            bearer,
+            basic,
+            var,
+            var_file,
             strip_cookies,
+            keep_host,
+            cookie_jar,
+            strip_bodies,
+            sticky_cookie,
+            pre_request_hook,
+            keep_headers,
             no_body,
+            max_body_bytes,
+            body_read_limit,
             delay,
+            rate_limit,
+            retries,
+            retry_backoff,
+            retry_status,
+            preserve_timing,
+            max_delay,
+            stream_timeout,
             insecure,
+            no_decompress,
+            timing,
+            tls_info,
+            client_cert,
+            client_key,
+            client_pkcs12,
+            client_pass,
+            annotate_geo,
+            geo_db,
             filter,
+            filter_url,
+            tag,
             method,
             range,
             proxy,
+            resolve,
+            #[cfg(feature = "obs-fold")]
+            obs_fold,
+            raw,
+            http_version,
+            fault,
+            fault_seed,
+            shuffle,
+            shuffle_seed,
+            accept_sweep,
+            order_sensitivity,
+            warm_and_verify,
+            min_hit_ratio,
+            compression_check,
+            extract_waf_boundary,
+            waf_signature,
+            waf_status,
+            trace_events,
+            report,
+            summary_table,
+            only_errors,
+            print_command,
             assert_no_mismatch,
+            repeat,
+            abort_on_error,
+            abort_on_mismatch,
+            plan_out,
+            dry_run,
+            plan_in,
+            watch,
+            yes,
+            production_pattern,
         } => {
+            if matches!(args.format, OutputFormat::Html) {
+                anyhow::bail!("HTML output is only supported for diff");
+            }
+
+            // A plan stands in for the capture and carries its own target
+            let plan = plan_in.as_deref().map(replay::load_plan).transpose()?;
+            let capture = capture.or(plan_in).unwrap_or_default();
+            let target = match plan {
+                Some(ref p) => vec![p.target.clone()],
+                None => target,
+            };
+
+            if watch {
+                return watch_replay(&capture).await;
+            }
+
             // Load capture (try as ushio format first, then HAR)
-            let mut requests = load_capture_or_har(&capture)?;
+            let mut requests = match plan {
+                Some(p) => p.requests,
+                None => load_capture_or_har(&capture)?,
+            };
 
-            // Apply request filters
-            requests = filter_requests(
+            // Apply request filters, remembering where each survivor sat in the capture
+            let (mut original_indices, filtered): (Vec<usize>, Vec<_>) = filter_requests(
                 requests,
                 filter.as_deref(),
+                filter_url.as_deref(),
+                &tag,
                 method.as_deref(),
                 range.as_deref(),
-            )?;
+            )?
+            .into_iter()
+            .unzip();
+            requests = filtered;
 
             if requests.is_empty() {
                 eprintln!("No requests match the given filters");
                 return Ok(());
             }
 
-            // Parse header mutations
-            let header_mutations: Vec<(String, String)> = header
-                .iter()
-                .filter_map(|h| {
-                    let parts: Vec<&str> = h.splitn(2, ':').collect();
-                    if parts.len() == 2 {
-                        Some((parts[0].to_string(), parts[1].to_string()))
-                    } else if parts.len() == 1 && h.ends_with(':') {
-                        Some((parts[0].to_string(), String::new()))
+            let random_seed = || {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0)
+            };
+            let shuffle_seed = shuffle_seed.unwrap_or_else(random_seed);
+            if shuffle {
+                // original_indices moves with the requests, so results still
+                // map back to their capture positions
+                let order = replay::shuffle_order(requests.len(), shuffle_seed);
+                requests = order.iter().map(|&i| requests[i].clone()).collect();
+                original_indices = order.iter().map(|&i| original_indices[i]).collect();
+            }
+
+            let split_targets = split.as_deref().map(replay::parse_split).transpose()?;
+
+            // A split replay runs once, producing one combined session
+            let runs: Vec<String> = match &split {
+                Some(spec) => vec![spec.clone()],
+                None => target.clone(),
+            };
+            let guarded_targets: Vec<String> = match &split_targets {
+                Some(s) => s.iter().map(|t| t.target.clone()).collect(),
+                None => target.clone(),
+            };
+
+            // Guard against accidental mutating replays against production
+            if !yes && !dry_run {
+                for t in &guarded_targets {
+                    if replay::needs_production_confirmation(&requests, t, &production_pattern) {
+                        confirm_production(t)?;
+                    }
+                }
+            }
+
+            if insecure {
+                eprintln!(
+                    "Warning: TLS certificate verification is disabled (--insecure); \
+                     results may come from an impostor server"
+                );
+            }
+            let mixed_case = replay::mixed_case_header_requests(&requests, raw);
+            if mixed_case > 0 {
+                eprintln!(
+                    "Note: {} request(s) have mixed-case header names, which are sent \
+                     lowercase; only --raw with a raw blob keeps the exact casing",
+                    mixed_case
+                );
+            }
+
+            // Loaded up front, so a bad certificate fails before anything is sent
+            let client_identity = match (&client_cert, &client_key, &client_pkcs12) {
+                (Some(cert), Some(key), _) => {
+                    Some(ushio::tls::ClientIdentity::from_pem_files(cert, key)?)
+                }
+                (_, _, Some(p12)) => Some(ushio::tls::ClientIdentity::from_pkcs12_file(
+                    p12,
+                    client_pass.as_deref().unwrap_or(""),
+                )?),
+                _ => None,
+            };
+
+            // Parse header mutations
+            let mut header_mutations: Vec<(String, String)> = header
+                .iter()
+                .filter_map(|h| {
+                    let parts: Vec<&str> = h.splitn(2, ':').collect();
+                    if parts.len() == 2 {
+                        Some((parts[0].to_string(), parts[1].to_string()))
+                    } else if parts.len() == 1 && h.ends_with(':') {
+                        Some((parts[0].to_string(), String::new()))
                     } else {
                         eprintln!(
                             "Warning: Invalid header format '{}', expected 'Name:value'",
@@ -225,140 +1142,1016 @@ async fn main() -> Result<()> {
                     }
                 })
                 .collect();
+            // Ahead of --header values, so an explicit User-Agent mutation still wins
+            if let Some(ua) = user_agent {
+                header_mutations.insert(0, ("User-Agent".to_string(), ua));
+            }
+            let default_headers = default_header
+                .iter()
+                .map(|h| {
+                    h.split_once(':')
+                        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Invalid default header '{}', expected 'Name:value'", h)
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Applied last, so they win over captured and --header values
+            let authorization = match (bearer, basic) {
+                (Some(ref token), _) => Some(replay::bearer_authorization(token)?),
+                (None, Some(ref credentials)) => Some(replay::basic_authorization(credentials)?),
+                (None, None) => None,
+            };
+            if let Some(value) = authorization {
+                header_mutations.push(("Authorization".to_string(), value));
+            }
+
+            let sticky_cookie = sticky_cookie
+                .map(|c| {
+                    c.split_once('=')
+                        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Invalid sticky cookie '{}', expected 'name=value'", c)
+                        })
+                })
+                .transpose()?;
+
+            let faults = fault
+                .iter()
+                .map(|f| ushio::fault::parse_fault(f))
+                .collect::<Result<Vec<_>>>()?;
+            let fault_seed = fault_seed.unwrap_or_else(random_seed);
+
+            let resolve = resolve
+                .iter()
+                .map(|spec| replay::parse_resolve(spec))
+                .collect::<Result<Vec<_>>>()?;
+            let url_rewrites = rewrite
+                .iter()
+                .map(|spec| replay::UrlRewrite::parse(spec))
+                .collect::<Result<Vec<_>>>()?;
+            let waf_rules =
+                diff::WafRules::with_signatures(&waf_signature)?.with_statuses(&waf_status);
+
+            if let Some(rate) = rate_limit {
+                if !(rate.is_finite() && rate > 0.0) {
+                    anyhow::bail!("--rate-limit must be a positive number, got {}", rate);
+                }
+            }
+
+            let adaptive = adaptive_concurrency
+                .then(|| -> Result<_> {
+                    Ok(replay::AdaptiveConcurrency {
+                        target_p95: replay::parse_duration(
+                            target_p95.as_deref().unwrap_or("200ms"),
+                        )?,
+                        max_concurrency: if concurrency > 1 {
+                            concurrency
+                        } else {
+                            ushio::adaptive::DEFAULT_MAX_CONCURRENCY
+                        },
+                    })
+                })
+                .transpose()?;
+
+            // Open geo databases up front so a bad path fails before replaying
+            let geo_db = if geo_db.is_empty() {
+                None
+            } else {
+                Some(ushio::geo::GeoDb::open(&geo_db)?)
+            };
+
+            // Pin generated seeds so the recorded command replays identically
+            let command = reproduce_command(
+                argv.iter().skip(1).cloned(),
+                &[
+                    ("--fault-seed", (!faults.is_empty()).then_some(fault_seed)),
+                    ("--shuffle-seed", shuffle.then_some(shuffle_seed)),
+                ],
+            );
+
+            let mut vars = match var_file {
+                Some(ref path) => template::load_var_file(path)?,
+                None => vec![],
+            };
+            for spec in &var {
+                vars.push(template::parse_var(spec)?);
+            }
 
             let config = replay::ReplayConfig {
                 timeout: Duration::from_secs(timeout),
+                deadline: deadline
+                    .as_deref()
+                    .map(replay::parse_timeout)
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!("Invalid --deadline: {}", e))?,
+                method_timeouts: method_timeout
+                    .as_deref()
+                    .map(replay::MethodTimeouts::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
                 concurrency,
                 header_mutations,
+                default_headers,
+                vars,
+                url_rewrites,
+                query: replay::QueryNormalization {
+                    sort: normalize_query,
+                    drop: drop_param,
+                },
                 strip_cookies,
+                keep_host,
+                cookie_jar,
+                strip_bodies,
+                sticky_cookie,
+                pre_request_hook,
                 capture_body: !no_body,
+                max_body_bytes,
+                body_read_limit,
+                keep_headers: keep_headers.map(|list| {
+                    list.split(',')
+                        .map(|h| h.trim().to_lowercase())
+                        .filter(|h| !h.is_empty())
+                        .collect()
+                }),
                 delay_ms: delay,
+                rate_limit,
+                preserve_timing,
+                max_delay: max_delay.map(Duration::from_millis),
+                stream_timeout: stream_timeout.map(Duration::from_millis),
+                retries,
+                retry_backoff: Duration::from_millis(retry_backoff),
+                retry_statuses: retry_status,
+                repeat: repeat as usize,
+                abort_on_error,
+                abort_on_mismatch,
                 insecure,
+                client_identity,
+                no_decompress,
+                annotate_geo,
+                timing,
+                tls_info,
                 capture_source: Some(capture.clone()),
+                body_dir: input_dir(&capture),
                 proxy: proxy.clone(),
+                resolve,
+                faults,
+                fault_seed,
+                adaptive,
+                #[cfg(feature = "obs-fold")]
+                obs_fold: obs_fold
+                    .as_deref()
+                    .map(ushio::fold::parse_folded_header)
+                    .transpose()?,
+                #[cfg(not(feature = "obs-fold"))]
+                obs_fold: None,
+                raw,
+                http_version: http_version.map(Into::into),
             };
 
-            // Replay against each target
-            for t in &target {
-                // Progress callback for TTY stderr
-                let progress: Option<replay::ProgressFn> = if std::io::stderr().is_terminal() {
-                    let counter = std::sync::Arc::new(AtomicUsize::new(0));
-                    Some(Box::new(move |total, result| {
-                        let done = counter.fetch_add(1, Ordering::Relaxed) + 1;
-                        let status_str = if result.error.is_some() {
-                            "ERR".to_string()
-                        } else {
-                            result.status.to_string()
-                        };
-                        eprint!(
-                            "\r  [{}/{}] {} {} → {}    ",
-                            done, total, result.method, result.url, status_str
-                        );
-                        if done == total {
-                            eprintln!();
+            if order_sensitivity {
+                for t in &target {
+                    let mut report =
+                        replay::order_sensitivity(&requests, t, config.clone()).await?;
+                    report.restore_request_indices(&original_indices);
+                    match args.format {
+                        OutputFormat::Pretty => output::print_order_sensitivity_pretty(&report),
+                        OutputFormat::Json => {
+                            println!("{}", output::print_order_sensitivity_json(&report))
                         }
-                    }))
-                } else {
-                    None
-                };
+                        OutputFormat::Compact => {
+                            println!("{}", output::print_order_sensitivity_compact(&report))
+                        }
+                        OutputFormat::Junit => {
+                            anyhow::bail!("JUnit output is not supported for --order-sensitivity")
+                        }
+                        OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                            anyhow::bail!(
+                                "{} output is not supported for --order-sensitivity",
+                                args.format.label()
+                            )
+                        }
+                    }
+                }
+                return Ok(());
+            }
 
-                let session =
-                    replay::replay_with_progress(&requests, t, config.clone(), progress).await?;
+            if warm_and_verify {
+                let min_hit_ratio = min_hit_ratio.unwrap_or(0.9);
+                if !(0.0..=1.0).contains(&min_hit_ratio) {
+                    anyhow::bail!(
+                        "--min-hit-ratio must be between 0.0 and 1.0, got {}",
+                        min_hit_ratio
+                    );
+                }
 
-                // Output results
-                match args.format {
-                    OutputFormat::Pretty => {
-                        output::print_replay_pretty(&session);
+                let mut failed = false;
+                for t in &target {
+                    let mut report =
+                        replay::warm_and_verify(&requests, t, config.clone(), min_hit_ratio)
+                            .await?;
+                    report.restore_request_indices(&original_indices);
+                    match args.format {
+                        OutputFormat::Pretty => output::print_cache_warm_pretty(&report),
+                        OutputFormat::Json => {
+                            println!("{}", output::print_cache_warm_json(&report))
+                        }
+                        OutputFormat::Compact => {
+                            println!("{}", output::print_cache_warm_compact(&report))
+                        }
+                        OutputFormat::Junit => {
+                            anyhow::bail!("JUnit output is not supported for --warm-and-verify")
+                        }
+                        OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                            anyhow::bail!(
+                                "{} output is not supported for --warm-and-verify",
+                                args.format.label()
+                            )
+                        }
                     }
+                    failed |= !report.passed;
+                }
+
+                if failed {
+                    eprintln!(
+                        "Assertion failed: cache hit ratio below {:.1}%",
+                        min_hit_ratio * 100.0
+                    );
+                    std::process::exit(2);
+                }
+                return Ok(());
+            }
+
+            if compression_check {
+                let mut reports = Vec::new();
+                for t in &target {
+                    let mut report =
+                        replay::compression_check(&requests, t, config.clone()).await?;
+                    report.restore_request_indices(&original_indices);
+                    reports.push(report);
+                }
+                let comparison = ushio::diff::compare_compression(
+                    reports,
+                    ushio::diff::COMPRESSION_RATIO_TOLERANCE,
+                );
+                match args.format {
+                    OutputFormat::Pretty => output::print_compression_pretty(&comparison),
                     OutputFormat::Json => {
-                        println!("{}", output::print_replay_json(&session));
+                        println!("{}", output::print_compression_json(&comparison))
                     }
                     OutputFormat::Compact => {
-                        println!("{}", output::print_replay_compact(&session));
+                        println!("{}", output::print_compression_compact(&comparison))
                     }
                     OutputFormat::Junit => {
-                        print!("{}", output::print_replay_junit(&session));
+                        anyhow::bail!("JUnit output is not supported for --compression-check")
+                    }
+                    OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                        anyhow::bail!(
+                            "{} output is not supported for --compression-check",
+                            args.format.label()
+                        )
                     }
                 }
+                return Ok(());
+            }
+
+            if let Some(ref spec) = accept_sweep {
+                let accepts = replay::parse_accept_sweep(spec)?;
+                for t in &target {
+                    let mut session =
+                        replay::replay_accept_sweep(&requests, t, &accepts, config.clone(), None)
+                            .await?;
+                    replay::restore_sweep_indices(&mut session, &original_indices, accepts.len());
+                    if let Some(ref path) = output {
+                        let output_path = session_output_path(path, t, target.len() > 1);
+                        let content =
+                            output::render_session(&session, args.format.session_format());
+                        std::fs::write(&output_path, content)?;
+                        eprintln!("Saved results to {}", output_path);
+                    }
+
+                    let mut report = replay::accept_sweep_report(&session, &accepts);
+                    report.restore_request_indices(&original_indices);
+                    match args.format {
+                        OutputFormat::Pretty => output::print_accept_sweep_pretty(&report),
+                        OutputFormat::Json => {
+                            println!("{}", output::print_accept_sweep_json(&report))
+                        }
+                        OutputFormat::Compact => {
+                            println!("{}", output::print_accept_sweep_compact(&report))
+                        }
+                        OutputFormat::Junit => {
+                            anyhow::bail!("JUnit output is not supported for --accept-sweep")
+                        }
+                        OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                            anyhow::bail!(
+                                "{} output is not supported for --accept-sweep",
+                                args.format.label()
+                            )
+                        }
+                    }
+                }
+                return Ok(());
+            }
 
-                // Save to file if requested
+            if summary_table
+                && matches!(
+                    args.format,
+                    OutputFormat::Json
+                        | OutputFormat::Junit
+                        | OutputFormat::Jsonl
+                        | OutputFormat::Csv
+                )
+            {
+                anyhow::bail!("--summary-table is only supported for pretty and compact output");
+            }
+
+            // Resolve requests as a replay would, print them, and stop
+            if dry_run {
+                if matches!(args.format, OutputFormat::Junit) {
+                    anyhow::bail!("JUnit output is not supported for --dry-run");
+                }
+                if matches!(args.format, OutputFormat::Jsonl | OutputFormat::Csv) {
+                    anyhow::bail!(
+                        "{} output is not supported for --dry-run",
+                        args.format.label()
+                    );
+                }
+                for t in &runs {
+                    let plan = replay::plan(&requests, t, &config).await?;
+                    match args.format {
+                        OutputFormat::Pretty => output::print_plan_pretty(&plan, &original_indices),
+                        OutputFormat::Json => {
+                            println!("{}", output::print_plan_json(&plan, &original_indices))
+                        }
+                        OutputFormat::Compact => {
+                            println!("{}", output::print_plan_compact(&plan, &original_indices))
+                        }
+                        OutputFormat::Junit
+                        | OutputFormat::Jsonl
+                        | OutputFormat::Html
+                        | OutputFormat::Csv => {
+                            unreachable!()
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            // Resolve everything up front, then send exactly what was recorded
+            let mut plans = Vec::new();
+            if let Some(ref path) = plan_out {
+                for t in &runs {
+                    let plan = replay::plan(&requests, t, &config).await?;
+                    let plan_path = session_output_path(path, t, runs.len() > 1);
+                    replay::save_plan(&plan, &plan_path)?;
+                    eprintln!("Saved replay plan to {}", plan_path);
+                    plans.push(plan);
+                }
+            }
+
+            let mut trace = Vec::new();
+            let mut sessions = Vec::new();
+            let mut failed_assertion = None;
+            let mut aborted_at = None;
+            let mut waf_blocked = std::collections::BTreeSet::new();
+
+            // Replay against each target
+            for (run_index, t) in runs.iter().enumerate() {
+                // A bar on interactive terminals, periodic lines for CI logs
+                let show_bar = std::io::stderr().is_terminal()
+                    && matches!(args.format, OutputFormat::Pretty)
+                    && !args.verbose;
+                let mut progress = replay_progress(show_bar);
+                if matches!(args.format, OutputFormat::Jsonl) {
+                    progress = stream_results_jsonl(progress, original_indices.clone());
+                }
+                let progress = Some(progress);
+
+                let mut session = match split_targets {
+                    Some(ref s) => {
+                        replay::replay_split(
+                            &requests,
+                            s,
+                            &original_indices,
+                            config.clone(),
+                            progress,
+                        )
+                        .await?
+                    }
+                    None if !plans.is_empty() => {
+                        replay::replay_plan(&plans[run_index], config.clone(), progress).await?
+                    }
+                    None => {
+                        replay::replay_with_progress(&requests, t, config.clone(), progress).await?
+                    }
+                };
+                session.command = Some(command.clone());
+                replay::restore_request_indices(&mut session, &original_indices);
+                if shuffle {
+                    // Back in capture order, so the session diffs cleanly
+                    session
+                        .results
+                        .sort_by_key(|r| (r.request_index, r.repeat_index));
+                }
+                if let Some(ref db) = geo_db {
+                    ushio::geo::annotate_session(&mut session, db);
+                }
+
+                // Output results
+                match args.format {
+                    _ if summary_table => {}
+                    OutputFormat::Pretty => {
+                        output::print_replay_pretty(&session, only_errors);
+                    }
+                    // Already written line by line as results came in
+                    OutputFormat::Jsonl => {}
+                    ref format => {
+                        print!(
+                            "{}",
+                            output::render_session(&session, format.session_format())
+                        );
+                    }
+                }
+
+                // Save to file if requested, in the selected format
                 if let Some(ref path) = output {
-                    let output_path = if target.len() > 1 {
-                        // Add target suffix for multiple targets
-                        let suffix = t.replace("://", "_").replace(['/', ':'], "_");
-                        format!("{}_{}", path.trim_end_matches(".json"), suffix)
-                    } else {
-                        path.clone()
-                    };
-                    replay::save_session(&session, &output_path)?;
+                    let output_path = session_output_path(path, t, runs.len() > 1);
+                    let content = output::render_session(&session, args.format.session_format());
+                    std::fs::write(&output_path, content)?;
                     eprintln!("Saved results to {}", output_path);
                 }
+                if let Some(ref path) = report {
+                    write_report(
+                        &session_output_path(path, t, runs.len() > 1),
+                        &output::print_replay_report(&session),
+                    )?;
+                }
+
+                // Rewritten after every target so it survives an early exit
+                if let Some(ref path) = trace_events {
+                    trace.extend(output::replay_trace_events(&session, run_index + 1));
+                    std::fs::write(path, output::print_trace_events(&trace))?;
+                }
+
+                if extract_waf_boundary.is_some() {
+                    waf_blocked.extend(ushio::diff::waf_blocked_indices(&session, &waf_rules));
+                }
 
-                // Assert mode for CI
+                // Assert mode for CI: stop at the first target with mismatches
                 if assert_no_mismatch && session.status_mismatches > 0 {
-                    eprintln!(
-                        "Assertion failed: {} status mismatch(es) detected",
-                        session.status_mismatches
-                    );
-                    std::process::exit(2);
+                    failed_assertion = Some(session.status_mismatches);
+                }
+                if let Some(index) = session.aborted_at {
+                    aborted_at = Some((index, t.clone()));
+                }
+
+                if summary_table {
+                    sessions.push(session);
+                }
+                if failed_assertion.is_some() || aborted_at.is_some() {
+                    break;
                 }
             }
+
+            if summary_table {
+                print!("{}", output::print_multi_target_table(&sessions));
+            }
+
+            if let Some(ref path) = extract_waf_boundary {
+                // Blocked indices refer to the full capture; find them among the filtered requests
+                let mut subset: Vec<_> = waf_blocked
+                    .iter()
+                    .filter_map(|i| original_indices.iter().position(|o| o == i))
+                    .map(|pos| requests[pos].clone())
+                    .collect();
+                capture::rebase_body_files(
+                    &mut subset,
+                    input_dir(&capture).as_deref(),
+                    input_dir(path).as_deref(),
+                )?;
+                let count = subset.len();
+                let subset =
+                    capture::Capture::new(subset).with_source(format!("waf-boundary:{}", capture));
+                capture::save_capture(&subset, path)?;
+                eprintln!("Wrote {} WAF-blocked request(s) to {}", count, path);
+            }
+
+            if print_command {
+                eprintln!("Reproduce with:\n  {}", command);
+            }
+
+            if let Some((index, target)) = aborted_at {
+                eprintln!("Aborted: request #{} failed against {}", index, target);
+                std::process::exit(2);
+            }
+            if let Some(mismatches) = failed_assertion {
+                eprintln!(
+                    "Assertion failed: {} status mismatch(es) detected",
+                    mismatches
+                );
+                std::process::exit(2);
+            }
         }
 
         Command::Diff {
             left,
             right,
+            against_capture,
             only_diff,
+            summary: summary_only,
+            fail_fast,
+            match_by,
+            drop_param,
+            compare_header,
+            fail_on,
+            latency_threshold,
+            latency_pct,
+            size_threshold,
+            ignore_header,
+            ignore_url,
+            output,
+            waf_signature,
+            waf_status,
+            report,
         } => {
-            // Load sessions
-            let left_session = replay::load_session(&left)?;
-            let right_session = replay::load_session(&right)?;
+            // A partial comparison would make a misleading full report
+            if fail_fast
+                && matches!(
+                    args.format,
+                    OutputFormat::Json | OutputFormat::Junit | OutputFormat::Html
+                )
+            {
+                anyhow::bail!("--fail-fast is not supported with JSON, JUnit, or HTML reports");
+            }
+            if !drop_param.is_empty() && !matches!(match_by, MatchBy::Url) {
+                anyhow::bail!("--drop-param only applies with --match-by url");
+            }
+            let options = diff::DiffOptions {
+                fail_fast,
+                match_by: match_by.into(),
+                compare_headers: compare_header,
+                ignore_headers: ignore_header,
+                waf: diff::WafRules::with_signatures(&waf_signature)?.with_statuses(&waf_status),
+                latency: (latency_threshold.is_some() || latency_pct.is_some()).then_some(
+                    diff::LatencyThreshold {
+                        ms: latency_threshold,
+                        pct: latency_pct,
+                    },
+                ),
+                size: size_threshold
+                    .as_deref()
+                    .map(diff::SizeThreshold::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+                drop_params: drop_param,
+                ignore_urls: ignore_url,
+            };
 
-            // Compute diff
-            let summary = diff::diff_sessions(&left_session, &right_session);
+            // Load sessions and compute diff
+            let from_stdin = [Some(&left), right.as_ref(), against_capture.as_ref()]
+                .into_iter()
+                .filter(|path| path.is_some_and(|p| p == "-"))
+                .count();
+            if from_stdin > 1 {
+                anyhow::bail!("Only one diff input can be read from stdin");
+            }
+            let left_session = load_session_input(&left)?;
+            let summary = match (against_capture, right) {
+                (Some(capture_path), _) => {
+                    let requests = load_capture_or_har(&capture_path)?;
+                    diff::diff_against_capture_with(
+                        &format!("capture:{}", capture_path),
+                        &requests,
+                        &left_session,
+                        &options,
+                    )
+                }
+                (None, Some(right)) => {
+                    let right_session = load_session_input(&right)?;
+                    diff::diff_sessions_with(&left_session, &right_session, &options)
+                }
+                (None, None) => anyhow::bail!("Either RIGHT or --against-capture is required"),
+            };
+
+            write_diff_report(
+                &summary,
+                args.format,
+                only_diff,
+                summary_only,
+                output.as_deref(),
+                "diff",
+            )?;
+            if let Some(ref path) = report {
+                write_report(path, &output::print_diff_report(&summary))?;
+            }
+
+            // Exit with code 1 if there are differences the caller cares about
+            if fail_on.iter().any(|category| category.triggered(&summary)) {
+                std::process::exit(1);
+            }
+        }
+
+        Command::Verify {
+            capture,
+            target,
+            timeout,
+            concurrency,
+            header,
+            insecure,
+            save_session,
+            only_diff,
+            summary: summary_only,
+            compare_header,
+            ignore_header,
+            fail_on,
+            size_threshold,
+            output,
+            report,
+            yes,
+            production_pattern,
+        } => {
+            // Checked before anything is sent
+            let options = diff::DiffOptions {
+                compare_headers: compare_header,
+                ignore_headers: ignore_header,
+                size: size_threshold
+                    .as_deref()
+                    .map(diff::SizeThreshold::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            let requests = load_capture_or_har(&capture)?;
+            if requests.is_empty() {
+                eprintln!("Capture {} has no requests", capture);
+                return Ok(());
+            }
+            if !yes
+                && replay::needs_production_confirmation(&requests, &target, &production_pattern)
+            {
+                confirm_production(&target)?;
+            }
+            if insecure {
+                eprintln!(
+                    "Warning: TLS certificate verification is disabled (--insecure); \
+                     results may come from an impostor server"
+                );
+            }
+
+            let header_mutations = header
+                .iter()
+                .map(|h| {
+                    h.split_once(':')
+                        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Invalid header '{}', expected 'Name:value'", h)
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let config = replay::ReplayConfig {
+                timeout: Duration::from_secs(timeout),
+                concurrency,
+                header_mutations,
+                insecure,
+                body_dir: input_dir(&capture),
+                ..Default::default()
+            };
+            let session = replay::replay(&requests, &target, config).await?;
+            if let Some(ref path) = save_session {
+                replay::save_session(&session, path)?;
+                eprintln!("Saved session to {}", path);
+            }
+
+            let summary = diff::diff_against_capture_with(
+                &format!("capture:{}", capture),
+                &requests,
+                &session,
+                &options,
+            );
+            write_diff_report(
+                &summary,
+                args.format,
+                only_diff,
+                summary_only,
+                output.as_deref(),
+                "verify",
+            )?;
+            if let Some(ref path) = report {
+                write_report(path, &output::print_diff_report(&summary))?;
+            }
+
+            if fail_on.iter().any(|category| category.triggered(&summary)) {
+                std::process::exit(1);
+            }
+        }
+
+        Command::DiffMulti { targets, only_diff } => {
+            let sessions = targets
+                .iter()
+                .map(|path| replay::load_session(path))
+                .collect::<Result<Vec<_>>>()?;
+            let summary = diff::diff_sessions_multi(&sessions);
 
-            // Output
             match args.format {
                 OutputFormat::Pretty => {
-                    output::print_diff_pretty(&summary, only_diff);
+                    output::print_multi_diff_pretty(&summary, only_diff);
                 }
                 OutputFormat::Json => {
-                    println!("{}", output::print_diff_json(&summary));
+                    println!("{}", output::print_multi_diff_json(&summary));
                 }
                 OutputFormat::Compact => {
-                    println!("{}", output::print_diff_compact(&summary));
+                    println!("{}", output::print_multi_diff_compact(&summary));
                 }
                 OutputFormat::Junit => {
-                    print!("{}", output::print_diff_junit(&summary));
+                    anyhow::bail!("JUnit output is not supported for diff-multi");
+                }
+                OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                    anyhow::bail!(
+                        "{} output is not supported for diff-multi",
+                        args.format.label()
+                    );
                 }
             }
 
-            // Exit with code 1 if there are differences
-            if summary.different > 0 {
+            // Exit with code 1 if any request diverges, matching `diff`
+            if summary.diverging > 0 {
                 std::process::exit(1);
             }
         }
 
-        Command::Convert { input, output } => {
+        Command::Merge {
+            inputs,
+            output,
+            dedup,
+        } => {
+            let output_dir = output.as_deref().and_then(input_dir);
+            let parts = inputs
+                .iter()
+                .map(|input| {
+                    let mut requests = load_capture_or_har(input)?;
+                    capture::rebase_body_files(
+                        &mut requests,
+                        input_dir(input).as_deref(),
+                        output_dir.as_deref(),
+                    )?;
+                    Ok((input.clone(), requests))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let total: usize = parts.iter().map(|(_, requests)| requests.len()).sum();
+            let merged = capture::merge_captures(parts, dedup);
+            match output {
+                Some(path) => {
+                    capture::save_capture(&merged, &path)?;
+                    let dropped = total - merged.requests.len();
+                    if dropped > 0 {
+                        eprintln!(
+                            "Merged {} files into {} requests ({} duplicates dropped) to {}",
+                            inputs.len(),
+                            merged.requests.len(),
+                            dropped,
+                            path
+                        );
+                    } else {
+                        eprintln!(
+                            "Merged {} files into {} requests to {}",
+                            inputs.len(),
+                            merged.requests.len(),
+                            path
+                        );
+                    }
+                }
+                None => {
+                    println!("{}", serde_json::to_string_pretty(&merged)?);
+                }
+            }
+        }
+
+        Command::Dedup {
+            input,
+            output,
+            dedup_by,
+            count,
+        } => {
+            let fields = capture::parse_dedup_fields(&dedup_by)?;
+            let mut requests = load_capture_or_har(&input)?;
+            capture::rebase_body_files(
+                &mut requests,
+                input_dir(&input).as_deref(),
+                output.as_deref().and_then(input_dir).as_deref(),
+            )?;
+            let total = requests.len();
+            let deduped = capture::Capture::new(capture::dedup_requests(requests, &fields, count))
+                .with_source(input.clone());
+            match output {
+                Some(path) => {
+                    capture::save_capture(&deduped, &path)?;
+                    eprintln!(
+                        "Kept {} of {} requests to {}",
+                        deduped.requests.len(),
+                        total,
+                        path
+                    );
+                }
+                None => {
+                    println!("{}", serde_json::to_string_pretty(&deduped)?);
+                }
+            }
+        }
+
+        Command::Tag {
+            input,
+            url_glob,
+            add,
+            remove,
+            output,
+        } => {
+            if let Some(tag) = add.iter().chain(&remove).find(|t| t.trim().is_empty()) {
+                anyhow::bail!("Invalid tag '{}'", tag);
+            }
+            let mut requests = load_capture_or_har(&input)?;
+            capture::rebase_body_files(
+                &mut requests,
+                input_dir(&input).as_deref(),
+                output.as_deref().and_then(input_dir).as_deref(),
+            )?;
+            let mut changed = 0;
+            for request in &mut requests {
+                let selected = url_glob
+                    .as_deref()
+                    .map_or(true, |glob| capture::url_glob_match(glob, &request.url));
+                if selected && capture::retag(request, &add, &remove) {
+                    changed += 1;
+                }
+            }
+            let total = requests.len();
+            let tagged = capture::Capture::new(requests).with_source(input.clone());
+            match output {
+                Some(path) => {
+                    capture::save_capture(&tagged, &path)?;
+                    eprintln!("Retagged {} of {} requests in {}", changed, total, path);
+                }
+                None => {
+                    println!("{}", serde_json::to_string_pretty(&tagged)?);
+                }
+            }
+        }
+
+        Command::Validate { input } => {
+            let content = read_input(&input)?;
+            let report = ushio::validate::validate_capture(&content, &input);
+
+            match args.format {
+                OutputFormat::Pretty => output::print_validation_pretty(&report),
+                OutputFormat::Json => println!("{}", output::print_validation_json(&report)),
+                OutputFormat::Compact => {
+                    println!("{}", output::print_validation_compact(&report))
+                }
+                OutputFormat::Junit => {
+                    anyhow::bail!("JUnit output is not supported for validate");
+                }
+                OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                    anyhow::bail!(
+                        "{} output is not supported for validate",
+                        args.format.label()
+                    );
+                }
+            }
+
+            // Warnings alone don't fail, so CI only gates on captures that won't replay
+            if !report.is_valid() {
+                std::process::exit(1);
+            }
+        }
+
+        Command::CaptureDiff { left, right } => {
+            let left_requests = load_capture_or_har(&left)?;
+            let right_requests = load_capture_or_har(&right)?;
+
+            let capture_diff = capture::diff_captures(&left_requests, &right_requests);
+
+            match args.format {
+                OutputFormat::Pretty => {
+                    output::print_capture_diff_pretty(&capture_diff, &left, &right);
+                }
+                OutputFormat::Json => {
+                    println!("{}", output::print_capture_diff_json(&capture_diff));
+                }
+                OutputFormat::Compact => {
+                    println!(
+                        "{}",
+                        output::print_capture_diff_compact(&capture_diff, &left, &right)
+                    );
+                }
+                OutputFormat::Junit => {
+                    anyhow::bail!("JUnit output is not supported for capture-diff");
+                }
+                OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                    anyhow::bail!(
+                        "{} output is not supported for capture-diff",
+                        args.format.label()
+                    );
+                }
+            }
+
+            // Exit with code 1 if the captures differ, matching `diff`
+            if !capture_diff.is_empty() {
+                std::process::exit(1);
+            }
+        }
+
+        Command::Convert {
+            input,
+            output,
+            to,
+            target,
+            name,
+        } => {
+            if target.is_some() && to != ConvertFormat::Curl {
+                anyhow::bail!("--target is only supported with --to curl");
+            }
+            let names = name
+                .iter()
+                .map(|n| capture::parse_name_rule(n))
+                .collect::<Result<Vec<_>>>()?;
+
+            // Large files are streamed entry by entry instead of loaded whole.
+            // Compressed ones always are, since their size says little.
+            let is_large = to == ConvertFormat::Ushio
+                && input != "-"
+                && (is_gzip_file(&input)
+                    || std::fs::metadata(&input)
+                        .map(|m| m.len() > STREAMING_CONVERT_THRESHOLD)
+                        .unwrap_or(false));
+            if is_large {
+                let reader = open_input_file(&input)?;
+                match output {
+                    Some(path) => {
+                        let writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                        let count =
+                            har::convert_har_streaming_named(reader, writer, Some(&input), &names)
+                                .map_err(|e| anyhow::anyhow!("Failed to parse HAR: {}", e))?;
+                        eprintln!("Converted {} requests to {}", count, path);
+                    }
+                    None => {
+                        let writer = std::io::BufWriter::new(std::io::stdout().lock());
+                        har::convert_har_streaming_named(reader, writer, Some(&input), &names)
+                            .map_err(|e| anyhow::anyhow!("Failed to parse HAR: {}", e))?;
+                        println!();
+                    }
+                }
+                return Ok(());
+            }
+
             // Read HAR file (stdin or file)
-            let (content, source) = if input == "-" {
-                let mut buf = String::new();
-                std::io::stdin()
-                    .read_to_string(&mut buf)
-                    .map_err(|e| anyhow::anyhow!("Failed to read stdin: {}", e))?;
-                (buf, "stdin".to_string())
+            let content = read_input(&input)?;
+            let source = if input == "-" {
+                "stdin".to_string()
             } else {
-                let c = std::fs::read_to_string(&input)
-                    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", input, e))?;
-                (c, input.clone())
+                input.clone()
             };
 
+            if to != ConvertFormat::Ushio {
+                let mut requests = capture::parse_capture(&content, &source)?.requests;
+                let text = if to == ConvertFormat::Har {
+                    capture::inline_body_files(&mut requests, input_dir(&input).as_deref())?;
+                    let har_data = har::capture_to_har(&requests, chrono::Utc::now());
+                    serde_json::to_string_pretty(&har_data)?
+                } else {
+                    // curl reads `@file` from the directory it runs in
+                    capture::rebase_body_files(&mut requests, input_dir(&input).as_deref(), None)?;
+                    let target = target
+                        .as_deref()
+                        .map(url::Url::parse)
+                        .transpose()
+                        .map_err(|e| anyhow::anyhow!("Invalid target URL: {}", e))?;
+                    requests
+                        .iter()
+                        .map(|r| curl::curl_command(r, target.as_ref()))
+                        .collect::<Result<Vec<_>>>()?
+                        .join("\n")
+                };
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, format!("{}\n", text))?;
+                        eprintln!("Converted {} requests to {}", requests.len(), path);
+                    }
+                    None => {
+                        println!("{}", text);
+                    }
+                }
+                return Ok(());
+            }
+
             // Parse HAR
             let har_data = har::parse_har(&content)
                 .map_err(|e| anyhow::anyhow!("Failed to parse HAR: {}", e))?;
 
             // Convert to capture format
-            let requests = har::har_to_capture(har_data);
+            let mut requests = har::har_to_capture(har_data);
+            for request in &mut requests {
+                capture::apply_name_rules(request, &names);
+            }
             let capture_data = capture::Capture::new(requests).with_source(source);
 
             // Output
@@ -378,6 +2171,56 @@ async fn main() -> Result<()> {
             }
         }
 
+        Command::Bench {
+            url,
+            requests,
+            concurrency,
+            method,
+            header,
+            timeout,
+            insecure,
+            output,
+        } => {
+            if requests == 0 {
+                anyhow::bail!("--requests must be at least 1");
+            }
+            let header_mutations = header
+                .iter()
+                .map(|h| {
+                    h.split_once(':')
+                        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("Invalid header '{}', expected 'Name:value'", h)
+                        })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let config = replay::ReplayConfig {
+                timeout: Duration::from_secs(timeout),
+                concurrency,
+                header_mutations,
+                capture_body: false,
+                insecure,
+                ..Default::default()
+            };
+            let session = replay::bench(&method, &url, requests, config, None).await?;
+
+            if let Some(ref path) = output {
+                replay::save_session(&session, path)?;
+                eprintln!("Saved results to {}", path);
+            }
+
+            match args.format {
+                OutputFormat::Pretty => output::print_bench_pretty(&session),
+                OutputFormat::Json => println!("{}", output::print_bench_json(&session)),
+                OutputFormat::Compact => println!("{}", output::print_bench_compact(&session)),
+                OutputFormat::Junit => anyhow::bail!("JUnit output is not supported for bench"),
+                OutputFormat::Jsonl | OutputFormat::Html | OutputFormat::Csv => {
+                    anyhow::bail!("{} output is not supported for bench", args.format.label())
+                }
+            }
+        }
+
         Command::Completions { shell } => {
             clap_complete::generate(shell, &mut Args::command(), "ushio", &mut std::io::stdout());
         }
@@ -417,18 +2260,411 @@ async fn main() -> Result<()> {
                 anyhow::bail!("Either --from-url or both --listen and --target are required");
             }
         }
+
+        Command::Record {
+            listen,
+            output,
+            ca_cert,
+            ca_key,
+            insecure,
+        } => {
+            let (ca, created) = ushio::record::RecordingCa::load_or_create(
+                std::path::Path::new(&ca_cert),
+                std::path::Path::new(&ca_key),
+            )?;
+            let addr: std::net::SocketAddr = listen
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid listen address: {}", listen))?;
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to bind to {}: {}", addr, e))?;
+
+            if created {
+                eprintln!("Created CA certificate {} (key: {})", ca_cert, ca_key);
+            }
+            eprintln!("Recording proxy listening on {}", addr);
+            eprintln!(
+                "Point clients at it as their HTTP(S) proxy; for HTTPS they must trust {}",
+                ca_cert
+            );
+            eprintln!("Press Ctrl-C to stop and save capture");
+
+            let shutdown = async {
+                tokio::signal::ctrl_c().await.ok();
+            };
+            let requests =
+                ushio::record::run_record_proxy(listener, ca, insecure, shutdown).await?;
+            let count = requests.len();
+            let capture_data =
+                capture::Capture::new(requests).with_source(format!("record:{}", listen));
+            std::fs::write(&output, serde_json::to_string_pretty(&capture_data)?)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output, e))?;
+            eprintln!("\nSaved {} requests to {}", count, output);
+        }
     }
 
     Ok(())
 }
 
-/// Load requests from either ushio capture format or HAR
+/// Session file for a target; multi-target runs get a per-target suffix
+fn session_output_path(path: &str, target: &str, multiple: bool) -> String {
+    if multiple {
+        let suffix = target.replace("://", "_").replace(['/', ':'], "_");
+        format!("{}_{}", path.trim_end_matches(".json"), suffix)
+    } else {
+        path.to_string()
+    }
+}
+
+/// How often progress lines are printed when no bar is shown
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Progress reporting for a replay run, to stderr.
+///
+/// With `bar`, an indicatif bar shows the count, failures so far, and ETA.
+/// Otherwise a line is printed at most every [`PROGRESS_LOG_INTERVAL`], plus
+/// a final one, so short runs stay quiet and CI logs aren't flooded.
+fn replay_progress(bar: bool) -> replay::ProgressFn {
+    let done = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    let started = Instant::now();
+
+    if bar {
+        let pb = indicatif::ProgressBar::new(0);
+        pb.set_style(
+            indicatif::ProgressStyle::with_template(
+                "  {bar:30.cyan/blue} {pos}/{len} {msg} ETA {eta}",
+            )
+            .expect("valid progress template")
+            .progress_chars("█▉▊▋▌▍▎▏ "),
+        );
+        return Box::new(move |total, result| {
+            pb.set_length(total as u64);
+            if result.error.is_some() {
+                let n = failed.fetch_add(1, Ordering::Relaxed) + 1;
+                pb.set_message(format!("({} failed)", n));
+            }
+            pb.inc(1);
+            if done.fetch_add(1, Ordering::Relaxed) + 1 == total {
+                pb.finish_and_clear();
+            }
+        });
+    }
+
+    let last_logged = std::sync::Mutex::new(None::<Instant>);
+    Box::new(move |total, result| {
+        if result.error.is_some() {
+            failed.fetch_add(1, Ordering::Relaxed);
+        }
+        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut last = last_logged.lock().unwrap();
+        let now = Instant::now();
+        let due = now.duration_since(last.unwrap_or(started)) >= PROGRESS_LOG_INTERVAL;
+        // The final line only closes out runs that already reported progress
+        if !(due || (done == total && last.is_some())) {
+            return;
+        }
+        *last = Some(now);
+
+        let elapsed = now.duration_since(started);
+        let eta = elapsed.mul_f64((total - done) as f64 / done as f64);
+        eprintln!(
+            "Progress: {}/{} ({}%), {} failed, ETA {}",
+            done,
+            total,
+            done * 100 / total.max(1),
+            failed.load(Ordering::Relaxed),
+            indicatif::HumanDuration(eta)
+        );
+    })
+}
+
+/// Write each result to stdout as a JSON line as soon as it finishes, then
+/// pass it on to `inner`. Lines carry the capture index (from
+/// `original_indices`), as the saved session does.
+fn stream_results_jsonl(
+    inner: replay::ProgressFn,
+    original_indices: Vec<usize>,
+) -> replay::ProgressFn {
+    Box::new(move |total, result| {
+        let mut line = result.clone();
+        if let Some(&index) = original_indices.get(result.request_index) {
+            line.request_index = index;
+        }
+        let mut stdout = std::io::stdout().lock();
+        // A closed pipe (`| head`) shouldn't abort the replay
+        let _ = writeln!(stdout, "{}", output::print_result_jsonl(&line));
+        let _ = stdout.flush();
+        drop(stdout);
+        inner(total, result);
+    })
+}
+
+/// Flags whose inline values are credentials, kept out of recorded commands
+const SECRET_FLAGS: &[&str] = &["--bearer", "--basic", "--client-pass"];
+
+/// Headers whose values are credentials, redacted in `--header` and
+/// `--default-header` values of recorded commands
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "api-key",
+    "x-auth-token",
+    "x-csrf-token",
+];
+
+/// `value` of `flag` with any credential in it replaced by `REDACTED`, or
+/// `None` when it holds none
+fn redact_flag_value(flag: &str, value: &str) -> Option<String> {
+    match flag {
+        _ if SECRET_FLAGS.contains(&flag) => {
+            (!value.starts_with('@')).then(|| "REDACTED".to_string())
+        }
+        "--header" | "--default-header" => {
+            let (name, header_value) = value.split_once(':')?;
+            let secret = SECRET_HEADERS.contains(&name.trim().to_ascii_lowercase().as_str())
+                && !header_value.trim().is_empty();
+            secret.then(|| format!("{}:REDACTED", name))
+        }
+        // Values can come from the environment or files; the names are enough
+        "--var" => value
+            .split_once('=')
+            .map(|(name, _)| format!("{}=REDACTED", name)),
+        "--proxy" => {
+            let mut url = url::Url::parse(value).ok()?;
+            if url.username().is_empty() && url.password().is_none() {
+                return None;
+            }
+            url.set_username("REDACTED").ok()?;
+            url.set_password(None).ok()?;
+            Some(url.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Rebuild the invocation as a shell command, appending any seed the user
+/// did not pass explicitly. Credentials are replaced with `REDACTED`: inline
+/// secrets, sensitive `--header` values, `--var` values, and `--proxy`
+/// userinfo. `--bearer @file` is kept, since it names a file rather than a
+/// secret.
+fn reproduce_command(
+    args: impl IntoIterator<Item = String>,
+    seeds: &[(&str, Option<u64>)],
+) -> String {
+    let mut args: Vec<String> = args.into_iter().collect();
+    for i in 0..args.len() {
+        let secret = match args[i].split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                redact_flag_value(flag, value).map(|v| format!("{}={}", flag, v))
+            }
+            _ if i > 0 => redact_flag_value(&args[i - 1], &args[i]),
+            _ => None,
+        };
+        if let Some(redacted) = secret {
+            args[i] = redacted;
+        }
+    }
+    for (flag, seed) in seeds {
+        let Some(seed) = seed else { continue };
+        let given = args
+            .iter()
+            .any(|a| a == flag || a.starts_with(&format!("{}=", flag)));
+        if !given {
+            args.push(flag.to_string());
+            args.push(seed.to_string());
+        }
+    }
+
+    std::iter::once("ushio".to_string())
+        .chain(args.iter().map(|a| curl::shell_quote(a)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Re-run this replay (minus `--watch`) in a child process every time the
+/// capture changes. A child keeps assertion exits from ending the watch.
+async fn watch_replay(capture: &str) -> Result<()> {
+    let path = std::path::Path::new(capture);
+    if !path.is_file() {
+        anyhow::bail!("--watch needs a capture file on disk, got '{}'", capture);
+    }
+
+    let exe = std::env::current_exe()?;
+    let args: Vec<std::ffi::OsString> = std::env::args_os()
+        .skip(1)
+        .filter(|a| a != "--watch")
+        .collect();
+    let clear = std::io::stdout().is_terminal();
+
+    loop {
+        if clear {
+            // Clear screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+            std::io::Write::flush(&mut std::io::stdout())?;
+        }
+        let status = tokio::process::Command::new(&exe)
+            .args(&args)
+            .status()
+            .await?;
+        eprintln!(
+            "[watch] replay exited with {}; waiting for changes to {} (Ctrl-C to stop)",
+            status, capture
+        );
+        ushio::watch::wait_for_change(path, ushio::watch::POLL_INTERVAL, ushio::watch::DEBOUNCE)
+            .await;
+    }
+}
+
+/// Print or save a diff report in `format`. Pretty output is for terminals
+/// only, so a report file gets JSON.
+fn write_diff_report(
+    summary: &diff::DiffSummary,
+    format: OutputFormat,
+    only_diff: bool,
+    summary_only: bool,
+    output: Option<&str>,
+    command: &str,
+) -> Result<()> {
+    if let Some(ref scope) = summary.header_scope {
+        eprintln!(
+            "Warning: sessions recorded different response headers; only comparing: {}",
+            if scope.is_empty() {
+                "(none)".to_string()
+            } else {
+                scope.join(", ")
+            }
+        );
+    }
+
+    let report = match format {
+        OutputFormat::Pretty => {
+            output::print_diff_pretty(summary, only_diff, summary_only);
+            output
+                .is_some()
+                .then(|| format!("{}\n", output::print_diff_json(summary)))
+        }
+        OutputFormat::Json => Some(format!("{}\n", output::print_diff_json(summary))),
+        OutputFormat::Compact => Some(format!("{}\n", output::print_diff_compact(summary))),
+        OutputFormat::Junit => Some(output::print_diff_junit(summary)),
+        OutputFormat::Html => Some(output::print_diff_html(summary)),
+        OutputFormat::Jsonl | OutputFormat::Csv => {
+            anyhow::bail!("{} output is not supported for {}", format.label(), command);
+        }
+    };
+    match (report, output) {
+        (Some(report), Some(path)) => {
+            std::fs::write(path, report)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))?;
+            eprintln!("Saved report to {}", path);
+        }
+        (Some(report), None) => print!("{}", report),
+        (None, _) => {}
+    }
+    Ok(())
+}
+
+/// Write a `--report` summary file
+fn write_report(path: &str, report: &str) -> Result<()> {
+    std::fs::write(path, format!("{}\n", report))
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))
+}
+
+/// Ask for confirmation before replaying mutating requests against production.
+///
+/// Prompts on a TTY; fails outright when stdin is not interactive.
+fn confirm_production(target: &str) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to replay non-idempotent requests against production target {} (pass --yes to confirm)",
+            target
+        );
+    }
+
+    eprint!(
+        "Target {} looks like production and the capture contains non-idempotent requests. Continue? [y/N] ",
+        target
+    );
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        anyhow::bail!("Aborted replay against {}", target);
+    }
+    Ok(())
+}
+
+/// Read a whole input file, or stdin when the path is "-". Gzipped input
+/// (e.g. `.har.gz`) is decompressed.
+fn read_input(path: &str) -> Result<String> {
+    let name = if path == "-" { "stdin" } else { path };
+    let mut bytes = Vec::new();
+    if path == "-" {
+        std::io::stdin().read_to_end(&mut bytes)
+    } else {
+        std::fs::File::open(path).and_then(|mut f| f.read_to_end(&mut bytes))
+    }
+    .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", name, e))?;
+
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        flate2::read::MultiGzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .map_err(|e| anyhow::anyhow!("Failed to decompress {}: {}", name, e))?;
+        bytes = decoded;
+    }
+    String::from_utf8(bytes).map_err(|e| anyhow::anyhow!("{} is not valid UTF-8: {}", name, e))
+}
+
+/// Directory an input file's relative paths (e.g. body files) are resolved
+/// against; `None` for stdin or a bare file name, meaning the working directory
+fn input_dir(path: &str) -> Option<std::path::PathBuf> {
+    if path == "-" {
+        return None;
+    }
+    std::path::Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(|dir| dir.to_path_buf())
+}
+
+/// Whether a file starts with the gzip magic bytes
+fn is_gzip_file(path: &str) -> bool {
+    let mut magic = [0u8; 2];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| magic == GZIP_MAGIC)
+}
+
+/// Open an input file for streaming, decompressing it if gzipped
+fn open_input_file(path: &str) -> Result<Box<dyn std::io::Read>> {
+    let file =
+        std::fs::File::open(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+    Ok(if is_gzip_file(path) {
+        Box::new(std::io::BufReader::new(flate2::read::MultiGzDecoder::new(
+            file,
+        )))
+    } else {
+        Box::new(std::io::BufReader::new(file))
+    })
+}
+
+/// Load a replay session from a file or stdin ("-")
+fn load_session_input(path: &str) -> Result<replay::ReplaySession> {
+    if path != "-" {
+        return replay::load_session(path);
+    }
+    serde_json::from_str(&read_input(path)?)
+        .map_err(|e| anyhow::anyhow!("Failed to parse session from stdin: {}", e))
+}
+
+/// Load requests from either ushio capture format or HAR ("-" reads stdin)
 fn load_capture_or_har(path: &str) -> Result<Vec<capture::CapturedRequest>> {
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+    let content = read_input(path)?;
 
     // Try as ushio capture first
-    if let Ok(cap) = serde_json::from_str::<capture::Capture>(&content) {
+    if let Ok(cap) = capture::parse_capture(&content, path) {
         return Ok(cap.requests);
     }
 
@@ -443,19 +2679,22 @@ fn load_capture_or_har(path: &str) -> Result<Vec<capture::CapturedRequest>> {
     ))
 }
 
-/// Filter requests by URL pattern, HTTP method, and index range
+/// Filter requests by URL pattern or glob, HTTP method, and index range,
+/// keeping each request's position in the capture
 fn filter_requests(
     requests: Vec<capture::CapturedRequest>,
     url_filter: Option<&str>,
+    url_glob: Option<&str>,
+    tags: &[String],
     method_filter: Option<&str>,
     range_filter: Option<&str>,
-) -> Result<Vec<capture::CapturedRequest>> {
+) -> Result<Vec<(usize, capture::CapturedRequest)>> {
     let methods: Option<Vec<String>> =
         method_filter.map(|m| m.split(',').map(|s| s.trim().to_uppercase()).collect());
 
     let (range_start, range_end) = parse_range(range_filter, requests.len())?;
 
-    let filtered: Vec<capture::CapturedRequest> = requests
+    let filtered: Vec<(usize, capture::CapturedRequest)> = requests
         .into_iter()
         .enumerate()
         .filter(|(i, req)| {
@@ -475,21 +2714,31 @@ fn filter_requests(
                     return false;
                 }
             }
+            // URL glob filter
+            if let Some(glob) = url_glob {
+                if !capture::glob_match(glob, &req.url) {
+                    return false;
+                }
+            }
+            // Tag filter
+            if !tags.is_empty() && !req.tags.iter().any(|t| tags.contains(t)) {
+                return false;
+            }
             true
         })
-        .map(|(_, req)| req)
         .collect();
 
     Ok(filtered)
 }
 
-/// Parse a range string like "5-10", "5-", "-10", or "5"
+/// Parse a range string like "5-10", "5-", "-10", "5..10", "5..", "..10", or "5"
 fn parse_range(range: Option<&str>, total: usize) -> Result<(usize, usize)> {
     let Some(range) = range else {
         return Ok((0, total.saturating_sub(1)));
     };
 
-    if let Some((start, end)) = range.split_once('-') {
+    // "50..75" reads the same as "50-75"; both ends are inclusive
+    if let Some((start, end)) = range.split_once("..").or_else(|| range.split_once('-')) {
         let start: usize = if start.is_empty() {
             0
         } else {
@@ -4,11 +4,40 @@
 
 use colored::Colorize;
 
-use crate::diff::{BodyDiff, DiffSummary, HeaderDiffType, RequestDiff};
-use crate::replay::ReplaySession;
+use crate::capture::{Body, CaptureDiff};
+use crate::diff::{
+    BodyDiff, CompressionComparison, DiffSummary, HeaderDiffType, JsonChangeKind, MultiDiffSummary,
+    RequestDiff, StatusDiff, StatusSeverity, TechChangeKind, UnmatchedRequest,
+};
+use crate::replay::{
+    AcceptSweepReport, CacheWarmReport, OrderSensitivityReport, ReplayPlan, ReplayResult,
+    ReplaySession,
+};
+use crate::validate::{Severity, ValidationReport};
 
 /// Print replay session in pretty format
-pub fn print_replay_pretty(session: &ReplaySession) {
+/// Print a replay session. With `only_errors`, print just the Issues
+/// section, and nothing at all for a clean run.
+pub fn print_replay_pretty(session: &ReplaySession, only_errors: bool) {
+    // Mismatches and errors
+    let issues: Vec<_> = session
+        .results
+        .iter()
+        .filter(|r| !r.status_match || r.error.is_some())
+        .collect();
+    if only_errors {
+        if !issues.is_empty() {
+            println!(
+                "  {} {}",
+                "Issues".bold().underline(),
+                session.target.dimmed()
+            );
+            println!();
+            print_issues(&issues);
+        }
+        return;
+    }
+
     println!();
     println!("{} {}", "ushio".bold().cyan(), "traffic replay".dimmed());
     println!("{}", "─".repeat(60).dimmed());
@@ -44,43 +73,76 @@ pub fn print_replay_pretty(session: &ReplaySession) {
             session.status_mismatches.to_string().yellow()
         );
     }
+    let retried = session.results.iter().filter(|r| r.attempts > 1).count();
+    if retried > 0 {
+        println!("  {} {}", "Retried:".bold(), retried.to_string().yellow());
+    }
+    let statuses = session.status_counts();
+    if !statuses.is_empty() {
+        let histogram: Vec<String> = statuses
+            .into_iter()
+            .map(|(status, count)| format!("{}: {}", format_status(status), count))
+            .collect();
+        println!("  {} {}", "Statuses:".bold(), histogram.join("  "));
+    }
+    if let Some(ref latency) = session.latency {
+        println!(
+            "  {} p50 {}ms  p90 {}ms  p99 {}ms  max {}ms",
+            "Latency:".bold(),
+            latency.p50_ms,
+            latency.p90_ms,
+            latency.p99_ms,
+            latency.max_ms
+        );
+    }
     println!();
 
-    // Show mismatches and errors
-    let issues: Vec<_> = session
-        .results
-        .iter()
-        .filter(|r| !r.status_match || r.error.is_some())
-        .collect();
-
     if !issues.is_empty() {
         println!("  {}", "Issues".bold().underline());
         println!();
+        print_issues(&issues);
+    }
 
-        for result in issues {
-            let status_str = if result.error.is_some() {
-                "ERR".red().to_string()
-            } else if result.status >= 400 {
-                result.status.to_string().red().to_string()
-            } else {
-                result.status.to_string().yellow().to_string()
-            };
-
+    // Requests whose outcome varied between --repeat runs
+    let repeats = session.repeat_stats();
+    if !repeats.is_empty() {
+        println!("  {}", "Repeat stability".bold().underline());
+        println!();
+        let unstable: Vec<_> = repeats.iter().filter(|s| !s.is_stable()).collect();
+        if unstable.is_empty() {
+            println!(
+                "    {} All {} requests got the same status on every run",
+                "✓".green(),
+                repeats.len()
+            );
+            println!();
+        }
+        for stats in unstable {
             println!(
                 "    {} {} {}",
-                format!("#{}", result.request_index).dimmed(),
-                result.method.bold(),
-                truncate_url(&result.url, 40)
+                format!("#{}", stats.request_index).dimmed(),
+                stats.method.bold(),
+                truncate_url(&stats.url, 40)
             );
-
-            if let Some(ref error) = result.error {
-                println!("      {} {}", "Error:".red(), error);
-            } else {
-                let expected = result
-                    .expected_status
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| "?".to_string());
-                println!("      Expected: {}, Got: {}", expected.green(), status_str);
+            let mut outcomes: Vec<String> = stats
+                .statuses
+                .iter()
+                .map(|(&status, count)| format!("{}×{}", format_status(status), count))
+                .collect();
+            if stats.errors > 0 {
+                outcomes.push(format!("{}×{}", "ERR".red(), stats.errors));
+            }
+            println!("      {} {}", "Statuses:".dimmed(), outcomes.join(" "));
+            if let (Some(min), Some(median), Some(max)) =
+                (stats.min_ms, stats.median_ms, stats.max_ms)
+            {
+                println!(
+                    "      {} {}/{}/{}ms (min/median/max)",
+                    "Latency:".dimmed(),
+                    min,
+                    median,
+                    max
+                );
             }
             println!();
         }
@@ -89,8 +151,204 @@ pub fn print_replay_pretty(session: &ReplaySession) {
     println!("{}", "─".repeat(60).dimmed());
 }
 
+/// The Issues section's entries, one block per result
+fn print_issues(issues: &[&ReplayResult]) {
+    for result in issues {
+        let status_str = if result.error.is_some() {
+            "ERR".red().to_string()
+        } else if result.status >= 400 {
+            result.status.to_string().red().to_string()
+        } else {
+            result.status.to_string().yellow().to_string()
+        };
+
+        let index = match result.repeat_index {
+            Some(run) => format!("#{} run {}", result.request_index, run + 1),
+            None => format!("#{}", result.request_index),
+        };
+        match result.name {
+            Some(ref name) => println!(
+                "    {} {} {}",
+                index.dimmed(),
+                name.bold(),
+                format!("{} {}", result.method, truncate_url(&result.url, 40)).dimmed()
+            ),
+            None => println!(
+                "    {} {} {}",
+                index.dimmed(),
+                result.method.bold(),
+                truncate_url(&result.url, 40)
+            ),
+        }
+
+        if let Some(ref error) = result.error {
+            println!("      {} {}", "Error:".red(), error);
+        } else {
+            let expected = result
+                .expected_status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "?".to_string());
+            println!("      Expected: {}, Got: {}", expected.green(), status_str);
+        }
+        if result.attempts > 1 {
+            println!("      Attempts: {}", result.attempts);
+        }
+        println!();
+    }
+}
+
+/// Render one row per target session: counts and p95 latency side by side
+pub fn print_multi_target_table(sessions: &[ReplaySession]) -> String {
+    let headers = ["Target", "Requests", "OK", "Failed", "Mismatches", "p95"];
+    let rows: Vec<[String; 6]> = sessions
+        .iter()
+        .map(|s| {
+            [
+                s.target.clone(),
+                s.total_requests.to_string(),
+                s.successful.to_string(),
+                s.failed.to_string(),
+                s.status_mismatches.to_string(),
+                s.latency_percentile(95)
+                    .map(|p| format!("{}ms", p))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(str::len);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+
+    // Target left-aligned, numbers right-aligned
+    let format_row = |cells: &[&str]| {
+        cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, w))| {
+                if i == 0 {
+                    format!("{:<w$}", cell)
+                } else {
+                    format!("{:>w$}", cell)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    let mut out = String::new();
+    out.push_str(&format_row(&headers));
+    out.push('\n');
+    out.push_str(&"─".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1)));
+    out.push('\n');
+    for row in &rows {
+        let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+        out.push_str(&format_row(&cells));
+        out.push('\n');
+    }
+    out
+}
+
+/// Print benchmark latency and status distribution in pretty format
+pub fn print_bench_pretty(session: &ReplaySession) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "bench".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    println!("  {} {}", "URL:".bold(), session.target);
+    println!("  {} {}", "Requests:".bold(), session.total_requests);
+    println!("  {} {}", "Concurrency:".bold(), session.meta.concurrency);
+    println!(
+        "  {} {}",
+        "Successful:".bold(),
+        session.successful.to_string().green()
+    );
+    if session.failed > 0 {
+        println!(
+            "  {} {}",
+            "Failed:".bold(),
+            session.failed.to_string().red()
+        );
+    }
+    println!();
+
+    if let Some(latency) = session.latency_summary() {
+        println!("  {}", "Latency".bold().underline());
+        println!();
+        println!("    {:<6} {}ms", "min", latency.min_ms);
+        println!("    {:<6} {:.1}ms", "mean", latency.mean_ms);
+        println!("    {:<6} {}ms", "p50", latency.p50_ms);
+        println!("    {:<6} {}ms", "p90", latency.p90_ms);
+        println!("    {:<6} {}ms", "p95", latency.p95_ms);
+        println!("    {:<6} {}ms", "p99", latency.p99_ms);
+        println!("    {:<6} {}ms", "max", latency.max_ms);
+        println!();
+    }
+
+    let statuses = session.status_counts();
+    if !statuses.is_empty() {
+        println!("  {}", "Status codes".bold().underline());
+        println!();
+        for (status, count) in statuses {
+            println!("    {} {}", format_status(status), count);
+        }
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print benchmark summary as JSON
+pub fn print_bench_json(session: &ReplaySession) -> String {
+    let statuses: serde_json::Map<String, serde_json::Value> = session
+        .status_counts()
+        .into_iter()
+        .map(|(status, count)| (status.to_string(), count.into()))
+        .collect();
+    let report = serde_json::json!({
+        "url": session.target,
+        "requests": session.total_requests,
+        "concurrency": session.meta.concurrency,
+        "successful": session.successful,
+        "failed": session.failed,
+        "latency": session.latency_summary(),
+        "statuses": statuses,
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print benchmark summary in compact format
+pub fn print_bench_compact(session: &ReplaySession) -> String {
+    let mut parts = vec![format!(
+        "{}: {}/{}",
+        session.target, session.successful, session.total_requests
+    )];
+    if let Some(latency) = session.latency_summary() {
+        parts.push(format!(
+            "p50={}ms p95={}ms p99={}ms",
+            latency.p50_ms, latency.p95_ms, latency.p99_ms
+        ));
+    }
+    let statuses: Vec<String> = session
+        .status_counts()
+        .into_iter()
+        .map(|(status, count)| format!("{}={}", status, count))
+        .collect();
+    if !statuses.is_empty() {
+        parts.push(format!("statuses=[{}]", statuses.join(",")));
+    }
+    parts.join(" ")
+}
+
 /// Print diff summary in pretty format
-pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
+/// Print a diff for the terminal. `summary_only` stops after the headline
+/// counts (`diff --summary`).
+pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool, summary_only: bool) {
     println!();
     println!("{} {}", "ushio".bold().cyan(), "diff results".dimmed());
     println!("{}", "─".repeat(60).dimmed());
@@ -103,6 +361,20 @@ pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
 
     // Stats
     println!("  {} {}", "Total:".bold(), summary.total_requests);
+    if summary.ignored > 0 {
+        println!(
+            "  {} {}",
+            "Ignored:".bold(),
+            summary.ignored.to_string().dimmed()
+        );
+    }
+    if summary.stopped_early {
+        println!(
+            "  {} {}",
+            "Fail-fast:".bold(),
+            "stopped at first difference, remaining requests not compared".yellow()
+        );
+    }
     println!(
         "  {} {}",
         "Identical:".bold(),
@@ -115,6 +387,33 @@ pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
             summary.different.to_string().yellow()
         );
     }
+    if summary.status_diffs > 0 {
+        println!(
+            "  {} {}",
+            "Status diffs:".bold(),
+            summary.status_diffs.to_string().yellow()
+        );
+        let critical = summary
+            .diffs
+            .iter()
+            .filter_map(|d| d.status_diff.as_ref())
+            .filter(|s| s.severity == StatusSeverity::Critical)
+            .count();
+        if critical > 0 {
+            println!(
+                "    {} {}",
+                "critical:".dimmed(),
+                critical.to_string().red()
+            );
+        }
+    }
+    if summary.header_diffs > 0 {
+        println!(
+            "  {} {}",
+            "Header diffs:".bold(),
+            summary.header_diffs.to_string().yellow()
+        );
+    }
     if summary.body_diffs > 0 {
         println!(
             "  {} {}",
@@ -122,6 +421,13 @@ pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
             summary.body_diffs.to_string().yellow()
         );
     }
+    if summary.trailer_diffs > 0 {
+        println!(
+            "  {} {}",
+            "Trailer diffs:".bold(),
+            summary.trailer_diffs.to_string().yellow()
+        );
+    }
     if summary.waf_diffs > 0 {
         println!(
             "  {} {}",
@@ -129,8 +435,69 @@ pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
             summary.waf_diffs.to_string().red()
         );
     }
+    if summary.latency_diffs > 0 {
+        println!(
+            "  {} {}",
+            "Latency diffs:".bold(),
+            summary.latency_diffs.to_string().yellow()
+        );
+    }
+    if summary.size_diffs > 0 {
+        println!(
+            "  {} {}",
+            "Size diffs:".bold(),
+            summary.size_diffs.to_string().yellow()
+        );
+    }
+    if summary.version_diffs > 0 {
+        println!(
+            "  {} {}",
+            "HTTP version diffs:".bold(),
+            summary.version_diffs.to_string().yellow()
+        );
+    }
+    if summary.tls_diffs > 0 {
+        println!(
+            "  {} {}",
+            "TLS diffs:".bold(),
+            summary.tls_diffs.to_string().yellow()
+        );
+    }
+    if summary_only {
+        for (label, requests) in [
+            ("Only in left:", &summary.unmatched_left),
+            ("Only in right:", &summary.unmatched_right),
+        ] {
+            if !requests.is_empty() {
+                println!("  {} {}", label.bold(), requests.len().to_string().yellow());
+            }
+        }
+        println!();
+        println!("{}", "─".repeat(60).dimmed());
+        return;
+    }
     println!();
 
+    // Backend technology changes, aggregated across requests
+    if !summary.tech_stack_changes.is_empty() {
+        println!("  {}", "Backend technology".bold().underline());
+        println!();
+        for change in &summary.tech_stack_changes {
+            let label = match change.kind {
+                TechChangeKind::BackendChanged => "changed".yellow(),
+                TechChangeKind::VersionChanged => "version".dimmed(),
+            };
+            println!(
+                "    {} {} → {} {}",
+                label,
+                change.left.describe(),
+                change.right.describe().bold(),
+                format!("({} requests)", change.requests).dimmed()
+            );
+        }
+        println!();
+    }
+
     // Show differences
     if !summary.diffs.is_empty() {
         println!("  {}", "Differences".bold().underline());
@@ -139,7 +506,28 @@ pub fn print_diff_pretty(summary: &DiffSummary, only_diff: bool) {
         for diff in &summary.diffs {
             print_request_diff(diff);
         }
-    } else if !only_diff {
+    }
+
+    // Results without a counterpart (--match-by url)
+    let unmatched = [
+        ("Only in left", &summary.unmatched_left),
+        ("Only in right", &summary.unmatched_right),
+    ];
+    for (title, requests) in unmatched.iter().filter(|(_, r)| !r.is_empty()) {
+        println!("  {}", title.bold().underline());
+        println!();
+        for req in requests.iter() {
+            println!(
+                "    {} {} {}",
+                format!("#{}", req.request_index).dimmed(),
+                req.method.bold(),
+                truncate_url(&req.url, 60)
+            );
+        }
+        println!();
+    }
+
+    if summary.different == 0 && !only_diff {
         println!("  {} No differences found", "✓".green());
         println!();
     }
@@ -160,7 +548,13 @@ fn print_request_diff(diff: &RequestDiff) {
     if let Some(ref status) = diff.status_diff {
         let left_str = format_status(status.left);
         let right_str = format_status(status.right);
-        println!("      {} {} → {}", "Status:".dimmed(), left_str, right_str);
+        println!(
+            "      {} {} → {} {}",
+            "Status:".dimmed(),
+            left_str,
+            right_str,
+            format_transition(status)
+        );
     }
 
     // WAF diff
@@ -180,59 +574,758 @@ fn print_request_diff(diff: &RequestDiff) {
         if let Some(ref reason) = waf.left_reason {
             println!("        {} {}", "Left:".dimmed(), reason);
         }
-        if let Some(ref reason) = waf.right_reason {
-            println!("        {} {}", "Right:".dimmed(), reason);
+        if let Some(ref reason) = waf.right_reason {
+            println!("        {} {}", "Right:".dimmed(), reason);
+        }
+    }
+
+    // Latency regression
+    if let Some(ref latency) = diff.latency_diff {
+        println!(
+            "      {} {}ms → {}ms {}",
+            "Latency:".dimmed(),
+            latency.left_ms,
+            latency.right_ms,
+            format!("(+{}ms)", latency.delta_ms).yellow()
+        );
+    }
+
+    // Negotiated protocol
+    if let Some(ref version) = diff.version_diff {
+        println!(
+            "      {} {} → {}",
+            "HTTP version:".dimmed(),
+            version.left,
+            version.right.yellow()
+        );
+    }
+
+    // Negotiated TLS parameters
+    if let Some(ref tls) = diff.tls_diff {
+        println!(
+            "      {} {} → {}",
+            "TLS:".dimmed(),
+            describe_tls(&tls.left_version, tls.left_cipher.as_deref()),
+            describe_tls(&tls.right_version, tls.right_cipher.as_deref()).yellow()
+        );
+    }
+
+    // Body size change
+    if let Some(ref size) = diff.size_diff {
+        println!(
+            "      {} {} → {} bytes {}",
+            "Size:".dimmed(),
+            size.left_bytes,
+            size.right_bytes,
+            format!("({:+})", size.delta_bytes).yellow()
+        );
+    }
+
+    // Backend technology diff
+    if let Some(ref tech) = diff.tech_stack_diff {
+        println!(
+            "      {} {} → {}",
+            "Backend:".dimmed(),
+            tech.left.describe(),
+            tech.right.describe()
+        );
+    }
+
+    // Body diff
+    if let Some(ref body) = diff.body_diff {
+        print_body_diff(body);
+    }
+
+    // Header and trailer diffs
+    let trailers = diff.trailer_diffs.iter().map(|t| (t, "trailer "));
+    for (header, kind) in diff.header_diffs.iter().map(|h| (h, "")).chain(trailers) {
+        let change = match header.diff_type {
+            HeaderDiffType::Added => "+".green().to_string(),
+            HeaderDiffType::Removed => "-".red().to_string(),
+            HeaderDiffType::Changed => "~".yellow().to_string(),
+        };
+
+        let left = header.left.as_deref().unwrap_or("-");
+        let right = header.right.as_deref().unwrap_or("-");
+
+        println!(
+            "      {} {}{} {} → {}",
+            change,
+            kind.dimmed(),
+            header.name.dimmed(),
+            truncate(left, 20),
+            truncate(right, 20)
+        );
+    }
+
+    println!();
+}
+
+/// Print body diff with colored unified output
+fn print_body_diff(body: &BodyDiff) {
+    println!(
+        "      {} {} bytes → {} bytes",
+        "Body:".dimmed(),
+        body.left_size,
+        body.right_size
+    );
+    if !body.json_changes.is_empty() {
+        for change in body.json_changes.iter().take(20) {
+            let value = |v: &Option<serde_json::Value>| {
+                v.as_ref()
+                    .map_or("-".to_string(), |v| truncate(&v.to_string(), 30))
+            };
+            let line = format!(
+                "{} {} → {}",
+                change.path,
+                value(&change.left),
+                value(&change.right)
+            );
+            match change.kind {
+                JsonChangeKind::Added => println!("        {}", format!("+ {}", line).green()),
+                JsonChangeKind::Removed => println!("        {}", format!("- {}", line).red()),
+                JsonChangeKind::Changed => println!("        {}", format!("~ {}", line).yellow()),
+            }
+        }
+        if body.json_changes.len() > 20 {
+            println!(
+                "        {}",
+                format!("... {} more paths", body.json_changes.len() - 20).dimmed()
+            );
+        }
+        return;
+    }
+    for line in body.unified_diff.lines().take(20) {
+        if let Some(rest) = line.strip_prefix('+') {
+            println!("        {}", format!("+{}", rest).green());
+        } else if let Some(rest) = line.strip_prefix('-') {
+            println!("        {}", format!("-{}", rest).red());
+        } else {
+            // context line — skip to keep output focused
+        }
+    }
+}
+
+/// Print a status code matrix across sessions, one column per target.
+/// The odd one out in a diverging row is marked with `*`.
+pub fn print_multi_diff_pretty(summary: &MultiDiffSummary, only_diff: bool) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "multi-target diff".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    for (i, target) in summary.targets.iter().enumerate() {
+        println!("  {} {}", format!("[{}]", i + 1).bold(), target);
+    }
+    println!();
+
+    println!("  {} {}", "Total:".bold(), summary.total_requests);
+    println!(
+        "  {} {}",
+        "Agreeing:".bold(),
+        summary.agreeing.to_string().green()
+    );
+    if summary.diverging > 0 {
+        println!(
+            "  {} {}",
+            "Diverging:".bold(),
+            summary.diverging.to_string().yellow()
+        );
+    }
+    for (i, count) in summary.odd_one_out_counts.iter().enumerate() {
+        if *count > 0 {
+            println!(
+                "  {} [{}] in {} request(s)",
+                "Odd one out:".bold(),
+                i + 1,
+                count.to_string().red()
+            );
+        }
+    }
+    println!();
+
+    // Each cell is a right-aligned status plus a one-character marker
+    const CELL: usize = 5;
+    let columns: Vec<String> = (1..=summary.targets.len())
+        .map(|i| format!("{:>w$} ", format!("[{}]", i), w = CELL - 1))
+        .collect();
+    let header = format!(
+        "{:>5}  {:<7} {:<40}  {}",
+        "#",
+        "Method",
+        "URL",
+        columns.join(" ")
+    );
+    println!("  {}", header.dimmed());
+    for row in summary.rows.iter().filter(|r| !only_diff || !r.agree) {
+        let cells: Vec<String> = row
+            .statuses
+            .iter()
+            .enumerate()
+            .map(|(i, status)| {
+                let text = status.map_or("-".to_string(), |s| s.to_string());
+                let pad = " ".repeat((CELL - 1).saturating_sub(text.len()));
+                let text = match status {
+                    Some(s) if !row.agree => format_status(*s),
+                    Some(_) => text.dimmed().to_string(),
+                    None => text.red().to_string(),
+                };
+                let marker = if row.odd_one_out == Some(i) {
+                    "*".red().bold().to_string()
+                } else {
+                    " ".to_string()
+                };
+                format!("{}{}{}", pad, text, marker)
+            })
+            .collect();
+        println!(
+            "  {:>5}  {:<7} {:<40}  {}",
+            row.request_index,
+            row.method,
+            truncate_url(&row.url, 40),
+            cells.join(" ")
+        );
+    }
+    println!();
+
+    if summary.diverging == 0 {
+        println!("  {} All targets agree", "✓".green());
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print a multi-target diff as JSON
+pub fn print_multi_diff_json(summary: &MultiDiffSummary) -> String {
+    serde_json::to_string_pretty(summary).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print a multi-target diff in compact format
+pub fn print_multi_diff_compact(summary: &MultiDiffSummary) -> String {
+    let status = if summary.diverging == 0 {
+        "SAME"
+    } else {
+        "DIFF"
+    };
+    let line = format!(
+        "{}: {} agreeing={} diverging={}",
+        summary.targets.join(" vs "),
+        status,
+        summary.agreeing,
+        summary.diverging
+    );
+    let odd: Vec<String> = summary
+        .targets
+        .iter()
+        .zip(&summary.odd_one_out_counts)
+        .filter(|(_, count)| **count > 0)
+        .map(|(target, count)| format!("{}:{}", target, count))
+        .collect();
+    if odd.is_empty() {
+        line
+    } else {
+        format!("{} odd_one_out=[{}]", line, odd.join(","))
+    }
+}
+
+/// Print capture diff in pretty format
+pub fn print_capture_diff_pretty(diff: &CaptureDiff, left: &str, right: &str) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "capture diff".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    println!("  {} {}", "Left:".bold(), left);
+    println!("  {} {}", "Right:".bold(), right);
+    println!();
+
+    println!(
+        "  {} {}",
+        "Unchanged:".bold(),
+        diff.unchanged.to_string().green()
+    );
+    if !diff.added.is_empty() {
+        println!(
+            "  {} {}",
+            "Added:".bold(),
+            diff.added.len().to_string().green()
+        );
+    }
+    if !diff.removed.is_empty() {
+        println!(
+            "  {} {}",
+            "Removed:".bold(),
+            diff.removed.len().to_string().red()
+        );
+    }
+    if !diff.changed.is_empty() {
+        println!(
+            "  {} {}",
+            "Changed:".bold(),
+            diff.changed.len().to_string().yellow()
+        );
+    }
+    println!();
+
+    if diff.is_empty() {
+        println!("  {} Captures send identical requests", "✓".green());
+        println!();
+    } else {
+        println!("  {}", "Differences".bold().underline());
+        println!();
+
+        for req in &diff.added {
+            println!(
+                "    {} {} {}",
+                "+".green(),
+                req.method.bold(),
+                truncate_url(&req.url, 50)
+            );
+        }
+        for req in &diff.removed {
+            println!(
+                "    {} {} {}",
+                "-".red(),
+                req.method.bold(),
+                truncate_url(&req.url, 50)
+            );
+        }
+        for req in &diff.changed {
+            println!(
+                "    {} {} {} {}",
+                "~".yellow(),
+                req.method.bold(),
+                truncate_url(&req.url, 50),
+                format!("({})", req.fields.join(", ")).dimmed()
+            );
+        }
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print capture diff as JSON
+pub fn print_capture_diff_json(diff: &CaptureDiff) -> String {
+    serde_json::to_string_pretty(diff).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print capture diff in compact format
+pub fn print_capture_diff_compact(diff: &CaptureDiff, left: &str, right: &str) -> String {
+    format!(
+        "{} vs {}: {} unchanged={} added={} removed={} changed={}",
+        left,
+        right,
+        if diff.is_empty() { "SAME" } else { "DIFF" },
+        diff.unchanged,
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    )
+}
+
+/// Print capture validation report in pretty format
+pub fn print_validation_pretty(report: &ValidationReport) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "validate".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    println!("  {} {}", "Capture:".bold(), report.path);
+    println!("  {} {}", "Requests:".bold(), report.total_requests);
+    println!();
+
+    if report.diagnostics.is_empty() {
+        println!("  {} Capture is valid", "✓".green());
+        println!();
+        return;
+    }
+
+    for diagnostic in &report.diagnostics {
+        let label = match diagnostic.severity {
+            Severity::Error => "error".red().bold(),
+            Severity::Warning => "warning".yellow().bold(),
+        };
+        println!(
+            "    {} {}: {}",
+            label,
+            diagnostic.location.bold(),
+            diagnostic.message
+        );
+    }
+    println!();
+
+    let errors = report.error_count();
+    let warnings = report.warning_count();
+    if errors > 0 {
+        println!(
+            "  {} {} errors, {} warnings",
+            "✗".red(),
+            errors.to_string().red(),
+            warnings
+        );
+    } else {
+        println!(
+            "  {} Capture is valid ({} warnings)",
+            "✓".green(),
+            warnings.to_string().yellow()
+        );
+    }
+    println!();
+}
+
+/// Print capture validation report in JSON format
+pub fn print_validation_json(report: &ValidationReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print capture validation report in compact format
+pub fn print_validation_compact(report: &ValidationReport) -> String {
+    format!(
+        "{}: {} requests={} errors={} warnings={}",
+        report.path,
+        if report.is_valid() {
+            "VALID"
+        } else {
+            "INVALID"
+        },
+        report.total_requests,
+        report.error_count(),
+        report.warning_count()
+    )
+}
+
+/// Print order-sensitivity report in pretty format
+pub fn print_order_sensitivity_pretty(report: &OrderSensitivityReport) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "order sensitivity".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    println!("  {} {}", "Target:".bold(), report.target);
+    println!("  {} {}", "Requests:".bold(), report.total_requests);
+    println!(
+        "  {} {}",
+        "Order-sensitive:".bold(),
+        if report.requests.is_empty() {
+            "0".green().to_string()
+        } else {
+            report.requests.len().to_string().yellow().to_string()
+        }
+    );
+    println!();
+
+    if report.requests.is_empty() {
+        println!("  {} Same statuses in both orderings", "✓".green());
+        println!();
+    } else {
+        println!("  {}", "Order-dependent requests".bold().underline());
+        println!();
+        for req in &report.requests {
+            println!(
+                "    {} {} {}",
+                format!("#{}", req.request_index).dimmed(),
+                req.method.bold(),
+                truncate_url(&req.url, 40)
+            );
+            println!(
+                "      {} {} → {}",
+                "Forward → reversed:".dimmed(),
+                format_status(req.forward_status),
+                format_status(req.reversed_status)
+            );
+            println!();
+        }
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print order-sensitivity report as JSON
+pub fn print_order_sensitivity_json(report: &OrderSensitivityReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print order-sensitivity report in compact format
+pub fn print_order_sensitivity_compact(report: &OrderSensitivityReport) -> String {
+    let indices: Vec<String> = report
+        .order_sensitive_indices
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    format!(
+        "{}: order_sensitive={}/{} indices=[{}]",
+        report.target,
+        report.requests.len(),
+        report.total_requests,
+        indices.join(",")
+    )
+}
+
+/// Print cache warm-and-verify report in pretty format
+pub fn print_cache_warm_pretty(report: &CacheWarmReport) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "cache warm".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    let ratio = format!(
+        "{:.1}% (min {:.1}%)",
+        report.hit_ratio * 100.0,
+        report.min_hit_ratio * 100.0
+    );
+    println!("  {} {}", "Target:".bold(), report.target);
+    println!("  {} {}", "Requests:".bold(), report.total_requests);
+    println!(
+        "  {} {}/{}",
+        "Hits:".bold(),
+        report.hits,
+        report.total_requests
+    );
+    println!(
+        "  {} {}",
+        "Hit ratio:".bold(),
+        if report.passed {
+            ratio.green().to_string()
+        } else {
+            ratio.red().to_string()
+        }
+    );
+    println!();
+
+    if report.misses.is_empty() {
+        println!("  {} Every request was a cache hit", "✓".green());
+        println!();
+    } else {
+        println!("  {}", "Not cached on second pass".bold().underline());
+        println!();
+        for miss in &report.misses {
+            println!(
+                "    {} {} {} {}",
+                format!("#{}", miss.request_index).dimmed(),
+                miss.method.bold(),
+                truncate_url(&miss.url, 40),
+                miss.cache_status
+                    .as_deref()
+                    .unwrap_or("no cache header")
+                    .yellow()
+            );
+        }
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print cache warm-and-verify report as JSON
+pub fn print_cache_warm_json(report: &CacheWarmReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print cache warm-and-verify report in compact format
+pub fn print_cache_warm_compact(report: &CacheWarmReport) -> String {
+    format!(
+        "{}: hits={}/{} ratio={:.3} min={:.3} {}",
+        report.target,
+        report.hits,
+        report.total_requests,
+        report.hit_ratio,
+        report.min_hit_ratio,
+        if report.passed { "PASS" } else { "FAIL" }
+    )
+}
+
+/// Compression ratio as a percentage, or "-" when nothing was measured
+fn format_ratio(ratio: Option<f64>) -> String {
+    ratio
+        .map(|r| format!("{:.1}%", r * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Print compression check in pretty format
+pub fn print_compression_pretty(comparison: &CompressionComparison) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "compression check".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    for report in &comparison.targets {
+        println!("  {} {}", "Target:".bold(), report.target);
+        println!(
+            "  {} {} → {} bytes ({})",
+            "Identity → gzip:".bold(),
+            report.uncompressed_size,
+            report.compressed_size,
+            format_ratio(report.ratio)
+        );
+        for req in &report.requests {
+            println!(
+                "    {} {} {} {} → {} ({}{})",
+                format!("#{}", req.request_index).dimmed(),
+                req.method.bold(),
+                truncate_url(&req.url, 40),
+                req.uncompressed_size,
+                req.compressed_size,
+                format_ratio(req.ratio),
+                req.content_encoding
+                    .as_deref()
+                    .map(|e| format!(", {}", e))
+                    .unwrap_or_default()
+                    .dimmed()
+            );
+        }
+        println!();
+    }
+
+    if comparison.targets.len() > 1 {
+        if comparison.drifts.is_empty() {
+            println!("  {} Compression ratios match across targets", "✓".green());
+        } else {
+            for drift in &comparison.drifts {
+                println!(
+                    "  {} {} compresses to {} vs {} on {} (requests: {})",
+                    "⚠".yellow(),
+                    drift.target.bold(),
+                    format_ratio(drift.ratio).yellow(),
+                    format_ratio(drift.baseline_ratio),
+                    drift.baseline_target,
+                    drift
+                        .request_indices
+                        .iter()
+                        .map(|i| format!("#{}", i))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print compression check as JSON
+pub fn print_compression_json(comparison: &CompressionComparison) -> String {
+    serde_json::to_string_pretty(comparison).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print compression check in compact format, one line per target
+pub fn print_compression_compact(comparison: &CompressionComparison) -> String {
+    comparison
+        .targets
+        .iter()
+        .map(|report| {
+            let drifted = comparison.drifts.iter().any(|d| d.target == report.target);
+            format!(
+                "{}: identity={} gzip={} ratio={}{}",
+                report.target,
+                report.uncompressed_size,
+                report.compressed_size,
+                report
+                    .ratio
+                    .map(|r| format!("{:.3}", r))
+                    .unwrap_or_else(|| "-".to_string()),
+                if drifted { " DRIFT" } else { "" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Print Accept sweep report in pretty format
+pub fn print_accept_sweep_pretty(report: &AcceptSweepReport) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "accept sweep".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+
+    println!("  {} {}", "Target:".bold(), report.target);
+    println!("  {} {}", "Accept:".bold(), report.accept_values.join(", "));
+    println!("  {} {}", "Requests:".bold(), report.total_requests);
+    println!(
+        "  {} {}",
+        "Negotiated:".bold(),
+        if report.requests.is_empty() {
+            "0".green().to_string()
+        } else {
+            report.requests.len().to_string().yellow().to_string()
+        }
+    );
+    println!();
+
+    if report.requests.is_empty() {
+        println!(
+            "  {} Same status and content type for every Accept value",
+            "✓".green()
+        );
+        println!();
+    } else {
+        println!("  {}", "Requests that vary by Accept".bold().underline());
+        println!();
+        for req in &report.requests {
+            println!(
+                "    {} {} {}",
+                format!("#{}", req.request_index).dimmed(),
+                req.method.bold(),
+                truncate_url(&req.url, 40)
+            );
+            for v in &req.variants {
+                println!(
+                    "      {:<24} {} {}",
+                    truncate(&v.accept, 24).dimmed(),
+                    format_status(v.status),
+                    v.content_type.as_deref().unwrap_or("-")
+                );
+            }
+            println!();
         }
     }
 
-    // Body diff
-    if let Some(ref body) = diff.body_diff {
-        print_body_diff(body);
-    }
+    println!("{}", "─".repeat(60).dimmed());
+}
 
-    // Header diffs
-    for header in &diff.header_diffs {
-        let change = match header.diff_type {
-            HeaderDiffType::Added => "+".green().to_string(),
-            HeaderDiffType::Removed => "-".red().to_string(),
-            HeaderDiffType::Changed => "~".yellow().to_string(),
-        };
+/// Print Accept sweep report as JSON
+pub fn print_accept_sweep_json(report: &AcceptSweepReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string())
+}
 
-        let left = header.left.as_deref().unwrap_or("-");
-        let right = header.right.as_deref().unwrap_or("-");
+/// Print Accept sweep report in compact format
+pub fn print_accept_sweep_compact(report: &AcceptSweepReport) -> String {
+    let indices: Vec<String> = report
+        .changed_indices
+        .iter()
+        .map(|i| i.to_string())
+        .collect();
+    format!(
+        "{}: negotiated={}/{} indices=[{}]",
+        report.target,
+        report.requests.len(),
+        report.total_requests,
+        indices.join(",")
+    )
+}
 
-        println!(
-            "      {} {} {} → {}",
-            change,
-            header.name.dimmed(),
-            truncate(left, 20),
-            truncate(right, 20)
-        );
+/// Format status code with color
+/// "TLS 1.3 (TLS13_AES_256_GCM_SHA384)"
+fn describe_tls(version: &str, cipher: Option<&str>) -> String {
+    match cipher {
+        Some(cipher) => format!("{} ({})", version, cipher),
+        None => version.to_string(),
     }
-
-    println!();
 }
 
-/// Print body diff with colored unified output
-fn print_body_diff(body: &BodyDiff) {
-    println!(
-        "      {} {} bytes → {} bytes",
-        "Body:".dimmed(),
-        body.left_size,
-        body.right_size
-    );
-    for line in body.unified_diff.lines().take(20) {
-        if let Some(rest) = line.strip_prefix('+') {
-            println!("        {}", format!("+{}", rest).green());
-        } else if let Some(rest) = line.strip_prefix('-') {
-            println!("        {}", format!("-{}", rest).red());
-        } else {
-            // context line — skip to keep output focused
-        }
+/// Status class transition, colored by severity
+fn format_transition(status: &StatusDiff) -> String {
+    let label = format!("({})", status.transition());
+    match status.severity {
+        StatusSeverity::Critical => label.red().bold().to_string(),
+        StatusSeverity::Major => label.yellow().to_string(),
+        StatusSeverity::Notable => label.cyan().to_string(),
+        StatusSeverity::Cosmetic => label.dimmed().to_string(),
     }
 }
 
-/// Format status code with color
 fn format_status(status: u16) -> String {
     if status == 0 {
         "N/A".dimmed().to_string()
@@ -247,6 +1340,73 @@ fn format_status(status: u16) -> String {
     }
 }
 
+/// Serialized forms of a replay session, shared by stdout and `--output` files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionFormat {
+    /// The session itself, loadable by `ushio diff`
+    Json,
+    /// One summary line
+    Compact,
+    /// JUnit XML, one test case per request
+    Junit,
+    /// One compact JSON result per line
+    Jsonl,
+    /// One row per result, for spreadsheets
+    Csv,
+}
+
+/// Render a replay session in `format`, ending with a newline
+pub fn render_session(session: &ReplaySession, format: SessionFormat) -> String {
+    match format {
+        SessionFormat::Json => format!("{}\n", print_replay_json(session)),
+        SessionFormat::Compact => format!("{}\n", print_replay_compact(session)),
+        SessionFormat::Junit => print_replay_junit(session),
+        SessionFormat::Jsonl => session
+            .results
+            .iter()
+            .map(|result| format!("{}\n", print_result_jsonl(result)))
+            .collect(),
+        SessionFormat::Csv => print_replay_csv(session),
+    }
+}
+
+/// Print replay results as CSV (RFC 4180), one row per request
+pub fn print_replay_csv(session: &ReplaySession) -> String {
+    let mut csv = String::from(
+        "request_index,method,url,status,expected_status,status_match,body_size,duration_ms,error\r\n",
+    );
+    for r in &session.results {
+        let row = [
+            r.request_index.to_string(),
+            csv_field(&r.method),
+            csv_field(&r.url),
+            r.status.to_string(),
+            r.expected_status.map_or(String::new(), |s| s.to_string()),
+            r.status_match.to_string(),
+            r.body_size.to_string(),
+            r.duration_ms.to_string(),
+            csv_field(r.error.as_deref().unwrap_or("")),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Quote a CSV field when it contains a delimiter, quote, or line break
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Print one replay result as a single line of JSON
+pub fn print_result_jsonl(result: &ReplayResult) -> String {
+    serde_json::to_string(result).unwrap_or_else(|_| "{}".to_string())
+}
+
 /// Print replay session as JSON
 pub fn print_replay_json(session: &ReplaySession) -> String {
     serde_json::to_string_pretty(session).unwrap_or_else(|_| "{}".to_string())
@@ -257,6 +1417,43 @@ pub fn print_diff_json(summary: &DiffSummary) -> String {
     serde_json::to_string_pretty(summary).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// The counts of a replay session, for `--report` files that CI parses
+/// whatever the main output format
+pub fn print_replay_report(session: &ReplaySession) -> String {
+    let report = serde_json::json!({
+        "target": session.target,
+        "total": session.total_requests,
+        "successful": session.successful,
+        "failed": session.failed,
+        "mismatches": session.status_mismatches,
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// The counts of a diff, without the per-request detail, for `--report` files
+pub fn print_diff_report(summary: &DiffSummary) -> String {
+    let report = serde_json::json!({
+        "left": summary.left_target,
+        "right": summary.right_target,
+        "total": summary.total_requests,
+        "identical": summary.identical,
+        "different": summary.different,
+        "status_diffs": summary.status_diffs,
+        "header_diffs": summary.header_diffs,
+        "trailer_diffs": summary.trailer_diffs,
+        "body_diffs": summary.body_diffs,
+        "waf_diffs": summary.waf_diffs,
+        "latency_diffs": summary.latency_diffs,
+        "size_diffs": summary.size_diffs,
+        "version_diffs": summary.version_diffs,
+        "tls_diffs": summary.tls_diffs,
+        "unmatched": summary.unmatched_left.len() + summary.unmatched_right.len(),
+        "ignored": summary.ignored,
+        "stopped_early": summary.stopped_early,
+    });
+    serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string())
+}
+
 /// Print replay session in compact format
 pub fn print_replay_compact(session: &ReplaySession) -> String {
     let mut parts = vec![format!(
@@ -270,6 +1467,20 @@ pub fn print_replay_compact(session: &ReplaySession) -> String {
     if session.status_mismatches > 0 {
         parts.push(format!("mismatches={}", session.status_mismatches));
     }
+    let statuses: Vec<String> = session
+        .status_counts()
+        .into_iter()
+        .map(|(status, count)| format!("{}:{}", status, count))
+        .collect();
+    if !statuses.is_empty() {
+        parts.push(format!("status={}", statuses.join(",")));
+    }
+    if let Some(ref latency) = session.latency {
+        parts.push(format!(
+            "p50={}ms p90={}ms p99={}ms max={}ms",
+            latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+        ));
+    }
 
     parts.join(" ")
 }
@@ -284,7 +1495,7 @@ pub fn print_diff_compact(summary: &DiffSummary) -> String {
         "DIFF"
     };
 
-    format!(
+    let line = format!(
         "{} vs {}: {} identical={} different={} body={} waf={}",
         summary.left_target,
         summary.right_target,
@@ -293,7 +1504,119 @@ pub fn print_diff_compact(summary: &DiffSummary) -> String {
         summary.different,
         summary.body_diffs,
         summary.waf_diffs
-    )
+    );
+    let line = if summary.latency_diffs > 0 {
+        format!("{} latency={}", line, summary.latency_diffs)
+    } else {
+        line
+    };
+    let line = if summary.size_diffs > 0 {
+        format!("{} size={}", line, summary.size_diffs)
+    } else {
+        line
+    };
+    let line = if summary.version_diffs > 0 {
+        format!("{} version={}", line, summary.version_diffs)
+    } else {
+        line
+    };
+    let line = if summary.tls_diffs > 0 {
+        format!("{} tls={}", line, summary.tls_diffs)
+    } else {
+        line
+    };
+    let line = if summary.unmatched_left.is_empty() && summary.unmatched_right.is_empty() {
+        line
+    } else {
+        format!(
+            "{} unmatched_left={} unmatched_right={}",
+            line,
+            summary.unmatched_left.len(),
+            summary.unmatched_right.len()
+        )
+    };
+    let line = if summary.ignored > 0 {
+        format!("{} ignored={}", line, summary.ignored)
+    } else {
+        line
+    };
+    if summary.stopped_early {
+        format!("{} stopped_early", line)
+    } else {
+        line
+    }
+}
+
+/// Print a dry-run plan: each request's final method, URL, and headers.
+/// `indices` are the requests' positions in the capture.
+pub fn print_plan_pretty(plan: &ReplayPlan, indices: &[usize]) {
+    println!();
+    println!("{} {}", "ushio".bold().cyan(), "dry run".dimmed());
+    println!("{}", "─".repeat(60).dimmed());
+    println!();
+    println!("  {} {}", "Target:".bold(), plan.target);
+    println!("  {} {}", "Requests:".bold(), plan.requests.len());
+    println!();
+
+    for (i, request) in plan.requests.iter().enumerate() {
+        let index = indices.get(i).copied().unwrap_or(i);
+        println!(
+            "    {} {} {}",
+            format!("#{}", index).dimmed(),
+            request.method.bold(),
+            request.url
+        );
+        for (name, value) in &request.headers {
+            println!("      {}: {}", name.dimmed(), value);
+        }
+        match request.body {
+            Some(Body::Inline(ref text)) => {
+                println!("      {}", format!("({} byte body)", text.len()).dimmed());
+            }
+            Some(Body::File { ref file }) => {
+                println!(
+                    "      {}",
+                    format!("(body from {})", file.display()).dimmed()
+                );
+            }
+            None => {}
+        }
+        println!();
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+}
+
+/// Print a dry-run plan as JSON, in the `--plan-out` format, with each
+/// request's capture index added as `request_index`
+pub fn print_plan_json(plan: &ReplayPlan, indices: &[usize]) -> String {
+    let Ok(mut value) = serde_json::to_value(plan) else {
+        return "{}".to_string();
+    };
+    if let Some(requests) = value["requests"].as_array_mut() {
+        for (i, request) in requests.iter_mut().enumerate() {
+            request["request_index"] = indices.get(i).copied().unwrap_or(i).into();
+        }
+    }
+    serde_json::to_string_pretty(&value).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Print a dry-run plan with one line per request
+pub fn print_plan_compact(plan: &ReplayPlan, indices: &[usize]) -> String {
+    plan.requests
+        .iter()
+        .enumerate()
+        .map(|(i, request)| {
+            format!(
+                "#{} {} {} headers={}",
+                indices.get(i).copied().unwrap_or(i),
+                request.method,
+                request.url,
+                request.headers.len()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Print replay session as JUnit XML for CI integration
@@ -359,6 +1682,66 @@ pub fn print_replay_junit(session: &ReplaySession) -> String {
     xml
 }
 
+/// Build Chrome Trace Event records for a replay session.
+///
+/// Each request becomes a complete (`"X"`) event on process `pid`. Overlapping
+/// requests are spread over separate lanes (`tid`) so concurrent replays
+/// render cleanly in `chrome://tracing` and Perfetto.
+pub fn replay_trace_events(session: &ReplaySession, pid: usize) -> Vec<serde_json::Value> {
+    let mut events = vec![serde_json::json!({
+        "name": "process_name",
+        "ph": "M",
+        "pid": pid,
+        "args": { "name": session.target },
+    })];
+
+    let mut order: Vec<_> = session.results.iter().collect();
+    order.sort_by_key(|r| (r.start_offset_ms, r.request_index));
+
+    // End time of the last event placed on each lane
+    let mut lanes: Vec<u64> = Vec::new();
+    for result in order {
+        let start = result.start_offset_ms;
+        let end = start + result.duration_ms;
+        let tid = match lanes.iter().position(|&lane_end| lane_end <= start) {
+            Some(i) => {
+                lanes[i] = end;
+                i
+            }
+            None => {
+                lanes.push(end);
+                lanes.len() - 1
+            }
+        };
+
+        events.push(serde_json::json!({
+            "name": format!("{} {}", result.method, result.url),
+            "cat": "replay",
+            "ph": "X",
+            "ts": start * 1000,
+            "dur": result.duration_ms * 1000,
+            "pid": pid,
+            "tid": tid,
+            "args": {
+                "request_index": result.request_index,
+                "status": result.status,
+                "error": result.error,
+            },
+        }));
+    }
+
+    events
+}
+
+/// Print trace events as a Trace Event Format JSON document
+pub fn print_trace_events(events: &[serde_json::Value]) -> String {
+    serde_json::to_string_pretty(&serde_json::json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    }))
+    .unwrap_or_else(|_| "{}".to_string())
+}
+
 /// Print diff summary as JUnit XML
 pub fn print_diff_junit(summary: &DiffSummary) -> String {
     let mut xml = String::new();
@@ -368,18 +1751,58 @@ pub fn print_diff_junit(summary: &DiffSummary) -> String {
         summary.total_requests, summary.different
     ));
 
-    for i in 0..summary.total_requests {
-        let diff = summary.diffs.iter().find(|d| d.request_index == i);
-        match diff {
-            Some(d) => {
+    // Compared and left-only requests by index, then results found only on the right
+    enum Case<'a> {
+        Diff(&'a RequestDiff),
+        Identical,
+        LeftOnly(&'a UnmatchedRequest),
+    }
+    let mut cases: Vec<(usize, Case)> = summary
+        .diffs
+        .iter()
+        .map(|d| (d.request_index, Case::Diff(d)))
+        .chain(
+            summary
+                .identical_indices
+                .iter()
+                .map(|&i| (i, Case::Identical)),
+        )
+        .chain(
+            summary
+                .unmatched_left
+                .iter()
+                .map(|u| (u.request_index, Case::LeftOnly(u))),
+        )
+        .collect();
+    cases.sort_by_key(|(i, _)| *i);
+    for (i, case) in cases {
+        match case {
+            Case::LeftOnly(u) => push_unmatched_testcase(&mut xml, u, "right"),
+            Case::Diff(d) => {
                 let name = format!("{} {}", d.method, d.url);
                 xml.push_str(&format!("  <testcase name=\"{}\">\n", xml_escape(&name)));
                 let mut reasons = Vec::new();
                 if let Some(ref s) = d.status_diff {
-                    reasons.push(format!("status {} → {}", s.left, s.right));
+                    reasons.push(format!(
+                        "status {} → {} ({})",
+                        s.left,
+                        s.right,
+                        s.transition()
+                    ));
+                }
+                if let Some(ref b) = d.body_diff {
+                    match b.json_changes.len() {
+                        0 => reasons.push("body differs".to_string()),
+                        n => reasons.push(format!("body differs at {} JSON path(s)", n)),
+                    }
                 }
-                if d.body_diff.is_some() {
-                    reasons.push("body differs".to_string());
+                for t in &d.trailer_diffs {
+                    reasons.push(format!(
+                        "trailer {} {} → {}",
+                        t.name,
+                        t.left.as_deref().unwrap_or("-"),
+                        t.right.as_deref().unwrap_or("-")
+                    ));
                 }
                 if let Some(ref w) = d.waf_diff {
                     let l = if w.left_blocked { "blocked" } else { "allowed" };
@@ -390,23 +1813,397 @@ pub fn print_diff_junit(summary: &DiffSummary) -> String {
                     };
                     reasons.push(format!("WAF {} → {}", l, r));
                 }
+                if let Some(ref l) = d.latency_diff {
+                    reasons.push(format!("latency {}ms → {}ms", l.left_ms, l.right_ms));
+                }
+                if let Some(ref s) = d.size_diff {
+                    reasons.push(format!("size {} → {} bytes", s.left_bytes, s.right_bytes));
+                }
+                if let Some(ref v) = d.version_diff {
+                    reasons.push(format!("{} → {}", v.left, v.right));
+                }
+                if let Some(ref t) = d.tls_diff {
+                    reasons.push(format!(
+                        "{} → {}",
+                        describe_tls(&t.left_version, t.left_cipher.as_deref()),
+                        describe_tls(&t.right_version, t.right_cipher.as_deref())
+                    ));
+                }
+                if let Some(ref t) = d.tech_stack_diff {
+                    if t.kind == TechChangeKind::BackendChanged {
+                        reasons.push(format!(
+                            "backend {} → {}",
+                            t.left.describe(),
+                            t.right.describe()
+                        ));
+                    }
+                }
                 xml.push_str(&format!(
                     "    <failure message=\"{}\" type=\"Diff\"/>\n",
                     xml_escape(&reasons.join("; "))
                 ));
                 xml.push_str("  </testcase>\n");
             }
-            None => {
+            Case::Identical => {
                 // Passed — need to infer method/url from position
                 xml.push_str(&format!("  <testcase name=\"request #{}\"/>\n", i));
             }
         }
     }
+    for u in &summary.unmatched_right {
+        push_unmatched_testcase(&mut xml, u, "left");
+    }
 
     xml.push_str("</testsuite>\n");
     xml
 }
 
+/// Inline stylesheet for the HTML diff report; colors follow the pretty output
+const HTML_REPORT_CSS: &str = "\
+body { font: 14px/1.5 -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; margin: 2em auto; max-width: 1100px; padding: 0 1em; color: #1f2328; }
+h1 { font-size: 1.4em; } h1 span { color: #0e7490; } h1 small { color: #656d76; font-weight: normal; }
+h2 { font-size: 1.1em; margin-top: 2em; }
+table.stats td { padding: 2px 16px 2px 0; } table.stats td:first-child { font-weight: 600; }
+details { border: 1px solid #d0d7de; border-radius: 6px; margin: 6px 0; }
+summary { cursor: pointer; padding: 6px 10px; }
+details > div { padding: 4px 10px 10px; border-top: 1px solid #d0d7de; }
+code, pre { font: 12px/1.4 ui-monospace, SFMono-Regular, Menlo, monospace; }
+pre { background: #f6f8fa; padding: 8px; overflow-x: auto; margin: 4px 0; }
+table.headers { border-collapse: collapse; margin: 4px 0; } table.headers td { padding: 2px 10px 2px 0; vertical-align: top; word-break: break-all; }
+.label { color: #656d76; } .idx { color: #656d76; } .method { font-weight: 600; }
+.ok { color: #1a7f37; } .warn { color: #9a6700; } .bad { color: #cf222e; } .info { color: #0e7490; }
+.add { color: #1a7f37; } .del { color: #cf222e; } .chg { color: #9a6700; }
+";
+
+/// Render diff summary as a self-contained HTML page: summary stats, then
+/// one collapsible section per differing request
+pub fn print_diff_html(summary: &DiffSummary) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>ushio diff: {} vs {}</title>\n<style>\n{}</style>\n</head>\n<body>\n",
+        xml_escape(&summary.left_target),
+        xml_escape(&summary.right_target),
+        HTML_REPORT_CSS
+    ));
+    html.push_str("<h1><span>ushio</span> <small>diff results</small></h1>\n");
+
+    let mut stats = vec![
+        ("Left", xml_escape(&summary.left_target)),
+        ("Right", xml_escape(&summary.right_target)),
+        ("Total", summary.total_requests.to_string()),
+        (
+            "Identical",
+            format!("<span class=\"ok\">{}</span>", summary.identical),
+        ),
+    ];
+    let counts = [
+        ("Different", summary.different, "warn"),
+        ("Body diffs", summary.body_diffs, "warn"),
+        ("Trailer diffs", summary.trailer_diffs, "warn"),
+        ("WAF diffs", summary.waf_diffs, "bad"),
+        ("Latency diffs", summary.latency_diffs, "warn"),
+        ("Size diffs", summary.size_diffs, "warn"),
+        ("HTTP version diffs", summary.version_diffs, "warn"),
+        ("TLS diffs", summary.tls_diffs, "warn"),
+    ];
+    for (label, count, class) in counts.into_iter().filter(|(_, n, _)| *n > 0) {
+        stats.push((label, format!("<span class=\"{}\">{}</span>", class, count)));
+    }
+    if summary.stopped_early {
+        stats.push((
+            "Fail-fast",
+            "<span class=\"warn\">stopped at first difference, remaining requests not compared</span>"
+                .to_string(),
+        ));
+    }
+    html.push_str("<table class=\"stats\">\n");
+    for (label, value) in stats {
+        html.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", label, value));
+    }
+    html.push_str("</table>\n");
+
+    if !summary.tech_stack_changes.is_empty() {
+        html.push_str("<h2>Backend technology</h2>\n<ul>\n");
+        for change in &summary.tech_stack_changes {
+            let label = match change.kind {
+                TechChangeKind::BackendChanged => "<span class=\"warn\">changed</span>",
+                TechChangeKind::VersionChanged => "<span class=\"label\">version</span>",
+            };
+            html.push_str(&format!(
+                "<li>{} {} → <strong>{}</strong> <span class=\"label\">({} requests)</span></li>\n",
+                label,
+                xml_escape(&change.left.describe()),
+                xml_escape(&change.right.describe()),
+                change.requests
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if !summary.diffs.is_empty() {
+        html.push_str("<h2>Differences</h2>\n");
+        for diff in &summary.diffs {
+            push_request_diff_html(&mut html, diff);
+        }
+    }
+
+    let unmatched = [
+        ("Only in left", &summary.unmatched_left),
+        ("Only in right", &summary.unmatched_right),
+    ];
+    for (title, requests) in unmatched.iter().filter(|(_, r)| !r.is_empty()) {
+        html.push_str(&format!("<h2>{}</h2>\n<ul>\n", title));
+        for req in requests.iter() {
+            html.push_str(&format!(
+                "<li><span class=\"idx\">#{}</span> <span class=\"method\">{}</span> <code>{}</code></li>\n",
+                req.request_index,
+                xml_escape(&req.method),
+                xml_escape(&req.url)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    if summary.different == 0 {
+        html.push_str("<p class=\"ok\">✓ No differences found</p>\n");
+    }
+    html.push_str(&format!(
+        "<p class=\"label\">Generated by ushio {}</p>\n</body>\n</html>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    html
+}
+
+/// Append one collapsible request section to the HTML report
+fn push_request_diff_html(html: &mut String, diff: &RequestDiff) {
+    let status_class = |status: u16| match status {
+        0 => "label",
+        500.. => "bad",
+        400..=499 => "warn",
+        300..=399 => "info",
+        _ => "ok",
+    };
+    let status_html = |status: u16| {
+        let text = if status == 0 {
+            "N/A".to_string()
+        } else {
+            status.to_string()
+        };
+        format!("<span class=\"{}\">{}</span>", status_class(status), text)
+    };
+    let transition_html = |status: &StatusDiff| {
+        let class = match status.severity {
+            StatusSeverity::Critical => "bad",
+            StatusSeverity::Major => "warn",
+            StatusSeverity::Notable | StatusSeverity::Cosmetic => "info",
+        };
+        format!(
+            "<span class=\"{}\">({})</span>",
+            class,
+            xml_escape(&status.transition())
+        )
+    };
+    let allowed_html = |blocked: bool| {
+        if blocked {
+            "<span class=\"bad\">blocked</span>"
+        } else {
+            "<span class=\"ok\">allowed</span>"
+        }
+    };
+
+    let mut tags = Vec::new();
+    if let Some(ref s) = diff.status_diff {
+        tags.push(format!(
+            "{} → {}",
+            status_html(s.left),
+            status_html(s.right)
+        ));
+    }
+    if diff.waf_diff.is_some() {
+        tags.push("<span class=\"bad\">WAF</span>".to_string());
+    }
+    if diff.body_diff.is_some() {
+        tags.push("<span class=\"warn\">body</span>".to_string());
+    }
+    if !diff.header_diffs.is_empty() || !diff.trailer_diffs.is_empty() {
+        tags.push("<span class=\"warn\">headers</span>".to_string());
+    }
+    if diff.latency_diff.is_some() {
+        tags.push("<span class=\"warn\">latency</span>".to_string());
+    }
+    if diff.size_diff.is_some() {
+        tags.push("<span class=\"warn\">size</span>".to_string());
+    }
+    if diff.version_diff.is_some() {
+        tags.push("<span class=\"warn\">version</span>".to_string());
+    }
+    if diff.tls_diff.is_some() {
+        tags.push("<span class=\"warn\">TLS</span>".to_string());
+    }
+    html.push_str(&format!(
+        "<details>\n<summary><span class=\"idx\">#{}</span> <span class=\"method\">{}</span> <code>{}</code> {}</summary>\n<div>\n",
+        diff.request_index,
+        xml_escape(&diff.method),
+        xml_escape(&diff.url),
+        tags.join(" ")
+    ));
+
+    if let Some(ref status) = diff.status_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">Status:</span> {} → {} {}</p>\n",
+            status_html(status.left),
+            status_html(status.right),
+            transition_html(status)
+        ));
+    }
+
+    if let Some(ref waf) = diff.waf_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">WAF:</span> {} → {}</p>\n",
+            allowed_html(waf.left_blocked),
+            allowed_html(waf.right_blocked)
+        ));
+        for (side, reason) in [("Left", &waf.left_reason), ("Right", &waf.right_reason)] {
+            if let Some(reason) = reason {
+                html.push_str(&format!(
+                    "<p><span class=\"label\">{}:</span> {}</p>\n",
+                    side,
+                    xml_escape(reason)
+                ));
+            }
+        }
+    }
+
+    if let Some(ref latency) = diff.latency_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">Latency:</span> {}ms → {}ms <span class=\"warn\">(+{}ms)</span></p>\n",
+            latency.left_ms, latency.right_ms, latency.delta_ms
+        ));
+    }
+
+    if let Some(ref version) = diff.version_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">HTTP version:</span> {} → {}</p>\n",
+            xml_escape(&version.left),
+            xml_escape(&version.right)
+        ));
+    }
+
+    if let Some(ref tls) = diff.tls_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">TLS:</span> {} → {}</p>\n",
+            xml_escape(&describe_tls(&tls.left_version, tls.left_cipher.as_deref())),
+            xml_escape(&describe_tls(
+                &tls.right_version,
+                tls.right_cipher.as_deref()
+            ))
+        ));
+    }
+
+    if let Some(ref size) = diff.size_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">Size:</span> {} → {} bytes <span class=\"warn\">({:+})</span></p>\n",
+            size.left_bytes, size.right_bytes, size.delta_bytes
+        ));
+    }
+
+    if let Some(ref tech) = diff.tech_stack_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">Backend:</span> {} → {}</p>\n",
+            xml_escape(&tech.left.describe()),
+            xml_escape(&tech.right.describe())
+        ));
+    }
+
+    if let Some(ref body) = diff.body_diff {
+        html.push_str(&format!(
+            "<p><span class=\"label\">Body:</span> {} bytes → {} bytes</p>\n<pre>",
+            body.left_size, body.right_size
+        ));
+        if !body.json_changes.is_empty() {
+            for change in &body.json_changes {
+                let value = |v: &Option<serde_json::Value>| {
+                    v.as_ref().map_or("-".to_string(), |v| v.to_string())
+                };
+                let (class, sign) = match change.kind {
+                    JsonChangeKind::Added => ("add", '+'),
+                    JsonChangeKind::Removed => ("del", '-'),
+                    JsonChangeKind::Changed => ("chg", '~'),
+                };
+                html.push_str(&format!(
+                    "<span class=\"{}\">{} {}</span>\n",
+                    class,
+                    sign,
+                    xml_escape(&format!(
+                        "{} {} → {}",
+                        change.path,
+                        value(&change.left),
+                        value(&change.right)
+                    ))
+                ));
+            }
+        } else {
+            for line in body.unified_diff.lines() {
+                let class = if line.starts_with('+') {
+                    "add"
+                } else if line.starts_with('-') {
+                    "del"
+                } else {
+                    "label"
+                };
+                html.push_str(&format!(
+                    "<span class=\"{}\">{}</span>\n",
+                    class,
+                    xml_escape(line)
+                ));
+            }
+        }
+        html.push_str("</pre>\n");
+    }
+
+    let trailers = diff.trailer_diffs.iter().map(|t| (t, "trailer "));
+    let headers: Vec<_> = diff
+        .header_diffs
+        .iter()
+        .map(|h| (h, ""))
+        .chain(trailers)
+        .collect();
+    if !headers.is_empty() {
+        html.push_str("<table class=\"headers\">\n");
+        for (header, kind) in headers {
+            let (class, sign) = match header.diff_type {
+                HeaderDiffType::Added => ("add", '+'),
+                HeaderDiffType::Removed => ("del", '-'),
+                HeaderDiffType::Changed => ("chg", '~'),
+            };
+            html.push_str(&format!(
+                "<tr><td class=\"{}\">{}</td><td class=\"label\">{}{}</td><td><code>{}</code></td><td>→</td><td><code>{}</code></td></tr>\n",
+                class,
+                sign,
+                kind,
+                xml_escape(&header.name),
+                xml_escape(header.left.as_deref().unwrap_or("-")),
+                xml_escape(header.right.as_deref().unwrap_or("-"))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</div>\n</details>\n");
+}
+
+/// A failed testcase for a result that has no counterpart on `missing_side`
+fn push_unmatched_testcase(xml: &mut String, request: &UnmatchedRequest, missing_side: &str) {
+    let name = format!("{} {}", request.method, request.url);
+    xml.push_str(&format!("  <testcase name=\"{}\">\n", xml_escape(&name)));
+    xml.push_str(&format!(
+        "    <failure message=\"no matching request on the {}\" type=\"Diff\"/>\n",
+        missing_side
+    ));
+    xml.push_str("  </testcase>\n");
+}
+
 /// Escape XML special characters
 fn xml_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -416,10 +2213,12 @@ fn xml_escape(s: &str) -> String {
         .replace('\'', "&apos;")
 }
 
-/// Truncate a string
+/// Truncate a string to `max_len` characters (not bytes, so multibyte
+/// characters are never split)
 fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() > max_len {
-        format!("{}...", &s[..max_len - 3])
+    if s.chars().count() > max_len {
+        let kept: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
     } else {
         s.to_string()
     }
@@ -427,18 +2226,52 @@ fn truncate(s: &str, max_len: usize) -> String {
 
 /// Truncate URL, keeping the path visible
 fn truncate_url(url: &str, max_len: usize) -> String {
-    if url.len() <= max_len {
+    if url.chars().count() <= max_len {
         return url.to_string();
     }
 
     // Try to extract just the path
     if let Ok(parsed) = url::Url::parse(url) {
         let path = parsed.path();
-        if path.len() <= max_len {
+        let len = path.chars().count();
+        if len <= max_len {
             return format!("...{}", path);
         }
-        return format!("...{}", &path[path.len() - max_len + 3..]);
+        let tail: String = path.chars().skip(len + 3 - max_len).collect();
+        return format!("...{}", tail);
     }
 
     truncate(url, max_len)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_multibyte() {
+        // The cut falls inside the emoji's four bytes
+        let value = "ab🚀🚀🚀🚀 rocket";
+        assert_eq!(truncate(value, 6), "ab🚀...");
+        assert_eq!(truncate("ünïcödé", 7), "ünïcödé");
+        assert_eq!(truncate("x", 2), "x");
+    }
+
+    #[test]
+    fn test_truncate_url_multibyte() {
+        // Not a parseable URL, so the plain truncation applies
+        assert_eq!(truncate_url("/pfad/ärger/größe", 10), "/pfad/ä...");
+        assert_eq!(
+            truncate_url("https://example.com/a/very/long/path/segment", 12),
+            "...h/segment"
+        );
+    }
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("https://example.com/a"), "https://example.com/a");
+        assert_eq!(csv_field("/search?q=a,b"), "\"/search?q=a,b\"");
+        assert_eq!(csv_field("said \"no\""), "\"said \"\"no\"\"\"");
+        assert_eq!(csv_field("line\nbreak"), "\"line\nbreak\"");
+    }
+}
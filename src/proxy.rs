@@ -110,15 +110,19 @@ async fn handle_proxy_request(
         .unwrap_or("/");
     let forward_url = format!("{}{}", target.trim_end_matches('/'), path);
 
-    // Collect request headers
-    let req_headers: Vec<(String, String)> = req
-        .headers()
+    // Collect request headers; values that aren't UTF-8 are recorded lossily
+    // and forwarded as sent
+    let mut forward_headers = req.headers().clone();
+    forward_headers.remove(hyper::header::HOST);
+    forward_headers.remove(hyper::header::CONTENT_LENGTH);
+    let req_headers: Vec<(String, String)> = forward_headers
         .iter()
-        .filter(|(name, _)| {
-            let n = name.as_str().to_lowercase();
-            n != "host" && n != "content-length"
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            )
         })
-        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
         .collect();
 
     // Read request body
@@ -135,10 +139,9 @@ async fn handle_proxy_request(
 
     // Forward the request
     let reqwest_method: reqwest::Method = method.parse().unwrap_or(reqwest::Method::GET);
-    let mut forward = client.request(reqwest_method, &forward_url);
-    for (k, v) in &req_headers {
-        forward = forward.header(k.as_str(), v.as_str());
-    }
+    let mut forward = client
+        .request(reqwest_method, &forward_url)
+        .headers(forward_headers);
     if let Some(ref body) = req_body {
         forward = forward.body(body.clone());
     }
@@ -154,8 +157,17 @@ async fn handle_proxy_request(
                     method: method.clone(),
                     url: forward_url.clone(),
                     headers: req_headers,
-                    body: req_body,
+                    body: req_body.map(Into::into),
+                    body_base64: None,
                     expected_status: Some(status),
+                    expected_headers: vec![],
+                    expected_body: None,
+                    name: None,
+                    description: None,
+                    raw: None,
+                    delay_ms: None,
+                    count: None,
+                    tags: vec![],
                 });
                 if reqs.len() % 10 == 0 {
                     eprint!("\r  Captured {} requests", reqs.len());
@@ -0,0 +1,241 @@
+//! Byte-exact request replay (`--raw`)
+//!
+//! reqwest normalizes everything it sends: header case and order, the request
+//! line, framing. For parser-differential WAF testing the capture can instead
+//! carry the exact request bytes in `raw`, which are written to a TCP (or TLS)
+//! socket unchanged and the response parsed by hand.
+//!
+//! Limitations: HTTP/1.1 only (ALPN offers `http/1.1`), one request per
+//! connection, no proxy, no redirects, no decompression. The blob is not
+//! rewritten, so its `Host` header and request target are sent as captured;
+//! only the connection goes to the replay target.
+
+use anyhow::{bail, Context, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::rustls;
+use url::Url;
+
+use crate::fold::{dechunk, parse_raw_response, RawResponse};
+
+/// Send `raw` unchanged to the host and port of `target`.
+///
+/// Returns the parsed response and the address that was connected to.
+pub async fn send(
+    target: &Url,
+    raw: &[u8],
+    timeout: Duration,
+    insecure: bool,
+) -> Result<(RawResponse, SocketAddr)> {
+    let host = target.host_str().context("Target URL has no host")?;
+    let port = target
+        .port_or_known_default()
+        .context("Target URL has no port")?;
+    let head_request = raw.starts_with(b"HEAD ");
+
+    let exchange = async {
+        let tcp = tokio::net::TcpStream::connect((host, port))
+            .await
+            .context("Failed to connect")?;
+        let peer = tcp.peer_addr().context("Failed to connect")?;
+
+        let response = match target.scheme() {
+            "http" => exchange(tcp, raw, head_request).await?,
+            "https" => {
+                let tls = connect_tls(host, tcp, insecure).await?;
+                exchange(tls, raw, head_request).await?
+            }
+            other => bail!(
+                "Raw replay supports http:// and https:// only, got {}://",
+                other
+            ),
+        };
+        Ok((parse_raw_response(&response)?, peer))
+    };
+    tokio::time::timeout(timeout, exchange)
+        .await
+        .context("Request timed out")?
+}
+
+/// Write the request and read until the response is complete or the peer closes
+async fn exchange<S>(mut stream: S, raw: &[u8], head_request: bool) -> Result<Vec<u8>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream
+        .write_all(raw)
+        .await
+        .context("Failed to send request")?;
+    stream.flush().await.context("Failed to send request")?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match stream.read(&mut buf).await {
+            Ok(n) => n,
+            // Servers often skip TLS close_notify; what arrived is still the response
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && !response.is_empty() => 0,
+            Err(e) => return Err(e).context("Failed to read response"),
+        };
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+        if response_complete(&response, head_request) {
+            break;
+        }
+    }
+    Ok(response)
+}
+
+/// Whether `data` holds a full response, so keep-alive servers don't stall us
+fn response_complete(data: &[u8], head_request: bool) -> bool {
+    let Some(split) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return false;
+    };
+    let head = String::from_utf8_lossy(&data[..split]);
+    let mut lines = head.split("\r\n");
+    let status: u16 = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    if head_request || (100..200).contains(&status) || status == 204 || status == 304 {
+        return true;
+    }
+
+    let body = &data[split + 4..];
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") && value.contains("chunked") {
+            return dechunk(body).is_ok();
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            return value
+                .trim()
+                .parse::<usize>()
+                .is_ok_and(|len| body.len() >= len);
+        }
+    }
+    // No framing: the body runs until the server closes the connection
+    false
+}
+
+/// Wrap `tcp` in TLS, verifying against the bundled web PKI roots unless `insecure`
+pub(crate) async fn connect_tls(
+    host: &str,
+    tcp: tokio::net::TcpStream,
+    insecure: bool,
+) -> Result<tokio_rustls::client::TlsStream<tokio::net::TcpStream>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS")?;
+    let mut config = if insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+            .with_no_client_auth()
+    } else {
+        let roots =
+            rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        builder.with_root_certificates(roots).with_no_client_auth()
+    };
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .context("Invalid TLS server name")?;
+    tokio_rustls::TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .context("TLS handshake failed")
+}
+
+/// Certificate verifier for `--insecure`: any certificate is accepted, but
+/// handshake signatures are still checked
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<rustls::crypto::CryptoProvider>);
+
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_complete_content_length() {
+        assert!(!response_complete(b"HTTP/1.1 200 OK\r\nContent-Len", false));
+        assert!(!response_complete(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nab",
+            false
+        ));
+        assert!(response_complete(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nabcde",
+            false
+        ));
+    }
+
+    #[test]
+    fn test_response_complete_chunked_and_bodyless() {
+        let partial = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n";
+        assert!(!response_complete(partial, false));
+        let done = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        assert!(response_complete(done, false));
+
+        assert!(response_complete(b"HTTP/1.1 204 No Content\r\n\r\n", false));
+        assert!(response_complete(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n",
+            true
+        ));
+        // Unframed bodies are read until close
+        assert!(!response_complete(b"HTTP/1.1 200 OK\r\n\r\nabc", false));
+    }
+}
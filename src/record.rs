@@ -0,0 +1,486 @@
+//! Forward proxy recording (`ushio record`)
+//!
+//! Clients use ushio as their HTTP(S) proxy. Plain HTTP requests arrive in
+//! absolute form (`GET http://host/path`) and are forwarded as they are.
+//! HTTPS clients open a `CONNECT` tunnel, which ushio terminates itself with
+//! a certificate for the requested host, signed by a local CA the client has
+//! to trust; the decrypted requests then go upstream over a new TLS
+//! connection. Every forwarded request is recorded, with the status it got
+//! as `expected_status`.
+//!
+//! Only HTTP/1.1 is spoken to clients (ALPN offers `http/1.1`). Requests that
+//! fail upstream get a 502 and are not recorded.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls;
+
+use crate::capture::CapturedRequest;
+
+/// Connection-level headers that are never recorded or passed along
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "proxy-connection",
+    "proxy-authorization",
+    "keep-alive",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// Local certificate authority that issues per-host certificates for
+/// intercepted HTTPS tunnels
+pub struct RecordingCa {
+    cert: rcgen::Certificate,
+    key: rcgen::KeyPair,
+    /// Shared by every issued certificate; only the CA key needs to persist
+    leaf_key: rcgen::KeyPair,
+    configs: Mutex<HashMap<String, Arc<rustls::ServerConfig>>>,
+}
+
+impl RecordingCa {
+    /// Load the CA key from `key_path`, or create a new CA and write its
+    /// certificate and key when the key doesn't exist yet.
+    ///
+    /// The CA certificate is rebuilt from the key with fixed parameters, so
+    /// a certificate the client already trusts keeps working across runs.
+    /// Returns the CA and whether it was newly created.
+    pub fn load_or_create(cert_path: &Path, key_path: &Path) -> Result<(Self, bool)> {
+        let (key, created) = if key_path.exists() {
+            let pem = std::fs::read_to_string(key_path)
+                .with_context(|| format!("Failed to read CA key {}", key_path.display()))?;
+            let key = rcgen::KeyPair::from_pem(&pem)
+                .with_context(|| format!("Invalid CA key in {}", key_path.display()))?;
+            (key, false)
+        } else {
+            let key = rcgen::KeyPair::generate().context("Failed to generate CA key")?;
+            write_private(key_path, &key.serialize_pem())?;
+            (key, true)
+        };
+
+        let mut params = rcgen::CertificateParams::default();
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "ushio recording CA");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "ushio");
+        params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        params.key_usages = vec![
+            rcgen::KeyUsagePurpose::KeyCertSign,
+            rcgen::KeyUsagePurpose::CrlSign,
+            rcgen::KeyUsagePurpose::DigitalSignature,
+        ];
+        let cert = params
+            .self_signed(&key)
+            .context("Failed to build CA certificate")?;
+        if created || !cert_path.exists() {
+            std::fs::write(cert_path, cert.pem()).with_context(|| {
+                format!("Failed to write CA certificate {}", cert_path.display())
+            })?;
+        }
+
+        let leaf_key = rcgen::KeyPair::generate().context("Failed to generate key")?;
+        let ca = Self {
+            cert,
+            key,
+            leaf_key,
+            configs: Mutex::new(HashMap::new()),
+        };
+        Ok((ca, created))
+    }
+
+    /// The CA certificate in PEM, for clients to trust
+    pub fn cert_pem(&self) -> String {
+        self.cert.pem()
+    }
+
+    /// Issue a certificate for `host`, valid from yesterday for a year
+    pub fn issue(&self, host: &str) -> Result<rcgen::Certificate> {
+        let mut params = rcgen::CertificateParams::new(vec![host.to_string()])
+            .with_context(|| format!("Invalid host name for certificate: {}", host))?;
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, host);
+        params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ServerAuth];
+        params.use_authority_key_identifier_extension = true;
+
+        let today = chrono::Utc::now().date_naive();
+        let ymd = |d: chrono::NaiveDate| {
+            use chrono::Datelike;
+            rcgen::date_time_ymd(d.year(), d.month() as u8, d.day() as u8)
+        };
+        params.not_before = ymd(today - chrono::Days::new(1));
+        params.not_after = ymd(today + chrono::Days::new(365));
+
+        // Browsers reject two different certificates with the same issuer and serial
+        let mut serial = Sha256::new();
+        serial.update(host.as_bytes());
+        serial.update(
+            chrono::Utc::now()
+                .timestamp_nanos_opt()
+                .unwrap_or(0)
+                .to_be_bytes(),
+        );
+        let mut serial = serial.finalize()[..16].to_vec();
+        serial[0] &= 0x7f;
+        params.serial_number = Some(serial.into());
+
+        params
+            .signed_by(&self.leaf_key, &self.cert, &self.key)
+            .context("Failed to sign certificate")
+    }
+
+    /// TLS server configuration presenting a certificate for `host`
+    fn server_config(&self, host: &str) -> Result<Arc<rustls::ServerConfig>> {
+        if let Some(config) = self.configs.lock().unwrap().get(host) {
+            return Ok(config.clone());
+        }
+
+        let cert = self.issue(host)?;
+        let key = rustls::pki_types::PrivateKeyDer::Pkcs8(self.leaf_key.serialize_der().into());
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .context("Failed to set up TLS")?
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.der().clone()], key)
+            .context("Failed to set up TLS")?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let config = Arc::new(config);
+        self.configs
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), config.clone());
+        Ok(config)
+    }
+}
+
+/// Write a private key readable only by the current user
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create CA key {}", path.display()))?;
+    std::io::Write::write_all(&mut file, content.as_bytes())
+        .with_context(|| format!("Failed to write CA key {}", path.display()))
+}
+
+/// Shared by every connection
+struct Recorder {
+    client: reqwest::Client,
+    ca: RecordingCa,
+    requests: Mutex<Vec<CapturedRequest>>,
+}
+
+/// Accept proxy connections on `listener` until `shutdown` completes, then
+/// return the recorded requests in the order their responses arrived.
+///
+/// `insecure` skips certificate verification towards upstream servers.
+pub async fn run_record_proxy(
+    listener: TcpListener,
+    ca: RecordingCa,
+    insecure: bool,
+    shutdown: impl Future<Output = ()>,
+) -> Result<Vec<CapturedRequest>> {
+    // Bodies pass through untouched, compressed or not
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .no_gzip()
+        .no_brotli()
+        .danger_accept_invalid_certs(insecure)
+        .build()
+        .context("Failed to build HTTP client")?;
+    let recorder = Arc::new(Recorder {
+        client,
+        ca,
+        requests: Mutex::new(Vec::new()),
+    });
+
+    tokio::pin!(shutdown);
+    loop {
+        let (stream, remote_addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = &mut shutdown => break,
+        };
+        let recorder = recorder.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |req| handle_proxy_request(req, recorder.clone()));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades()
+                .await
+            {
+                if !e.to_string().contains("connection closed") {
+                    eprintln!("Connection error from {}: {}", remote_addr, e);
+                }
+            }
+        });
+    }
+
+    let requests = recorder.requests.lock().unwrap().clone();
+    Ok(requests)
+}
+
+/// Handle a request from a proxy client: open a tunnel for `CONNECT`,
+/// forward absolute-form requests
+async fn handle_proxy_request(
+    req: Request<hyper::body::Incoming>,
+    recorder: Arc<Recorder>,
+) -> Result<Response<Full<Bytes>>, hyper::Error> {
+    if req.method() == Method::CONNECT {
+        let Some(authority) = req.uri().authority().cloned() else {
+            return Ok(plain_response(
+                StatusCode::BAD_REQUEST,
+                "CONNECT needs host:port",
+            ));
+        };
+        tokio::spawn(async move {
+            let upgraded = match hyper::upgrade::on(req).await {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    eprintln!("  Tunnel to {} failed: {}", authority, e);
+                    return;
+                }
+            };
+            if let Err(e) = intercept_tunnel(upgraded, &authority, recorder).await {
+                eprintln!("  Tunnel to {} failed: {:#}", authority, e);
+            }
+        });
+        return Ok(Response::new(Full::new(Bytes::new())));
+    }
+
+    if req.uri().scheme().is_none() || req.uri().authority().is_none() {
+        return Ok(plain_response(
+            StatusCode::BAD_REQUEST,
+            "ushio record is a forward proxy: set it as the client's HTTP(S) proxy",
+        ));
+    }
+    let url = req.uri().to_string();
+    forward_and_record(req, url, &recorder).await
+}
+
+/// Terminate TLS inside a `CONNECT` tunnel and serve the requests in it
+async fn intercept_tunnel(
+    upgraded: hyper::upgrade::Upgraded,
+    authority: &hyper::http::uri::Authority,
+    recorder: Arc<Recorder>,
+) -> Result<()> {
+    let host = authority
+        .host()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let config = recorder.ca.server_config(host)?;
+    let tls = tokio_rustls::TlsAcceptor::from(config)
+        .accept(TokioIo::new(upgraded))
+        .await
+        .context("TLS handshake with client failed (does it trust the ushio CA?)")?;
+
+    // Default port is implied, as the client would have written the URL
+    let origin = match authority.port_u16() {
+        Some(443) | None => format!("https://{}", authority.host()),
+        Some(_) => format!("https://{}", authority),
+    };
+    let service = service_fn(move |req: Request<hyper::body::Incoming>| {
+        let recorder = recorder.clone();
+        let path = req
+            .uri()
+            .path_and_query()
+            .map_or("/".to_string(), |pq| pq.to_string());
+        let url = format!("{}{}", origin, path);
+        async move { forward_and_record(req, url, &recorder).await }
+    });
+    http1::Builder::new()
+        .serve_connection(TokioIo::new(tls), service)
+        .await
+        .context("Connection error")
+}
+
+/// Send the request upstream, record it with the status it got, and relay
+/// the response
+async fn forward_and_record(
+    req: Request<hyper::body::Incoming>,
+    url: String,
+    recorder: &Recorder,
+) -> Result<Response<Full<Bytes>>, hyper::Error> {
+    let method = req.method().to_string();
+    let mut request_headers = req.headers().clone();
+    for name in HOP_BY_HOP {
+        request_headers.remove(*name);
+    }
+    let headers = recorded_headers(&request_headers);
+    let body = req.collect().await?.to_bytes();
+
+    // Upstream gets the header bytes exactly as the client sent them
+    let reqwest_method: reqwest::Method = method.parse().unwrap_or(reqwest::Method::GET);
+    let mut forward = recorder
+        .client
+        .request(reqwest_method, &url)
+        .headers(request_headers);
+    if !body.is_empty() {
+        forward = forward.body(body.clone());
+    }
+
+    let upstream = match forward.send().await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            eprintln!("  Forward error for {} {}: {}", method, url, e);
+            return Ok(plain_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Proxy error: {}", e),
+            ));
+        }
+    };
+    let status = upstream.status();
+    let response_headers = upstream.headers().clone();
+    let response_body = match upstream.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("  Forward error for {} {}: {}", method, url, e);
+            return Ok(plain_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Proxy error: {}", e),
+            ));
+        }
+    };
+
+    // Text bodies stay readable; anything else is kept exactly as base64
+    let (text_body, body_base64) = if body.is_empty() {
+        (None, None)
+    } else {
+        match String::from_utf8(body.to_vec()) {
+            Ok(text) => (Some(text), None),
+            Err(_) => (None, Some(BASE64.encode(&body))),
+        }
+    };
+    {
+        let mut requests = recorder.requests.lock().unwrap();
+        requests.push(CapturedRequest {
+            method,
+            url,
+            headers,
+            body: text_body.map(Into::into),
+            body_base64,
+            expected_status: Some(status.as_u16()),
+            ..Default::default()
+        });
+        if requests.len() % 10 == 0 {
+            eprint!("\r  Captured {} requests", requests.len());
+        }
+    }
+
+    let mut response = Response::builder().status(status);
+    for (name, value) in &response_headers {
+        if !HOP_BY_HOP.contains(&name.as_str()) {
+            response = response.header(name, value);
+        }
+    }
+    Ok(response
+        .body(Full::new(response_body))
+        .unwrap_or_else(|_| plain_response(StatusCode::BAD_GATEWAY, "Invalid upstream response")))
+}
+
+/// Header pairs as recorded in the capture. A value that isn't valid UTF-8
+/// is kept lossily rather than dropped.
+fn recorded_headers(headers: &hyper::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(k, v)| {
+            (
+                k.to_string(),
+                String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+fn plain_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(message.to_string())));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issued_certificate_chains_to_reloaded_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("ca.pem");
+        let key_path = dir.path().join("ca-key.pem");
+        let (_, created) = RecordingCa::load_or_create(&cert_path, &key_path).unwrap();
+        assert!(created);
+
+        // A later run signs with the same key; the saved certificate still verifies
+        let (ca, created) = RecordingCa::load_or_create(&cert_path, &key_path).unwrap();
+        assert!(!created);
+        let leaf = ca.issue("shop.example.com").unwrap();
+
+        let saved = std::fs::read_to_string(&cert_path).unwrap();
+        let saved = pem_der(&saved);
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(saved.into()).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let verifier =
+            rustls::client::WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+
+        let verify = |name: &str| {
+            use rustls::client::danger::ServerCertVerifier;
+            verifier.verify_server_cert(
+                leaf.der(),
+                &[],
+                &rustls::pki_types::ServerName::try_from(name.to_string()).unwrap(),
+                &[],
+                rustls::pki_types::UnixTime::now(),
+            )
+        };
+        assert!(verify("shop.example.com").is_ok());
+        assert!(verify("other.example.com").is_err());
+    }
+
+    #[test]
+    fn test_recorded_headers_keep_non_utf8_values() {
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("x-plain", "abc".parse().unwrap());
+        headers.insert(
+            "x-latin1",
+            hyper::header::HeaderValue::from_bytes(b"caf\xe9").unwrap(),
+        );
+        let recorded = recorded_headers(&headers);
+        assert!(recorded.contains(&("x-plain".to_string(), "abc".to_string())));
+        assert!(recorded.contains(&("x-latin1".to_string(), "caf\u{fffd}".to_string())));
+    }
+
+    fn pem_der(pem: &str) -> Vec<u8> {
+        let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        BASE64.decode(body).unwrap()
+    }
+}
@@ -3,45 +3,250 @@
 //! Replays captured requests against target endpoints in deterministic order.
 
 use anyhow::{Context, Result};
-use futures::stream::{self, StreamExt};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::stream::{self, FuturesUnordered, StreamExt};
+use http_body_util::BodyExt;
+use reqwest::cookie::{CookieStore, Jar};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use url::Url;
 
-use crate::capture::CapturedRequest;
+use crate::adaptive::{self, AimdController};
+use crate::capture::{Body, CapturedRequest};
+use crate::fault::{self, Fault, FaultSpec};
+use crate::fold::{self, FoldedHeader};
+use crate::geo::GeoInfo;
+use crate::hook::{self, HookInput};
+use crate::raw;
+use crate::template;
+use crate::timing::{self, Timing};
+use crate::tls::{self, ClientIdentity, TlsParams};
 
-/// Maximum response body size to capture (256 KB)
-const MAX_BODY_CAPTURE: usize = 256 * 1024;
+/// Default maximum response body size to capture (256 KB)
+pub const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
 
 /// Configuration for replay execution
 #[derive(Debug, Clone)]
 pub struct ReplayConfig {
     pub timeout: Duration,
+    /// Per-method timeout overrides of `timeout`
+    pub method_timeouts: MethodTimeouts,
+    /// Budget for the whole session; requests not started by then are skipped
+    pub deadline: Option<Duration>,
     pub concurrency: usize,
     pub header_mutations: Vec<(String, String)>,
+    /// Headers added to requests that don't already carry them; unlike
+    /// `header_mutations`, captured values win
+    pub default_headers: Vec<(String, String)>,
+    /// `${name}` values filled into URLs, header values, and bodies; later entries win
+    pub vars: Vec<(String, String)>,
+    /// Applied in order to each URL after the host is swapped for the target
+    pub url_rewrites: Vec<UrlRewrite>,
+    /// Sorting and dropping of query parameters, before `url_rewrites`
+    pub query: QueryNormalization,
+    /// Directory that relative `{"file": ...}` bodies are read from,
+    /// normally the capture's; `None` means the working directory
+    pub body_dir: Option<PathBuf>,
     pub strip_cookies: bool,
+    /// Send the captured `Host` header (or the captured URL's host) instead
+    /// of deriving it from the target
+    pub keep_host: bool,
+    /// Keep a session cookie jar: `Set-Cookie` responses are sent on later
+    /// requests, overriding captured cookies of the same name
+    pub cookie_jar: bool,
+    /// Cookie (name, value) forced onto every request, e.g. to pin an A/B bucket
+    pub sticky_cookie: Option<(String, String)>,
+    /// Shell command run before each request; its JSON output sets headers
+    pub pre_request_hook: Option<String>,
+    /// Send every request with an empty body, keeping method and headers
+    pub strip_bodies: bool,
     pub capture_body: bool,
+    /// Bodies larger than this are hashed but not stored
+    pub max_body_bytes: usize,
+    /// Stop reading response bodies after this many bytes; `None` reads them whole
+    pub body_read_limit: Option<usize>,
+    /// Record only these response headers (lowercase); `None` keeps all
+    pub keep_headers: Option<Vec<String>>,
     pub delay_ms: u64,
+    /// Global cap in requests per second, shared by all in-flight requests
+    pub rate_limit: Option<f64>,
+    /// Start requests with the spacing recorded in their `delay_ms`
+    pub preserve_timing: bool,
+    /// Upper bound for each recorded gap under `preserve_timing`
+    pub max_delay: Option<Duration>,
+    /// Read window for streaming responses (SSE, open-ended chunked); `None` waits for the full body
+    pub stream_timeout: Option<Duration>,
+    /// Extra attempts for connection errors, timeouts, and `retry_statuses`
+    pub retries: u32,
+    /// Wait before the first retry; doubles for each further one
+    pub retry_backoff: Duration,
+    /// Response statuses that are retried like errors, e.g. 502, 503, 504
+    pub retry_statuses: Vec<u16>,
+    /// Send each request this many times in a row (at least 1)
+    pub repeat: usize,
+    /// Stop starting requests once one fails with an error
+    pub abort_on_error: bool,
+    /// Stop starting requests once one gets an unexpected status
+    pub abort_on_mismatch: bool,
     pub insecure: bool,
+    /// Client certificate presented to servers that ask for one (mTLS)
+    pub client_identity: Option<ClientIdentity>,
+    /// Keep response bodies as sent on the wire (no gzip/deflate/brotli decoding)
+    pub no_decompress: bool,
+    /// Record the IP each request actually connected to
+    pub annotate_geo: bool,
+    /// Break each request's time into DNS, connect, TTFB, and download
+    pub timing: bool,
+    /// Record the TLS version and cipher suite each HTTPS host negotiates
+    pub tls_info: bool,
     pub capture_source: Option<String>,
     pub proxy: Option<String>,
+    /// DNS overrides: connect to this address whenever the host is requested
+    pub resolve: Vec<(String, SocketAddr)>,
+    /// Faults to inject into a deterministic subset of requests
+    pub faults: Vec<FaultSpec>,
+    /// Seed that decides which requests get faults
+    pub fault_seed: u64,
+    /// Adjust the in-flight limit during the run instead of using `concurrency`
+    pub adaptive: Option<AdaptiveConcurrency>,
+    /// Send this header with obsolete line folding over a raw socket
+    /// (requires the `obs-fold` feature)
+    pub obs_fold: Option<FoldedHeader>,
+    /// Send requests that carry a `raw` blob byte-for-byte over a socket
+    pub raw: bool,
+    /// Pin the protocol version instead of negotiating it
+    pub http_version: Option<HttpVersion>,
+}
+
+/// Protocol version to pin replays to (`--http-version`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpVersion {
+    Http1,
+    /// HTTP/2 with prior knowledge: no upgrade or ALPN fallback to HTTP/1.1
+    Http2,
+}
+
+/// Settings for AIMD-controlled concurrency
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveConcurrency {
+    /// p95 latency the controller tries to stay under
+    pub target_p95: Duration,
+    /// Ceiling for the in-flight limit
+    pub max_concurrency: usize,
 }
 
 impl Default for ReplayConfig {
     fn default() -> Self {
         Self {
             timeout: Duration::from_secs(30),
+            method_timeouts: MethodTimeouts::default(),
+            deadline: None,
             concurrency: 1,
             header_mutations: vec![],
+            default_headers: vec![],
+            vars: vec![],
+            url_rewrites: vec![],
+            query: QueryNormalization::default(),
+            body_dir: None,
             strip_cookies: false,
+            keep_host: false,
+            cookie_jar: false,
+            sticky_cookie: None,
+            pre_request_hook: None,
+            strip_bodies: false,
             capture_body: true,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            body_read_limit: None,
+            keep_headers: None,
             delay_ms: 0,
+            rate_limit: None,
+            preserve_timing: false,
+            max_delay: None,
+            stream_timeout: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(100),
+            retry_statuses: vec![],
+            repeat: 1,
+            abort_on_error: false,
+            abort_on_mismatch: false,
             insecure: false,
+            client_identity: None,
+            no_decompress: false,
+            annotate_geo: false,
+            timing: false,
+            tls_info: false,
             capture_source: None,
             proxy: None,
+            resolve: vec![],
+            faults: vec![],
+            fault_seed: 0,
+            adaptive: None,
+            obs_fold: None,
+            raw: false,
+            http_version: None,
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// Whether the session `deadline` has passed
+    fn past_deadline(&self, session_start: Instant) -> bool {
+        self.deadline
+            .is_some_and(|deadline| session_start.elapsed() >= deadline)
+    }
+
+    /// Whether `result` should end the replay (`abort_on_error` / `abort_on_mismatch`)
+    fn aborts_on(&self, result: &ReplayResult) -> bool {
+        match result.error {
+            Some(_) => self.abort_on_error,
+            None => self.abort_on_mismatch && !result.status_match,
+        }
+    }
+}
+
+/// Timeouts resolved by HTTP method, e.g. "POST=60,default=10"
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodTimeouts {
+    pub by_method: Vec<(String, Duration)>,
+    /// Applies to methods not listed in `by_method`
+    pub default: Option<Duration>,
+}
+
+impl MethodTimeouts {
+    /// Parse a spec like "POST=60,PUT=2m,default=10" (bare numbers are seconds)
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut timeouts = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (method, value) = part.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Invalid method timeout '{}', expected METHOD=SECS", part)
+            })?;
+            let duration = parse_timeout(value)?;
+            if method.eq_ignore_ascii_case("default") {
+                timeouts.default = Some(duration);
+            } else {
+                timeouts
+                    .by_method
+                    .push((method.trim().to_uppercase(), duration));
+            }
         }
+        Ok(timeouts)
+    }
+
+    /// Timeout for `method`, if one is configured
+    pub fn resolve(&self, method: &str) -> Option<Duration> {
+        self.by_method
+            .iter()
+            .find(|(m, _)| m.eq_ignore_ascii_case(method))
+            .map(|(_, d)| *d)
+            .or(self.default)
     }
 }
 
@@ -55,6 +260,12 @@ pub enum ErrorKind {
     Tls,
     Request,
     Response,
+    /// Request was dropped by fault injection
+    Fault,
+    /// Pre-request hook failed or printed invalid output
+    Hook,
+    /// Not sent because the session deadline had passed
+    Skipped,
     Unknown,
 }
 
@@ -67,13 +278,68 @@ pub struct ReplayResult {
     pub status: u16,
     pub headers: Vec<(String, String)>,
     pub body: Option<String>,
+    /// Body that isn't valid UTF-8, base64-encoded (`body` is then `None`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
     pub body_hash: Option<String>,
     pub body_size: usize,
     pub duration_ms: u64,
+    /// Milliseconds from session start until this request was sent
+    #[serde(default)]
+    pub start_offset_ms: u64,
     pub expected_status: Option<u16>,
     pub status_match: bool,
     pub error: Option<String>,
     pub error_kind: Option<ErrorKind>,
+    /// Streaming response was cut off after `stream_timeout`; body is partial
+    #[serde(default)]
+    pub truncated_stream: bool,
+    /// Body was cut off at `body_read_limit`; `body_size` and `body_hash` cover the part read
+    #[serde(default)]
+    pub body_truncated: bool,
+    /// Protocol version the response came back with, e.g. "HTTP/1.1" or "HTTP/2"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+    /// TLS version a separate handshake with this request's host negotiated,
+    /// e.g. "TLS 1.3" (with `--tls-info`; probed once per host, not read
+    /// from the request's own connection)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_tls_version: Option<String>,
+    /// TLS cipher suite the same per-host probe negotiated (with `--tls-info`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_tls_cipher: Option<String>,
+    /// Target this request was sent to (set for split replays)
+    pub target: Option<String>,
+    /// Faults injected into this request
+    #[serde(default)]
+    pub faults: Vec<String>,
+    /// Accept header this request was sent with (set for Accept sweeps)
+    pub accept: Option<String>,
+    /// Human-readable label carried over from the capture
+    pub name: Option<String>,
+    /// IP and port the request connected to (with `--annotate-geo`)
+    pub peer_addr: Option<String>,
+    /// Country/ASN of `peer_addr` (with `--geo-db`)
+    pub geo: Option<GeoInfo>,
+    /// Response trailers (e.g. `grpc-status`), sent after a chunked or HTTP/2 body
+    #[serde(default)]
+    pub trailers: Vec<(String, String)>,
+    /// `Content-Encoding` ushio decoded the body from; `body_size` is the decoded size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    /// How many times the request was sent (more than 1 after `--retries`)
+    #[serde(default = "one_attempt")]
+    pub attempts: u32,
+    /// Where the time went (with `--timing`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+    /// Which of the `--repeat` runs of this request this is, from 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_index: Option<usize>,
+}
+
+fn one_attempt() -> u32 {
+    1
 }
 
 /// Metadata about how a replay was executed
@@ -84,6 +350,8 @@ pub struct ReplayMeta {
     pub timeout_secs: u64,
     pub concurrency: usize,
     pub insecure: bool,
+    /// Seed used for fault injection, if any faults were configured
+    pub fault_seed: Option<u64>,
 }
 
 /// Result of a complete replay session
@@ -97,6 +365,150 @@ pub struct ReplaySession {
     pub failed: usize,
     pub status_mismatches: usize,
     pub results: Vec<ReplayResult>,
+    /// Response headers retained per result (lowercase); `None` means all were kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_headers: Option<Vec<String>>,
+    /// Command line that reproduces this replay, including generated seeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// In-flight limit over time (adaptive concurrency only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub concurrency_timeline: Vec<ConcurrencySample>,
+    /// Request that stopped the replay early (`--abort-on-error` /
+    /// `--abort-on-mismatch`); `results` then omits requests never started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aborted_at: Option<usize>,
+    /// Latency over requests that got a response, computed when the replay
+    /// finishes; absent when none did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencySummary>,
+}
+
+impl ReplaySession {
+    /// Latency percentile over requests that got a response
+    pub fn latency_percentile(&self, pct: usize) -> Option<u64> {
+        let mut durations = self.response_durations();
+        (!durations.is_empty()).then(|| adaptive::percentile(&mut durations, pct))
+    }
+
+    /// Latency distribution over requests that got a response
+    pub fn latency_summary(&self) -> Option<LatencySummary> {
+        LatencySummary::from_durations(self.response_durations())
+    }
+
+    /// Number of responses per status code (failed requests are not counted)
+    pub fn status_counts(&self) -> BTreeMap<u16, usize> {
+        let mut counts = BTreeMap::new();
+        for r in self.results.iter().filter(|r| r.error.is_none()) {
+            *counts.entry(r.status).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Outcome of each repeated request across its `--repeat` runs, in
+    /// request order (empty without repeats)
+    pub fn repeat_stats(&self) -> Vec<RepeatStats> {
+        let mut grouped: BTreeMap<usize, Vec<&ReplayResult>> = BTreeMap::new();
+        for r in self.results.iter().filter(|r| r.repeat_index.is_some()) {
+            grouped.entry(r.request_index).or_default().push(r);
+        }
+        grouped
+            .into_iter()
+            .map(|(request_index, runs)| {
+                let mut statuses = BTreeMap::new();
+                for r in runs.iter().filter(|r| r.error.is_none()) {
+                    *statuses.entry(r.status).or_insert(0) += 1;
+                }
+                let mut durations: Vec<u64> = runs
+                    .iter()
+                    .filter(|r| r.error.is_none())
+                    .map(|r| r.duration_ms)
+                    .collect();
+                RepeatStats {
+                    request_index,
+                    method: runs[0].method.clone(),
+                    url: runs[0].url.clone(),
+                    runs: runs.len(),
+                    errors: runs.iter().filter(|r| r.error.is_some()).count(),
+                    statuses,
+                    min_ms: durations.iter().min().copied(),
+                    median_ms: (!durations.is_empty())
+                        .then(|| adaptive::percentile(&mut durations, 50)),
+                    max_ms: durations.iter().max().copied(),
+                }
+            })
+            .collect()
+    }
+
+    fn response_durations(&self) -> Vec<u64> {
+        self.results
+            .iter()
+            .filter(|r| r.error.is_none())
+            .map(|r| r.duration_ms)
+            .collect()
+    }
+}
+
+/// Latency statistics in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min_ms: u64,
+    pub mean_ms: f64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencySummary {
+    fn from_durations(mut durations: Vec<u64>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        let mean = durations.iter().sum::<u64>() as f64 / durations.len() as f64;
+        Some(LatencySummary {
+            count: durations.len(),
+            min_ms: *durations.iter().min().unwrap_or(&0),
+            mean_ms: mean,
+            p50_ms: adaptive::percentile(&mut durations, 50),
+            p90_ms: adaptive::percentile(&mut durations, 90),
+            p95_ms: adaptive::percentile(&mut durations, 95),
+            p99_ms: adaptive::percentile(&mut durations, 99),
+            max_ms: *durations.iter().max().unwrap_or(&0),
+        })
+    }
+}
+
+/// How one request fared across its `--repeat` runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepeatStats {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    pub runs: usize,
+    /// Runs that got no response
+    pub errors: usize,
+    /// Number of responses per status code
+    pub statuses: BTreeMap<u16, usize>,
+    pub min_ms: Option<u64>,
+    pub median_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+impl RepeatStats {
+    /// Every run got a response, all with the same status
+    pub fn is_stable(&self) -> bool {
+        self.errors == 0 && self.statuses.len() <= 1
+    }
+}
+
+/// In-flight limit in effect from `offset_ms` onwards
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcurrencySample {
+    pub offset_ms: u64,
+    pub concurrency: usize,
 }
 
 /// Progress callback: (total_requests, completed_result)
@@ -119,28 +531,279 @@ pub async fn replay_with_progress(
     progress: Option<ProgressFn>,
 ) -> Result<ReplaySession> {
     let target_url = Url::parse(target).context("Invalid target URL")?;
+    let request_targets = vec![(target, &target_url); requests.len()];
+    run_replay(requests, target, &request_targets, false, config, progress).await
+}
+
+/// Send `count` identical requests to one URL, e.g. for latency measurement.
+///
+/// The requests go through the normal replay path, so `config.concurrency`,
+/// timeouts and header mutations apply as usual.
+pub async fn bench(
+    method: &str,
+    url: &str,
+    count: usize,
+    config: ReplayConfig,
+    progress: Option<ProgressFn>,
+) -> Result<ReplaySession> {
+    let parsed = Url::parse(url).context("Invalid URL")?;
+    let origin = parsed.origin().ascii_serialization();
+    let requests = vec![
+        CapturedRequest {
+            method: method.to_uppercase(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        count
+    ];
+    let mut session = replay_with_progress(&requests, &origin, config, progress).await?;
+    session.target = url.to_string();
+    Ok(session)
+}
+
+/// A target in a weighted split replay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTarget {
+    pub target: String,
+    pub weight: u32,
+}
+
+/// Parse a split spec like "https://old=90,https://new=10"
+pub fn parse_split(spec: &str) -> Result<Vec<SplitTarget>> {
+    let targets = spec
+        .split(',')
+        .map(|part| {
+            let (target, weight) = part.trim().rsplit_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Invalid split entry '{}', expected URL=WEIGHT", part)
+            })?;
+            let weight: u32 = weight
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid split weight: '{}'", weight))?;
+            Url::parse(target).context(format!("Invalid split target URL: {}", target))?;
+            Ok(SplitTarget {
+                target: target.to_string(),
+                weight,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if targets.iter().map(|t| t.weight as u64).sum::<u64>() == 0 {
+        anyhow::bail!("Split weights must sum to more than zero");
+    }
+    Ok(targets)
+}
+
+/// Pick the split target for a request index.
+///
+/// The index is hashed so that assignment is deterministic across runs but
+/// spread evenly rather than in contiguous blocks.
+pub fn select_split_target(index: usize, split: &[SplitTarget]) -> usize {
+    let total: u64 = split.iter().map(|t| t.weight as u64).sum();
+    let mut bucket = mix_index(index as u64) % total;
+    for (i, t) in split.iter().enumerate() {
+        if bucket < t.weight as u64 {
+            return i;
+        }
+        bucket -= t.weight as u64;
+    }
+    split.len() - 1
+}
+
+/// SplitMix64 finalizer: a stable, well-distributed hash of an integer
+pub(crate) fn mix_index(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// A reproducible random permutation of `0..len` (Fisher-Yates driven by
+/// `mix_index`), for `--shuffle`
+pub fn shuffle_order(len: usize, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    let mut state = seed;
+    for i in (1..len).rev() {
+        state = mix_index(state);
+        order.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    order
+}
+
+/// Replay with requests distributed across weighted targets.
+///
+/// Produces one combined session; each result records the target it hit.
+/// Targets are picked by capture index (`capture_indices[i]` for request
+/// `i`, or its position when not given), so a request goes to the same
+/// target however the capture was filtered or shuffled.
+pub async fn replay_split(
+    requests: &[CapturedRequest],
+    split: &[SplitTarget],
+    capture_indices: &[usize],
+    config: ReplayConfig,
+    progress: Option<ProgressFn>,
+) -> Result<ReplaySession> {
+    let urls = split
+        .iter()
+        .map(|t| Url::parse(&t.target).context("Invalid target URL"))
+        .collect::<Result<Vec<_>>>()?;
+    let request_targets: Vec<(&str, &Url)> = (0..requests.len())
+        .map(|i| {
+            let pick = select_split_target(original_index(capture_indices, i), split);
+            (split[pick].target.as_str(), &urls[pick])
+        })
+        .collect();
+
+    let label = split
+        .iter()
+        .map(|t| format!("{}={}", t.target, t.weight))
+        .collect::<Vec<_>>()
+        .join(",");
+    run_replay(requests, &label, &request_targets, true, config, progress).await
+}
+
+/// Shared replay loop; `request_targets[i]` is where request `i` is sent
+async fn run_replay(
+    requests: &[CapturedRequest],
+    session_target: &str,
+    request_targets: &[(&str, &Url)],
+    tag_targets: bool,
+    config: ReplayConfig,
+    progress: Option<ProgressFn>,
+) -> Result<ReplaySession> {
+    // With --repeat, each request appears `repeat` times in a row; results
+    // are numbered back to the request's position as they finish
+    let repeat = config.repeat.max(1);
+    let repeated_requests: Vec<CapturedRequest>;
+    let repeated_targets: Vec<(&str, &Url)>;
+    let (requests, request_targets) = if repeat > 1 {
+        repeated_requests = requests
+            .iter()
+            .flat_map(|r| std::iter::repeat(r).take(repeat))
+            .cloned()
+            .collect();
+        repeated_targets = request_targets
+            .iter()
+            .flat_map(|t| std::iter::repeat(*t).take(repeat))
+            .collect();
+        (&repeated_requests[..], &repeated_targets[..])
+    } else {
+        (requests, request_targets)
+    };
 
     // Build HTTP client
-    let mut client_builder = reqwest::Client::builder()
+    let mut client_builder = tls::client_builder(config.client_identity.as_ref())
         .timeout(config.timeout)
         .redirect(reqwest::redirect::Policy::none()); // Don't follow redirects
 
     if config.insecure {
         client_builder = client_builder.danger_accept_invalid_certs(true);
     }
+    match config.http_version {
+        Some(HttpVersion::Http1) => client_builder = client_builder.http1_only(),
+        Some(HttpVersion::Http2) => client_builder = client_builder.http2_prior_knowledge(),
+        None => {}
+    }
+
+    // Bodies are decoded in `decode_body`, which records what the server used
+    client_builder = client_builder.no_gzip().no_brotli();
 
     if let Some(ref proxy_url) = config.proxy {
         let proxy = reqwest::Proxy::all(proxy_url).context("Invalid proxy URL")?;
         client_builder = client_builder.proxy(proxy);
     }
+    for (host, addr) in &config.resolve {
+        client_builder = client_builder.resolve(host, *addr);
+    }
+    if config.timing {
+        client_builder = client_builder
+            .dns_resolver(Arc::new(timing::TimedResolver))
+            .connector_layer(timing::ConnectTimingLayer);
+    }
+
+    let state = SessionState {
+        pacer: config.rate_limit.map(Pacer::per_second),
+        cookie_jar: config.cookie_jar.then(|| Arc::new(Jar::default())),
+        schedule: if config.preserve_timing {
+            timing_schedule(requests, config.max_delay)
+        } else {
+            vec![]
+        },
+        tls_probes: Default::default(),
+    };
+    if let Some(ref jar) = state.cookie_jar {
+        client_builder = client_builder.cookie_provider(jar.clone());
+    }
 
     let client = client_builder
         .build()
         .context("Failed to build HTTP client")?;
 
-    let raw_results = if config.concurrency > 1 {
+    let state = &state;
+    let session_start = Instant::now();
+    let mut concurrency_timeline = Vec::new();
+
+    // Runs as each request finishes, so progress and streamed output keep up
+    let total = requests.len();
+    let aborted_at = std::sync::OnceLock::new();
+    let finish = |mut result: ReplayResult| {
+        if tag_targets {
+            result.target = Some(request_targets[result.request_index].0.to_string());
+        }
+        if repeat > 1 {
+            result.repeat_index = Some(result.request_index % repeat);
+            result.request_index /= repeat;
+        }
+        if let Some(ref cb) = progress {
+            cb(total, &result);
+        }
+        if config.aborts_on(&result) {
+            let _ = aborted_at.set(result.request_index);
+        }
+        result
+    };
+    let finish = &finish;
+    let aborted = || aborted_at.get().is_some();
+    let raw_results = if let Some(ref adaptive) = config.adaptive {
+        // Adaptive replay: the in-flight limit follows observed latency, so
+        // timing (and anything order-dependent) varies between runs
+        let mut controller = AimdController::new(adaptive.target_p95, adaptive.max_concurrency);
+        concurrency_timeline.push(ConcurrencySample {
+            offset_ms: 0,
+            concurrency: controller.limit(),
+        });
+
+        let mut in_flight = FuturesUnordered::new();
+        let mut results = Vec::with_capacity(requests.len());
+        let mut next = 0;
+        loop {
+            while in_flight.len() < controller.limit() && next < requests.len() && !aborted() {
+                in_flight.push(replay_single_or_error(
+                    &client,
+                    &requests[next],
+                    next,
+                    request_targets[next].1,
+                    &config,
+                    state,
+                    session_start,
+                ));
+                next += 1;
+            }
+            let Some(result) = in_flight.next().await else {
+                break;
+            };
+            let result = finish(result);
+            if let Some(limit) = controller.observe(result.duration_ms, result.error.is_some()) {
+                concurrency_timeline.push(ConcurrencySample {
+                    offset_ms: session_start.elapsed().as_millis() as u64,
+                    concurrency: limit,
+                });
+            }
+            results.push(result);
+        }
+        results.sort_by_key(|r| (r.request_index, r.repeat_index));
+        results
+    } else if config.concurrency > 1 {
         // Concurrent replay with ordered results via buffered()
-        let target_url_ref = &target_url;
         let client_ref = &client;
         let config_ref = &config;
 
@@ -149,22 +812,50 @@ pub async fn replay_with_progress(
                 .iter()
                 .enumerate()
                 .map(|(index, request)| async move {
-                    replay_single_or_error(client_ref, request, index, target_url_ref, config_ref)
-                        .await
+                    // Requests queued behind an abort are never started
+                    if aborted() {
+                        return None;
+                    }
+                    Some(finish(
+                        replay_single_or_error(
+                            client_ref,
+                            request,
+                            index,
+                            request_targets[index].1,
+                            config_ref,
+                            state,
+                            session_start,
+                        )
+                        .await,
+                    ))
                 }),
         )
         .buffered(config.concurrency)
+        .filter_map(|result| async move { result })
         .collect::<Vec<_>>()
         .await
     } else {
         // Sequential replay with delay support
         let mut results = Vec::with_capacity(requests.len());
         for (index, request) in requests.iter().enumerate() {
-            if index > 0 && config.delay_ms > 0 {
+            if index > 0 && config.delay_ms > 0 && !config.past_deadline(session_start) {
                 tokio::time::sleep(Duration::from_millis(config.delay_ms)).await;
             }
-            results
-                .push(replay_single_or_error(&client, request, index, &target_url, &config).await);
+            results.push(finish(
+                replay_single_or_error(
+                    &client,
+                    request,
+                    index,
+                    request_targets[index].1,
+                    &config,
+                    state,
+                    session_start,
+                )
+                .await,
+            ));
+            if aborted() {
+                break;
+            }
         }
         results
     };
@@ -174,7 +865,6 @@ pub async fn replay_with_progress(
     let mut failed = 0;
     let mut status_mismatches = 0;
 
-    let total = raw_results.len();
     for result in raw_results {
         if result.error.is_some() {
             failed += 1;
@@ -184,14 +874,11 @@ pub async fn replay_with_progress(
                 status_mismatches += 1;
             }
         }
-        if let Some(ref cb) = progress {
-            cb(total, &result);
-        }
         results.push(result);
     }
 
-    Ok(ReplaySession {
-        target: target.to_string(),
+    let mut session = ReplaySession {
+        target: session_target.to_string(),
         timestamp: chrono::Utc::now(),
         meta: ReplayMeta {
             ushio_version: env!("CARGO_PKG_VERSION").to_string(),
@@ -199,153 +886,1495 @@ pub async fn replay_with_progress(
             timeout_secs: config.timeout.as_secs(),
             concurrency: config.concurrency,
             insecure: config.insecure,
+            fault_seed: (!config.faults.is_empty()).then_some(config.fault_seed),
         },
         total_requests: requests.len(),
         successful,
         failed,
         status_mismatches,
         results,
-    })
+        captured_headers: config.keep_headers,
+        command: None,
+        concurrency_timeline,
+        aborted_at: aborted_at.into_inner(),
+        latency: None,
+    };
+    session.latency = session.latency_summary();
+    Ok(session)
 }
 
-/// Classify an error into an ErrorKind
-fn classify_error(err: &anyhow::Error) -> ErrorKind {
-    let msg = err.to_string().to_lowercase();
-    if msg.contains("timed out") || msg.contains("timeout") {
-        ErrorKind::Timeout
-    } else if msg.contains("dns") || msg.contains("resolve") || msg.contains("no such host") {
-        ErrorKind::Dns
-    } else if msg.contains("ssl") || msg.contains("tls") || msg.contains("certificate") {
-        ErrorKind::Tls
-    } else if msg.contains("connect")
-        || msg.contains("connection refused")
-        || msg.contains("connection reset")
-    {
-        ErrorKind::Connect
-    } else if msg.contains("request") {
-        ErrorKind::Request
-    } else if msg.contains("response") || msg.contains("body") {
-        ErrorKind::Response
-    } else {
-        ErrorKind::Unknown
-    }
+/// A request whose status depended on the order it was replayed in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderSensitiveRequest {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    pub forward_status: u16,
+    pub reversed_status: u16,
 }
 
-/// Replay a single request, converting errors into a ReplayResult
-async fn replay_single_or_error(
-    client: &reqwest::Client,
-    request: &CapturedRequest,
-    index: usize,
-    target_url: &Url,
-    config: &ReplayConfig,
-) -> ReplayResult {
-    match replay_single(client, request, index, target_url, config).await {
-        Ok(result) => result,
-        Err(e) => {
-            let error_kind = classify_error(&e);
-            ReplayResult {
-                request_index: index,
-                method: request.method.clone(),
-                url: rewrite_url(&request.url, target_url).unwrap_or_else(|_| request.url.clone()),
-                status: 0,
-                headers: vec![],
-                body: None,
-                body_hash: None,
-                body_size: 0,
-                duration_ms: 0,
-                expected_status: request.expected_status,
-                status_match: false,
-                error: Some(e.to_string()),
-                error_kind: Some(error_kind),
-            }
+/// Result of replaying a capture in original and reversed order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderSensitivityReport {
+    pub target: String,
+    pub total_requests: usize,
+    pub order_sensitive_indices: Vec<usize>,
+    pub requests: Vec<OrderSensitiveRequest>,
+}
+
+impl OrderSensitivityReport {
+    /// Report capture indices, as [`restore_request_indices`] does for sessions
+    pub fn restore_request_indices(&mut self, original: &[usize]) {
+        for request in &mut self.requests {
+            request.request_index = original_index(original, request.request_index);
+        }
+        for index in &mut self.order_sensitive_indices {
+            *index = original_index(original, *index);
         }
     }
 }
 
-/// Replay a single request
-async fn replay_single(
-    client: &reqwest::Client,
-    request: &CapturedRequest,
-    index: usize,
-    target_url: &Url,
-    config: &ReplayConfig,
-) -> Result<ReplayResult> {
-    // Rewrite URL to target
-    let url = rewrite_url(&request.url, target_url)?;
-
-    // Build headers
-    let headers = apply_mutations(
-        &request.headers,
-        &config.header_mutations,
-        config.strip_cookies,
-    );
-    let header_map = build_header_map(&headers)?;
+/// Replay the capture forward and then reversed against the same target,
+/// reporting requests whose status differs between the two orderings.
+///
+/// This surfaces hidden inter-request dependencies (e.g. a GET that only
+/// succeeds after a prior POST created the resource).
+pub async fn order_sensitivity(
+    requests: &[CapturedRequest],
+    target: &str,
+    config: ReplayConfig,
+) -> Result<OrderSensitivityReport> {
+    let forward = replay(requests, target, config.clone()).await?;
 
-    // Build request
-    let method: reqwest::Method = request.method.parse().context("Invalid HTTP method")?;
-    let mut req = client.request(method, &url).headers(header_map);
+    let reversed_requests: Vec<CapturedRequest> = requests.iter().rev().cloned().collect();
+    let reversed = replay(&reversed_requests, target, config).await?;
 
-    // Add body if present
-    if let Some(ref body) = request.body {
-        req = req.body(body.clone());
+    let n = requests.len();
+    let mut sensitive = Vec::new();
+    for fwd in &forward.results {
+        // Reversed position i holds original request n - 1 - i
+        let rev = &reversed.results[n - 1 - fwd.request_index];
+        if fwd.status != rev.status {
+            sensitive.push(OrderSensitiveRequest {
+                request_index: fwd.request_index,
+                method: fwd.method.clone(),
+                url: fwd.url.clone(),
+                forward_status: fwd.status,
+                reversed_status: rev.status,
+            });
+        }
     }
 
-    // Execute with timing
-    let start = Instant::now();
-    let response = req.send().await.context("Request failed")?;
-    let duration = start.elapsed();
+    Ok(OrderSensitivityReport {
+        target: target.to_string(),
+        total_requests: n,
+        order_sensitive_indices: sensitive.iter().map(|r| r.request_index).collect(),
+        requests: sensitive,
+    })
+}
 
-    let status = response.status().as_u16();
-    let response_headers: Vec<(String, String)> = response
-        .headers()
-        .iter()
-        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+/// Parse a comma-separated list of Accept values to sweep
+pub fn parse_accept_sweep(spec: &str) -> Result<Vec<String>> {
+    let values: Vec<String> = spec
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
         .collect();
+    if values.len() < 2 {
+        anyhow::bail!("Accept sweep needs at least two values, got '{}'", spec);
+    }
+    Ok(values)
+}
 
-    let body_bytes = response
-        .bytes()
-        .await
-        .context("Failed to read response body")?;
-    let body_size = body_bytes.len();
+/// Replay every request once per Accept value.
+///
+/// Results are ordered request-major: request `i` sent with `accepts[j]`
+/// lands at index `i * accepts.len() + j` and is tagged with `accept`.
+pub async fn replay_accept_sweep(
+    requests: &[CapturedRequest],
+    target: &str,
+    accepts: &[String],
+    config: ReplayConfig,
+    progress: Option<ProgressFn>,
+) -> Result<ReplaySession> {
+    let expanded: Vec<CapturedRequest> = requests
+        .iter()
+        .flat_map(|request| {
+            accepts.iter().map(move |accept| {
+                let mut variant = request.clone();
+                variant
+                    .headers
+                    .retain(|(name, _)| !name.eq_ignore_ascii_case("accept"));
+                variant.headers.push(("Accept".to_string(), accept.clone()));
+                variant
+            })
+        })
+        .collect();
 
-    // Always compute hash for comparison even when body capture is off
-    let body_hash = if !body_bytes.is_empty() {
-        let hash = Sha256::digest(&body_bytes);
-        Some(format!("{:x}", hash))
-    } else {
-        None
-    };
+    let mut session = replay_with_progress(&expanded, target, config, progress).await?;
+    for result in &mut session.results {
+        result.accept = Some(accepts[result.request_index % accepts.len()].clone());
+    }
+    Ok(session)
+}
 
-    let body = if config.capture_body && body_size <= MAX_BODY_CAPTURE {
-        String::from_utf8(body_bytes.to_vec()).ok()
-    } else {
-        None
-    };
+/// What one Accept value produced for a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptVariant {
+    pub accept: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+}
 
-    let status_match = request
-        .expected_status
-        .map(|expected| expected == status)
-        .unwrap_or(true);
+/// A request whose status or content type changed with the Accept header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiationChange {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    pub variants: Vec<AcceptVariant>,
+}
 
-    Ok(ReplayResult {
-        request_index: index,
-        method: request.method.clone(),
-        url,
-        status,
-        headers: response_headers,
-        body,
-        body_hash,
-        body_size,
-        duration_ms: duration.as_millis() as u64,
-        expected_status: request.expected_status,
-        status_match,
-        error: None,
-        error_kind: None,
-    })
+/// Result of an Accept header sweep
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcceptSweepReport {
+    pub target: String,
+    pub accept_values: Vec<String>,
+    pub total_requests: usize,
+    pub changed_indices: Vec<usize>,
+    pub requests: Vec<NegotiationChange>,
+}
+
+impl AcceptSweepReport {
+    /// Report capture indices, as [`restore_request_indices`] does for sessions
+    pub fn restore_request_indices(&mut self, original: &[usize]) {
+        for request in &mut self.requests {
+            request.request_index = original_index(original, request.request_index);
+        }
+        for index in &mut self.changed_indices {
+            *index = original_index(original, *index);
+        }
+    }
+}
+
+/// Point a sweep session's results back at capture indices. Sweep results
+/// are numbered per variant, so every Accept value sent for a request gets
+/// that request's capture index.
+pub fn restore_sweep_indices(session: &mut ReplaySession, original: &[usize], accepts: usize) {
+    for result in &mut session.results {
+        result.request_index = original_index(original, result.request_index / accepts);
+    }
+    if let Some(index) = session.aborted_at {
+        session.aborted_at = Some(original_index(original, index / accepts));
+    }
+}
+
+/// Group a sweep session by original request and report requests whose
+/// status or media type differs between Accept values
+pub fn accept_sweep_report(session: &ReplaySession, accepts: &[String]) -> AcceptSweepReport {
+    let mut changed = Vec::new();
+    for (request_index, group) in session.results.chunks(accepts.len()).enumerate() {
+        let variants: Vec<AcceptVariant> = group
+            .iter()
+            .map(|r| AcceptVariant {
+                accept: r.accept.clone().unwrap_or_default(),
+                status: r.status,
+                content_type: r
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, v)| media_type(v)),
+            })
+            .collect();
+
+        let first = &variants[0];
+        if variants
+            .iter()
+            .any(|v| v.status != first.status || v.content_type != first.content_type)
+        {
+            changed.push(NegotiationChange {
+                request_index,
+                method: group[0].method.clone(),
+                url: group[0].url.clone(),
+                variants,
+            });
+        }
+    }
+
+    AcceptSweepReport {
+        target: session.target.clone(),
+        accept_values: accepts.to_vec(),
+        total_requests: session.results.len() / accepts.len(),
+        changed_indices: changed.iter().map(|c| c.request_index).collect(),
+        requests: changed,
+    }
+}
+
+/// Media type without parameters, e.g. "text/html; charset=utf-8" -> "text/html"
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Accept-Encoding values sent for each request by `compression_check`
+const COMPRESSION_ENCODINGS: [&str; 2] = ["gzip", "identity"];
+
+/// On-wire sizes of one request sent with and without compression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionMeasurement {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    /// Content-Encoding the server chose for the gzip request
+    pub content_encoding: Option<String>,
+    /// Body bytes received with `Accept-Encoding: gzip`
+    pub compressed_size: usize,
+    /// Body bytes received with `Accept-Encoding: identity`
+    pub uncompressed_size: usize,
+    /// `compressed_size / uncompressed_size`; null for empty bodies
+    pub ratio: Option<f64>,
+}
+
+/// Compression measurements for one target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionReport {
+    pub target: String,
+    pub total_requests: usize,
+    pub compressed_size: usize,
+    pub uncompressed_size: usize,
+    /// Ratio over all bodies combined
+    pub ratio: Option<f64>,
+    pub requests: Vec<CompressionMeasurement>,
+}
+
+impl CompressionReport {
+    /// Report capture indices, as [`restore_request_indices`] does for sessions
+    pub fn restore_request_indices(&mut self, original: &[usize]) {
+        for request in &mut self.requests {
+            request.request_index = original_index(original, request.request_index);
+        }
+    }
+}
+
+/// Compressed size as a fraction of the uncompressed size (lower is better)
+pub fn compression_ratio(compressed_size: usize, uncompressed_size: usize) -> Option<f64> {
+    (uncompressed_size > 0).then(|| compressed_size as f64 / uncompressed_size as f64)
+}
+
+/// Send every request with `Accept-Encoding: gzip` and again with
+/// `identity`, measuring body sizes as received on the wire.
+pub async fn compression_check(
+    requests: &[CapturedRequest],
+    target: &str,
+    config: ReplayConfig,
+) -> Result<CompressionReport> {
+    let expanded: Vec<CapturedRequest> = requests
+        .iter()
+        .flat_map(|request| {
+            COMPRESSION_ENCODINGS.iter().map(move |encoding| {
+                let mut variant = request.clone();
+                variant
+                    .headers
+                    .retain(|(name, _)| !name.eq_ignore_ascii_case("accept-encoding"));
+                variant
+                    .headers
+                    .push(("Accept-Encoding".to_string(), encoding.to_string()));
+                variant
+            })
+        })
+        .collect();
+
+    let config = ReplayConfig {
+        no_decompress: true,
+        ..config
+    };
+    let session = replay(&expanded, target, config).await?;
+    Ok(compression_report(&session))
+}
+
+/// Pair up a `compression_check` session (gzip then identity per request)
+pub fn compression_report(session: &ReplaySession) -> CompressionReport {
+    let requests: Vec<CompressionMeasurement> = session
+        .results
+        .chunks(COMPRESSION_ENCODINGS.len())
+        .enumerate()
+        .map(|(request_index, pair)| {
+            let (gzip, identity) = (&pair[0], &pair[1]);
+            CompressionMeasurement {
+                request_index,
+                method: gzip.method.clone(),
+                url: gzip.url.clone(),
+                content_encoding: gzip
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-encoding"))
+                    .map(|(_, v)| v.clone()),
+                compressed_size: gzip.body_size,
+                uncompressed_size: identity.body_size,
+                ratio: compression_ratio(gzip.body_size, identity.body_size),
+            }
+        })
+        .collect();
+
+    let compressed_size = requests.iter().map(|r| r.compressed_size).sum();
+    let uncompressed_size = requests.iter().map(|r| r.uncompressed_size).sum();
+    CompressionReport {
+        target: session.target.clone(),
+        total_requests: requests.len(),
+        compressed_size,
+        uncompressed_size,
+        ratio: compression_ratio(compressed_size, uncompressed_size),
+        requests,
+    }
+}
+
+/// Response headers that report a CDN cache outcome, in lookup order
+const CACHE_STATUS_HEADERS: &[&str] = &["cf-cache-status", "x-cache-status", "x-cache"];
+
+/// The cache status a response reported, e.g. "HIT" or "MISS from edge-1"
+pub fn cache_status(result: &ReplayResult) -> Option<&str> {
+    CACHE_STATUS_HEADERS.iter().find_map(|wanted| {
+        result
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, v)| v.as_str())
+    })
+}
+
+/// Whether a cache status counts as a hit ("HIT", "TCP_HIT", "HIT from cdn", ...)
+fn is_cache_hit(status: &str) -> bool {
+    status.to_uppercase().contains("HIT")
+}
+
+/// A request that was not served from cache on the verify pass
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMiss {
+    pub request_index: usize,
+    pub method: String,
+    pub url: String,
+    /// Reported cache status, or null if the response had no cache header
+    pub cache_status: Option<String>,
+}
+
+/// Result of warming a cache and replaying again to verify it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheWarmReport {
+    pub target: String,
+    pub total_requests: usize,
+    pub hits: usize,
+    pub hit_ratio: f64,
+    pub min_hit_ratio: f64,
+    pub passed: bool,
+    pub misses: Vec<CacheMiss>,
+}
+
+impl CacheWarmReport {
+    /// Report capture indices, as [`restore_request_indices`] does for sessions
+    pub fn restore_request_indices(&mut self, original: &[usize]) {
+        for miss in &mut self.misses {
+            miss.request_index = original_index(original, miss.request_index);
+        }
+    }
+}
+
+/// Replay the capture once to warm caches, then again to measure how many
+/// responses were cache hits.
+///
+/// The report is built from the second pass only; requests without a
+/// cache status header count as misses.
+pub async fn warm_and_verify(
+    requests: &[CapturedRequest],
+    target: &str,
+    config: ReplayConfig,
+    min_hit_ratio: f64,
+) -> Result<CacheWarmReport> {
+    replay(requests, target, config.clone()).await?;
+    let verify = replay(requests, target, config).await?;
+    Ok(cache_warm_report(&verify, min_hit_ratio))
+}
+
+/// Measure the cache hit ratio of a (verify pass) session
+pub fn cache_warm_report(session: &ReplaySession, min_hit_ratio: f64) -> CacheWarmReport {
+    let misses: Vec<CacheMiss> = session
+        .results
+        .iter()
+        .filter(|r| !cache_status(r).is_some_and(is_cache_hit))
+        .map(|r| CacheMiss {
+            request_index: r.request_index,
+            method: r.method.clone(),
+            url: r.url.clone(),
+            cache_status: cache_status(r).map(str::to_string),
+        })
+        .collect();
+
+    let total = session.results.len();
+    let hits = total - misses.len();
+    let hit_ratio = if total == 0 {
+        0.0
+    } else {
+        hits as f64 / total as f64
+    };
+
+    CacheWarmReport {
+        target: session.target.clone(),
+        total_requests: total,
+        hits,
+        hit_ratio,
+        min_hit_ratio,
+        passed: hit_ratio >= min_hit_ratio,
+        misses,
+    }
+}
+
+/// Classify an error into an ErrorKind
+fn classify_error(err: &anyhow::Error) -> ErrorKind {
+    // The cause sits below our "Request failed" context, so look at the whole chain
+    let msg = format!("{:#}", err).to_lowercase();
+    let timed_out = err
+        .chain()
+        .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout());
+    if msg.contains("pre-request hook") {
+        ErrorKind::Hook
+    } else if timed_out || msg.contains("timed out") || msg.contains("timeout") {
+        ErrorKind::Timeout
+    } else if msg.contains("dns") || msg.contains("resolve") || msg.contains("no such host") {
+        ErrorKind::Dns
+    } else if msg.contains("ssl") || msg.contains("tls") || msg.contains("certificate") {
+        ErrorKind::Tls
+    } else if msg.contains("connect")
+        || msg.contains("connection refused")
+        || msg.contains("connection reset")
+    {
+        ErrorKind::Connect
+    } else if msg.contains("request") {
+        ErrorKind::Request
+    } else if msg.contains("response") || msg.contains("body") {
+        ErrorKind::Response
+    } else {
+        ErrorKind::Unknown
+    }
+}
+
+/// State shared by all requests of one replay session
+#[derive(Debug)]
+struct SessionState {
+    pacer: Option<Pacer>,
+    cookie_jar: Option<Arc<Jar>>,
+    /// Start offset of each request under `--preserve-timing`
+    schedule: Vec<Duration>,
+    /// `--tls-info` probe results by "host:port"; `None` when the probe failed
+    tls_probes: tokio::sync::Mutex<HashMap<String, Option<TlsParams>>>,
+}
+
+impl SessionState {
+    /// TLS parameters for the host of `url`, probed on first use. `None`
+    /// for plain HTTP and for hosts whose probe failed.
+    async fn tls_params(&self, url: &str, config: &ReplayConfig) -> Option<TlsParams> {
+        let url = Url::parse(url).ok().filter(|u| u.scheme() == "https")?;
+        let host = url.host_str()?.trim_matches(['[', ']']).to_string();
+        let port = url.port_or_known_default()?;
+        let key = format!("{}:{}", host, port);
+
+        // Held across the probe, so concurrent requests don't probe a host twice
+        let mut probes = self.tls_probes.lock().await;
+        if let Some(params) = probes.get(&key) {
+            return params.clone();
+        }
+        // Connect where the client does: --resolve overrides the address, not the port
+        let addr = match config.resolve.iter().find(|(h, _)| *h == host) {
+            Some((_, addr)) => Some(SocketAddr::new(addr.ip(), port)),
+            None => tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .ok()
+                .and_then(|mut addrs| addrs.next()),
+        };
+        let params = match addr {
+            Some(addr) => tls::probe(&host, addr, config.timeout, config.insecure)
+                .await
+                .ok(),
+            None => None,
+        };
+        probes.insert(key, params.clone());
+        params
+    }
+}
+
+/// Offsets from the session start at which each request should be sent to
+/// reproduce the captured think-time, each gap capped at `max_delay`
+pub fn timing_schedule(requests: &[CapturedRequest], max_delay: Option<Duration>) -> Vec<Duration> {
+    let mut offset = Duration::ZERO;
+    requests
+        .iter()
+        .enumerate()
+        .map(|(index, request)| {
+            if index > 0 {
+                let gap = Duration::from_millis(request.delay_ms.unwrap_or(0));
+                offset += max_delay.map_or(gap, |max| gap.min(max));
+            }
+            offset
+        })
+        .collect()
+}
+
+/// Fixed-interval request pacing for `--rate-limit`.
+///
+/// Each request reserves the next free send slot, so the cap holds across
+/// all in-flight requests regardless of concurrency.
+#[derive(Debug)]
+struct Pacer {
+    interval: Duration,
+    next_slot: std::sync::Mutex<Option<Instant>>,
+}
+
+impl Pacer {
+    fn per_second(rate: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / rate),
+            next_slot: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Reserve the earliest send slot at or after `now`
+    fn reserve(&self, now: Instant) -> Instant {
+        let mut next = self.next_slot.lock().unwrap();
+        let slot = next.map_or(now, |n| n.max(now));
+        *next = Some(slot + self.interval);
+        slot
+    }
+
+    async fn wait(&self) {
+        let slot = self.reserve(Instant::now());
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
+
+/// Replay a single request, converting errors into a ReplayResult
+async fn replay_single_or_error(
+    client: &reqwest::Client,
+    request: &CapturedRequest,
+    index: usize,
+    target_url: &Url,
+    config: &ReplayConfig,
+    state: &SessionState,
+    session_start: Instant,
+) -> ReplayResult {
+    let skipped = || {
+        error_result(
+            request,
+            index,
+            target_url,
+            session_start.elapsed().as_millis() as u64,
+            "skipped: deadline exceeded".to_string(),
+            ErrorKind::Skipped,
+        )
+    };
+    if let Some(offset) = state.schedule.get(index) {
+        // Don't wait for a slot the deadline cuts off anyway
+        if config.deadline.is_some_and(|deadline| *offset >= deadline) {
+            return skipped();
+        }
+        tokio::time::sleep_until((session_start + *offset).into()).await;
+    }
+    if let Some(ref pacer) = state.pacer {
+        pacer.wait().await;
+    }
+    if config.past_deadline(session_start) {
+        return skipped();
+    }
+
+    let faults = fault::faults_for(index, config.fault_seed, &config.faults);
+
+    if faults.contains(&Fault::Drop) {
+        let start_offset_ms = session_start.elapsed().as_millis() as u64;
+        let mut result = error_result(
+            request,
+            index,
+            target_url,
+            start_offset_ms,
+            "Request dropped by fault injection".to_string(),
+            ErrorKind::Fault,
+        );
+        result.faults = faults.iter().map(|f| f.to_string()).collect();
+        return result;
+    }
+
+    let mut request = Cow::Borrowed(request);
+    let mut fault_names = Vec::new();
+    for f in &faults {
+        match f {
+            Fault::Delay(d) => tokio::time::sleep(*d).await,
+            // Only recorded when there was a body to cut short
+            Fault::TruncateBody => {
+                if !truncate_request_body(&mut request, config.body_dir.as_deref()) {
+                    continue;
+                }
+            }
+            Fault::Drop => {}
+        }
+        fault_names.push(f.to_string());
+    }
+
+    let mut attempts = 0;
+    let mut result = loop {
+        attempts += 1;
+        let start_offset_ms = session_start.elapsed().as_millis() as u64;
+        let result = match replay_single(
+            client,
+            &request,
+            index,
+            target_url,
+            config,
+            state.cookie_jar.as_deref(),
+            session_start,
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                let error_kind = classify_error(&e);
+                error_result(
+                    &request,
+                    index,
+                    target_url,
+                    start_offset_ms,
+                    e.to_string(),
+                    error_kind,
+                )
+            }
+        };
+        if attempts > config.retries
+            || !should_retry(&result, &config.retry_statuses)
+            || config.past_deadline(session_start)
+        {
+            break result;
+        }
+        tokio::time::sleep(retry_delay(config.retry_backoff, attempts)).await;
+        if let Some(ref pacer) = state.pacer {
+            pacer.wait().await;
+        }
+    };
+    result.faults = fault_names;
+    result.attempts = attempts;
+    if config.tls_info && result.error.is_none() {
+        if let Some(params) = state.tls_params(&result.url, config).await {
+            result.probe_tls_version = Some(params.version);
+            result.probe_tls_cipher = Some(params.cipher);
+        }
+    }
+    result
+}
+
+/// Cut the request body to half its length for the `truncate-body` fault:
+/// text bodies on a character boundary, base64 bodies as decoded bytes.
+/// Returns false when there was no body to truncate.
+fn truncate_request_body(request: &mut Cow<'_, CapturedRequest>, body_dir: Option<&Path>) -> bool {
+    // An unreadable body file or invalid base64 fails later, when the request is built
+    if let Some(ref body) = request.body {
+        let truncated = match body.load(body_dir) {
+            Ok(text) if !text.is_empty() => fault::truncate_body(&text),
+            _ => return false,
+        };
+        request.to_mut().body = Some(Body::Inline(truncated));
+        return true;
+    }
+    match request.body_base64.as_ref().map(|b| BASE64.decode(b)) {
+        Some(Ok(bytes)) if !bytes.is_empty() => {
+            request.to_mut().body_base64 = Some(BASE64.encode(&bytes[..bytes.len() / 2]));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether a result looks transient: a connection error, a timeout, or one
+/// of `retry_statuses`
+fn should_retry(result: &ReplayResult, retry_statuses: &[u16]) -> bool {
+    match result.error_kind {
+        Some(ErrorKind::Connect | ErrorKind::Timeout) => true,
+        Some(_) => false,
+        None => retry_statuses.contains(&result.status),
+    }
+}
+
+/// Backoff before retry number `attempt` (1-based): `base`, then doubling
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+}
+
+/// Build the result recorded for a request that produced no response
+fn error_result(
+    request: &CapturedRequest,
+    index: usize,
+    target_url: &Url,
+    start_offset_ms: u64,
+    error: String,
+    error_kind: ErrorKind,
+) -> ReplayResult {
+    ReplayResult {
+        request_index: index,
+        method: request.method.clone(),
+        url: rewrite_url(&request.url, target_url).unwrap_or_else(|_| request.url.clone()),
+        status: 0,
+        headers: vec![],
+        body: None,
+        body_base64: None,
+        body_hash: None,
+        body_size: 0,
+        duration_ms: 0,
+        start_offset_ms,
+        expected_status: request.expected_status,
+        status_match: false,
+        error: Some(error),
+        error_kind: Some(error_kind),
+        truncated_stream: false,
+        body_truncated: false,
+        http_version: None,
+        probe_tls_version: None,
+        probe_tls_cipher: None,
+        target: None,
+        faults: vec![],
+        accept: None,
+        name: request.name.clone(),
+        peer_addr: None,
+        geo: None,
+        trailers: vec![],
+        content_encoding: None,
+        attempts: 1,
+        timing: None,
+        repeat_index: None,
+    }
+}
+
+/// Replay a single request
+async fn replay_single(
+    client: &reqwest::Client,
+    request: &CapturedRequest,
+    index: usize,
+    target_url: &Url,
+    config: &ReplayConfig,
+    cookie_jar: Option<&Jar>,
+    session_start: Instant,
+) -> Result<ReplayResult> {
+    // Rewrite URL to target, then fill in variables
+    let url = request_url(request, target_url, config)?;
+
+    // Raw blobs skip every mutation: the bytes are the test case
+    if let Some(blob) = request.raw.as_ref().filter(|_| config.raw) {
+        let timeout = config
+            .method_timeouts
+            .resolve(&request.method)
+            .unwrap_or(config.timeout);
+
+        let start = Instant::now();
+        let (response, peer) = raw::send(target_url, blob.as_bytes(), timeout, config.insecure)
+            .await
+            .context("Request failed")?;
+        return Ok(build_result(
+            request,
+            index,
+            url,
+            config,
+            ResponseParts {
+                status: response.status,
+                peer_addr: config.annotate_geo.then(|| peer.to_string()),
+                headers: response.headers,
+                body: response.body,
+                trailers: vec![],
+                content_encoding: None,
+                truncated_stream: false,
+                body_truncated: false,
+                http_version: None,
+                start,
+                duration: start.elapsed(),
+                timing: None,
+            },
+            session_start,
+        ));
+    }
+
+    let (headers, body) = resolve_request(request, index, &url, config, cookie_jar).await?;
+    // Binary bodies (base64 in the capture) are sent as decoded bytes
+    let binary_body = match (&body, &request.body_base64) {
+        (None, Some(encoded)) if !config.strip_bodies => Some(
+            BASE64
+                .decode(encoded)
+                .context("Invalid base64 in body_base64")?,
+        ),
+        _ => None,
+    };
+    let body = body
+        .as_deref()
+        .map(str::as_bytes)
+        .or(binary_body.as_deref());
+
+    // Folded headers bypass reqwest, which rejects CR/LF in header values
+    if let Some(ref folded) = config.obs_fold {
+        let parsed = Url::parse(&url).context("Invalid target URL")?;
+        let raw = fold::build_raw_request(&request.method, &parsed, &headers, folded, body);
+        let timeout = config
+            .method_timeouts
+            .resolve(&request.method)
+            .unwrap_or(config.timeout);
+
+        let start = Instant::now();
+        let response = fold::send_raw(&parsed, &raw, timeout)
+            .await
+            .context("Request failed")?;
+        return Ok(build_result(
+            request,
+            index,
+            url,
+            config,
+            ResponseParts {
+                status: response.status,
+                peer_addr: None,
+                headers: response.headers,
+                body: response.body,
+                trailers: vec![],
+                content_encoding: None,
+                truncated_stream: false,
+                body_truncated: false,
+                http_version: None,
+                start,
+                duration: start.elapsed(),
+                timing: None,
+            },
+            session_start,
+        ));
+    }
+
+    // Offer what decode_body understands, as reqwest would with its own decoding
+    let mut headers = headers;
+    if !config.no_decompress
+        && !headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case("accept-encoding"))
+    {
+        headers.push((
+            "Accept-Encoding".to_string(),
+            "gzip, deflate, br".to_string(),
+        ));
+    }
+    let header_map = build_header_map(&headers)?;
+
+    // Build request
+    let method: reqwest::Method = request.method.parse().context("Invalid HTTP method")?;
+    let mut req = client.request(method, &url).headers(header_map);
+    if let Some(timeout) = config.method_timeouts.resolve(&request.method) {
+        req = req.timeout(timeout);
+    }
+
+    // Add body if present
+    if let Some(body) = body {
+        req = req.body(body.to_vec());
+    }
+
+    // Execute with timing
+    let start = Instant::now();
+    let (response, phases) = if config.timing {
+        timing::measure(req.send()).await
+    } else {
+        (req.send().await, timing::ConnectPhases::default())
+    };
+    let response = response.context("Request failed")?;
+    let duration = start.elapsed();
+
+    let status = response.status().as_u16();
+    let http_version = Some(version_label(response.version()).to_string());
+    let peer_addr = config
+        .annotate_geo
+        .then(|| response.remote_addr().map(|a| a.to_string()))
+        .flatten();
+    let response_headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect();
+
+    // Trailers arrive after the last body frame, so only a fully read body has them
+    let (body_bytes, trailers, truncated_stream, body_truncated) = match config.stream_timeout {
+        Some(window) if is_streaming_response(response.headers()) => {
+            let (bytes, truncated, cut) =
+                read_stream_window(response, window, config.body_read_limit).await?;
+            (bytes, vec![], truncated, cut)
+        }
+        _ => match config.body_read_limit {
+            Some(limit) => {
+                let (bytes, trailers, cut) = read_body_limited(response.into(), limit).await?;
+                (bytes, trailers, false, cut)
+            }
+            None => {
+                let body: reqwest::Body = response.into();
+                let collected = body
+                    .collect()
+                    .await
+                    .context("Failed to read response body")?;
+                let trailers = collected.trailers().map(header_pairs).unwrap_or_default();
+                (collected.to_bytes().to_vec(), trailers, false, false)
+            }
+        },
+    };
+    let timing = config
+        .timing
+        .then(|| Timing::new(phases, duration, start.elapsed() - duration));
+
+    let (body_bytes, content_encoding) = if config.no_decompress {
+        (body_bytes, None)
+    } else {
+        let encoding = response_headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("content-encoding"))
+            .map(|(_, v)| v.trim().to_ascii_lowercase());
+        // Undecodable bodies (unknown or broken encodings, cut-off streams) are kept as received
+        match encoding.and_then(|e| decode_body(&e, &body_bytes).map(|b| (b, e))) {
+            Some((decoded, encoding)) => (decoded, Some(encoding)),
+            None => (body_bytes, None),
+        }
+    };
+
+    Ok(build_result(
+        request,
+        index,
+        url,
+        config,
+        ResponseParts {
+            status,
+            peer_addr,
+            headers: response_headers,
+            body: body_bytes,
+            trailers,
+            content_encoding,
+            truncated_stream,
+            body_truncated,
+            http_version,
+            start,
+            duration,
+            timing,
+        },
+        session_start,
+    ))
+}
+
+/// "HTTP/1.1", "HTTP/2", ... as recorded on results
+fn version_label(version: reqwest::Version) -> &'static str {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_11 => "HTTP/1.1",
+        reqwest::Version::HTTP_2 => "HTTP/2",
+        reqwest::Version::HTTP_3 => "HTTP/3",
+        _ => "unknown",
+    }
+}
+
+/// Decode a body sent with `Content-Encoding: encoding`.
+///
+/// Returns `None` for `identity`, unsupported encodings, and bodies that
+/// fail to decode.
+fn decode_body(encoding: &str, body: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read;
+
+    let mut decoded = Vec::new();
+    let read = match encoding {
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded),
+        // Per RFC 9110 deflate is zlib-wrapped, but some servers send raw deflate
+        "deflate" => flate2::read::ZlibDecoder::new(body)
+            .read_to_end(&mut decoded)
+            .or_else(|_| {
+                decoded.clear();
+                flate2::read::DeflateDecoder::new(body).read_to_end(&mut decoded)
+            }),
+        "br" => brotli_decompressor::Decompressor::new(body, 4096).read_to_end(&mut decoded),
+        _ => return None,
+    };
+    read.ok().map(|_| decoded)
+}
+
+/// Headers and body exactly as they will be sent: mutations, cookie
+/// options, and the pre-request hook applied
+async fn resolve_request<'a>(
+    request: &'a CapturedRequest,
+    index: usize,
+    url: &str,
+    config: &ReplayConfig,
+    cookie_jar: Option<&Jar>,
+) -> Result<(Vec<(String, String)>, Option<Cow<'a, str>>)> {
+    // HAR and hand-written captures may lack Host; the captured URL names it
+    let mut captured = Cow::Borrowed(&request.headers);
+    let has_host = request
+        .headers
+        .iter()
+        .any(|(n, _)| n.eq_ignore_ascii_case("host"));
+    if config.keep_host && !has_host {
+        let original = Url::parse(&request.url).context("Invalid original URL")?;
+        if let Some(host) = original.host_str() {
+            let host = match original.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            captured.to_mut().insert(0, ("Host".to_string(), host));
+        }
+    }
+    if !config.default_headers.is_empty() {
+        fill_default_headers(captured.to_mut(), &config.default_headers);
+    }
+    let mut headers = apply_mutations(
+        &captured,
+        &config.header_mutations,
+        config.strip_cookies,
+        config.keep_host,
+    );
+    // Server-issued cookies replace captured ones of the same name
+    if let Some(jar) = cookie_jar {
+        let parsed = Url::parse(url).context("Invalid target URL")?;
+        if let Some(value) = jar.cookies(&parsed) {
+            for pair in value.to_str().unwrap_or("").split(';') {
+                if let Some((name, value)) = pair.trim().split_once('=') {
+                    apply_sticky_cookie(&mut headers, name, value);
+                }
+            }
+        }
+    }
+    if let Some((ref name, ref value)) = config.sticky_cookie {
+        apply_sticky_cookie(&mut headers, name, value);
+    }
+    for (_, value) in headers.iter_mut() {
+        if let Cow::Owned(filled) = template::substitute(value, &config.vars) {
+            *value = filled;
+        }
+    }
+    let body = match request.body {
+        Some(ref body) if !config.strip_bodies => {
+            Some(match body.load(config.body_dir.as_deref())? {
+                Cow::Borrowed(text) => template::substitute(text, &config.vars),
+                Cow::Owned(text) => {
+                    Cow::Owned(template::substitute(&text, &config.vars).into_owned())
+                }
+            })
+        }
+        _ => None,
+    };
+
+    if let Some(ref command) = config.pre_request_hook {
+        let input = HookInput {
+            index,
+            method: &request.method,
+            url,
+            headers: &headers,
+            body: body.as_deref(),
+        };
+        let added = hook::run_pre_request_hook(command, &input).await?;
+        hook::merge_hook_headers(&mut headers, added);
+    }
+
+    Ok((headers, body))
+}
+
+/// What came back for a request, however it was sent
+struct ResponseParts {
+    status: u16,
+    peer_addr: Option<String>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    trailers: Vec<(String, String)>,
+    /// Encoding `body` was decoded from
+    content_encoding: Option<String>,
+    truncated_stream: bool,
+    /// `body` stops at `body_read_limit`
+    body_truncated: bool,
+    /// Negotiated protocol, e.g. "HTTP/2"; `None` for raw socket sends
+    http_version: Option<String>,
+    start: Instant,
+    duration: Duration,
+    timing: Option<Timing>,
+}
+
+/// Turn a received response into a ReplayResult
+fn build_result(
+    request: &CapturedRequest,
+    index: usize,
+    url: String,
+    config: &ReplayConfig,
+    response: ResponseParts,
+    session_start: Instant,
+) -> ReplayResult {
+    let ResponseParts {
+        status,
+        peer_addr,
+        headers: mut response_headers,
+        body: body_bytes,
+        trailers,
+        content_encoding,
+        truncated_stream,
+        body_truncated,
+        http_version,
+        start,
+        duration,
+        timing,
+    } = response;
+    if let Some(ref keep) = config.keep_headers {
+        response_headers.retain(|(name, _)| keep.contains(&name.to_lowercase()));
+    }
+    let body_size = body_bytes.len();
+
+    // Always compute hash for comparison even when body capture is off
+    let body_hash = if !body_bytes.is_empty() {
+        let hash = Sha256::digest(&body_bytes);
+        Some(format!("{:x}", hash))
+    } else {
+        None
+    };
+
+    let (body, body_base64) = if config.capture_body && body_size <= config.max_body_bytes {
+        match String::from_utf8(body_bytes) {
+            Ok(text) => (Some(text), None),
+            Err(e) => (None, Some(BASE64.encode(e.as_bytes()))),
+        }
+    } else {
+        (None, None)
+    };
+
+    let status_match = request
+        .expected_status
+        .map(|expected| expected == status)
+        .unwrap_or(true);
+
+    ReplayResult {
+        request_index: index,
+        method: request.method.clone(),
+        url,
+        status,
+        headers: response_headers,
+        body,
+        body_base64,
+        body_hash,
+        body_size,
+        duration_ms: duration.as_millis() as u64,
+        start_offset_ms: start.duration_since(session_start).as_millis() as u64,
+        expected_status: request.expected_status,
+        status_match,
+        error: None,
+        error_kind: None,
+        truncated_stream,
+        body_truncated,
+        http_version,
+        probe_tls_version: None,
+        probe_tls_cipher: None,
+        target: None,
+        faults: vec![],
+        accept: None,
+        name: request.name.clone(),
+        peer_addr,
+        geo: None,
+        trailers,
+        content_encoding,
+        attempts: 1,
+        timing,
+        repeat_index: None,
+    }
+}
+
+/// Check whether a response looks open-ended: SSE, or chunked with no length
+fn is_streaming_response(headers: &HeaderMap) -> bool {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_lowercase())
+    };
+
+    if header("content-type").is_some_and(|ct| ct.starts_with("text/event-stream")) {
+        return true;
+    }
+    header("transfer-encoding").is_some_and(|te| te.contains("chunked"))
+        && header("content-length").is_none()
+}
+
+/// Read a response body for at most `window` (and at most `limit` bytes),
+/// returning what arrived, whether the stream was cut off by the window, and
+/// whether it was cut off by the limit
+async fn read_stream_window(
+    mut response: reqwest::Response,
+    window: Duration,
+    limit: Option<usize>,
+) -> Result<(Vec<u8>, bool, bool)> {
+    let deadline = tokio::time::Instant::now() + window;
+    let mut body = Vec::new();
+    loop {
+        match tokio::time::timeout_at(deadline, response.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                body.extend_from_slice(&chunk);
+                if let Some(limit) = limit.filter(|&limit| body.len() > limit) {
+                    body.truncate(limit);
+                    return Ok((body, false, true));
+                }
+            }
+            Ok(Ok(None)) => return Ok((body, false, false)),
+            Ok(Err(e)) => return Err(e).context("Failed to read response body"),
+            Err(_) => return Ok((body, true, false)),
+        }
+    }
+}
+
+/// Read at most `limit` bytes of a response body, returning them, the
+/// trailers (only when the body was read to the end), and whether the body
+/// was cut off. The rest is never read; dropping the body closes the
+/// connection.
+async fn read_body_limited(
+    mut body: reqwest::Body,
+    limit: usize,
+) -> Result<(Vec<u8>, Vec<(String, String)>, bool)> {
+    let mut bytes = Vec::new();
+    let mut trailers = vec![];
+    while let Some(frame) = body.frame().await {
+        let frame = frame.context("Failed to read response body")?;
+        if let Some(map) = frame.trailers_ref() {
+            trailers = header_pairs(map);
+        } else if let Ok(chunk) = frame.into_data() {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > limit {
+                bytes.truncate(limit);
+                return Ok((bytes, vec![], true));
+            }
+        }
+    }
+    Ok((bytes, trailers, false))
+}
+
+/// Header map as (name, value) pairs; values that aren't text become empty
+fn header_pairs(map: &HeaderMap) -> Vec<(String, String)> {
+    map.iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect()
+}
+
+/// Parse a curl-style DNS override "host:port:ip" (IPv6 may be bracketed)
+pub fn parse_resolve(spec: &str) -> Result<(String, SocketAddr)> {
+    let mut parts = spec.splitn(3, ':');
+    let (Some(host), Some(port), Some(ip)) = (parts.next(), parts.next(), parts.next()) else {
+        anyhow::bail!("Invalid --resolve '{}', expected HOST:PORT:IP", spec);
+    };
+    if host.is_empty() {
+        anyhow::bail!("Invalid --resolve '{}': empty host", spec);
+    }
+    let port: u16 = port
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid --resolve '{}': bad port '{}'", spec, port))?;
+    let ip: std::net::IpAddr = ip
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid --resolve '{}': bad IP address '{}'", spec, ip))?;
+    Ok((host.to_lowercase(), SocketAddr::new(ip, port)))
+}
+
+/// A sed-style URL substitution (`--rewrite 's/regex/replacement/'`)
+#[derive(Debug, Clone)]
+pub struct UrlRewrite {
+    pattern: regex::Regex,
+    replacement: String,
+    global: bool,
+}
+
+impl UrlRewrite {
+    /// Parse `s/regex/replacement/` with an optional trailing `g` to replace
+    /// every match. Any delimiter works (`s|^/api|/v2/api|`), and an escaped
+    /// delimiter stands for itself. Replacements use `$1`/`${name}` for
+    /// capture groups; sed's `\1` is accepted too.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut chars = spec.chars();
+        let (Some('s'), Some(delim)) = (chars.next(), chars.next()) else {
+            anyhow::bail!(
+                "Invalid --rewrite '{}', expected s/regex/replacement/",
+                spec
+            );
+        };
+        if delim.is_alphanumeric() || delim == '\\' {
+            anyhow::bail!("Invalid --rewrite '{}': bad delimiter '{}'", spec, delim);
+        }
+
+        // Split on unescaped delimiters
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            match c {
+                _ if escaped => {
+                    if c != delim {
+                        parts.last_mut().unwrap().push('\\');
+                    }
+                    parts.last_mut().unwrap().push(c);
+                    escaped = false;
+                }
+                '\\' => escaped = true,
+                _ if c == delim => parts.push(String::new()),
+                _ => parts.last_mut().unwrap().push(c),
+            }
+        }
+        let [pattern, replacement, flags] = <[String; 3]>::try_from(parts).map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid --rewrite '{}', expected s/regex/replacement/",
+                spec
+            )
+        })?;
+        let global = match flags.as_str() {
+            "" => false,
+            "g" => true,
+            other => anyhow::bail!("Invalid --rewrite '{}': unknown flags '{}'", spec, other),
+        };
+
+        let pattern = regex::Regex::new(&pattern)
+            .with_context(|| format!("Invalid --rewrite '{}': bad regex", spec))?;
+        let sed_group = regex::Regex::new(r"\\([0-9])").expect("valid regex");
+        let replacement = sed_group.replace_all(&replacement, "$${$1}").into_owned();
+        Ok(Self {
+            pattern,
+            replacement,
+            global,
+        })
+    }
+
+    /// Apply to `url`
+    pub fn apply(&self, url: &str) -> String {
+        let limit = if self.global { 0 } else { 1 };
+        self.pattern
+            .replacen(url, limit, self.replacement.as_str())
+            .into_owned()
+    }
+}
+
+/// Query string clean-up before sending (`--normalize-query`, `--drop-param`)
+#[derive(Debug, Clone, Default)]
+pub struct QueryNormalization {
+    /// Sort parameters by name, then value
+    pub sort: bool,
+    /// Parameter names removed from every URL, e.g. a cache-busting `_ts`
+    pub drop: Vec<String>,
+}
+
+impl QueryNormalization {
+    /// Apply to `url`. Parameters are kept byte-for-byte (including any
+    /// `${var}` placeholders); only their order and presence change.
+    pub fn apply(&self, url: &str) -> String {
+        if !self.sort && self.drop.is_empty() {
+            return url.to_string();
+        }
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let Some((base, query)) = rest.split_once('?') else {
+            return url.to_string();
+        };
+
+        // Compare on decoded names and values, keep the raw text
+        let mut params: Vec<((String, String), &str)> = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|raw| {
+                let decoded = url::form_urlencoded::parse(raw.as_bytes())
+                    .next()
+                    .map(|(n, v)| (n.into_owned(), v.into_owned()))
+                    .unwrap_or_default();
+                (decoded, raw)
+            })
+            .filter(|((name, _), _)| !self.drop.contains(name))
+            .collect();
+        if self.sort {
+            params.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        let mut out = base.to_string();
+        if !params.is_empty() {
+            out.push('?');
+            out.push_str(
+                &params
+                    .iter()
+                    .map(|(_, raw)| *raw)
+                    .collect::<Vec<_>>()
+                    .join("&"),
+            );
+        }
+        if let Some(fragment) = fragment {
+            out.push('#');
+            out.push_str(fragment);
+        }
+        out
+    }
+}
+
+/// `Authorization` value for `--bearer`: the token itself, or `@path` to
+/// read it from a file (surrounding whitespace is trimmed)
+pub fn bearer_authorization(spec: &str) -> Result<String> {
+    let token = match spec.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read bearer token from {}", path))?
+            .trim()
+            .to_string(),
+        None => spec.to_string(),
+    };
+    if token.is_empty() {
+        anyhow::bail!("Bearer token is empty");
+    }
+    Ok(format!("Bearer {}", token))
+}
+
+/// `Authorization` value for `--basic user:pass`
+pub fn basic_authorization(spec: &str) -> Result<String> {
+    if !spec.contains(':') {
+        anyhow::bail!("Invalid --basic credentials, expected USER:PASS");
+    }
+    Ok(format!("Basic {}", BASE64.encode(spec)))
+}
+
+/// Parse a timeout: bare numbers are seconds, anything else goes through
+/// `parse_duration` ("500ms", "2m")
+pub fn parse_timeout(value: &str) -> Result<Duration> {
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        Ok(Duration::from_secs(value.parse().map_err(|_| {
+            anyhow::anyhow!("Invalid timeout: '{}'", value)
+        })?))
+    } else {
+        parse_duration(value)
+    }
+}
+
+/// Parse a duration like "500ms", "2s", or "1m" (bare numbers are milliseconds)
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "ms"),
+    };
+    let n: u64 = num
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid duration: '{}'", s))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(n)),
+        "s" => Ok(Duration::from_secs(n)),
+        "m" => Ok(Duration::from_secs(n * 60)),
+        _ => anyhow::bail!("Invalid duration unit in '{}' (expected ms, s, or m)", s),
+    }
+}
+
+/// Final URL for a request: the target's host, then query normalization,
+/// then `--rewrite` rules in order, then variables
+fn request_url(request: &CapturedRequest, target: &Url, config: &ReplayConfig) -> Result<String> {
+    let url = config.query.apply(&rewrite_url(&request.url, target)?);
+    let url = config
+        .url_rewrites
+        .iter()
+        .fold(url, |url, rule| rule.apply(&url));
+    Ok(template::substitute_url(&url, &config.vars))
 }
 
 /// Rewrite a URL to use the target host
-fn rewrite_url(original: &str, target: &Url) -> Result<String> {
+pub(crate) fn rewrite_url(original: &str, target: &Url) -> Result<String> {
     let mut url = Url::parse(original).context("Invalid original URL")?;
 
     // Replace scheme, host, and port with target
@@ -357,10 +2386,20 @@ fn rewrite_url(original: &str, target: &Url) -> Result<String> {
 }
 
 /// Apply header mutations to a request
+/// Append each default header the request doesn't already carry
+fn fill_default_headers(headers: &mut Vec<(String, String)>, defaults: &[(String, String)]) {
+    for (name, value) in defaults {
+        if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            headers.push((name.clone(), value.clone()));
+        }
+    }
+}
+
 fn apply_mutations(
     headers: &[(String, String)],
     mutations: &[(String, String)],
     strip_cookies: bool,
+    keep_host: bool,
 ) -> Vec<(String, String)> {
     let mut result: Vec<(String, String)> = headers
         .iter()
@@ -370,8 +2409,8 @@ fn apply_mutations(
             if strip_cookies && name_lower == "cookie" {
                 return false;
             }
-            // Skip host header (will be set by reqwest)
-            if name_lower == "host" {
+            // Skip host header (will be set by reqwest) unless it is kept
+            if name_lower == "host" && !keep_host {
                 return false;
             }
             // Skip content-length (will be set by reqwest)
@@ -389,12 +2428,18 @@ fn apply_mutations(
             // Remove header
             result.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
         } else {
-            // Add or replace header
+            // Add or replace header; a repeated header is replaced as a
+            // whole, at the position of its first occurrence
             let pos = result
                 .iter()
                 .position(|(n, _)| n.eq_ignore_ascii_case(name));
             if let Some(idx) = pos {
                 result[idx] = (name.clone(), value.clone());
+                let rest = result.split_off(idx + 1);
+                result.extend(
+                    rest.into_iter()
+                        .filter(|(n, _)| !n.eq_ignore_ascii_case(name)),
+                );
             } else {
                 result.push((name.clone(), value.clone()));
             }
@@ -404,8 +2449,35 @@ fn apply_mutations(
     result
 }
 
-/// Build a HeaderMap from header tuples
-fn build_header_map(headers: &[(String, String)]) -> Result<HeaderMap> {
+/// Set a cookie on the request, replacing any existing cookie of the same name
+fn apply_sticky_cookie(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    // The old value can sit in any of several Cookie lines
+    let mut first = None;
+    for (i, (n, v)) in headers.iter_mut().enumerate() {
+        if !n.eq_ignore_ascii_case("cookie") {
+            continue;
+        }
+        let cookies: Vec<&str> = v
+            .split(';')
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty() && c.split('=').next() != Some(name))
+            .collect();
+        *v = cookies.join("; ");
+        first.get_or_insert(i);
+    }
+
+    let pair = format!("{}={}", name, value);
+    match first {
+        Some(idx) if headers[idx].1.is_empty() => headers[idx].1 = pair,
+        Some(idx) => headers[idx].1 = format!("{}; {}", headers[idx].1, pair),
+        None => headers.push(("Cookie".to_string(), pair)),
+    }
+    headers.retain(|(n, v)| !(n.eq_ignore_ascii_case("cookie") && v.is_empty()));
+}
+
+/// Build a HeaderMap from header tuples. Repeated names (two `Cookie` or
+/// `Accept` lines) are all kept, in order, and sent as separate lines.
+pub(crate) fn build_header_map(headers: &[(String, String)]) -> Result<HeaderMap> {
     let mut map = HeaderMap::new();
 
     for (name, value) in headers {
@@ -415,12 +2487,165 @@ fn build_header_map(headers: &[(String, String)]) -> Result<HeaderMap> {
         let header_value: HeaderValue = value
             .parse()
             .context(format!("Invalid header value for {}", name))?;
-        map.insert(header_name, header_value);
+        map.append(header_name, header_value);
     }
 
     Ok(map)
 }
 
+/// Requests with header names whose casing replay can't keep: the HTTP
+/// client sends every name lowercase (as HTTP/2 requires). With `raw`,
+/// requests that carry a `raw` blob are sent byte-for-byte and don't count.
+pub fn mixed_case_header_requests(requests: &[CapturedRequest], raw: bool) -> usize {
+    requests
+        .iter()
+        .filter(|r| !(raw && r.raw.is_some()))
+        .filter(|r| {
+            r.headers
+                .iter()
+                .any(|(name, _)| name.bytes().any(|b| b.is_ascii_uppercase()))
+        })
+        .count()
+}
+
+/// Methods that never change server state and are safe to replay anywhere
+const SAFE_METHODS: &[&str] = &["GET", "HEAD", "OPTIONS", "TRACE"];
+
+/// Check whether replaying `requests` against `target` needs explicit confirmation.
+///
+/// True when the target host contains `production_pattern` (case-insensitive)
+/// and the capture contains at least one non-idempotent method. An empty
+/// pattern disables the guard.
+pub fn needs_production_confirmation(
+    requests: &[CapturedRequest],
+    target: &str,
+    production_pattern: &str,
+) -> bool {
+    if production_pattern.is_empty() {
+        return false;
+    }
+
+    let host = Url::parse(target)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default();
+    if !host.contains(&production_pattern.to_lowercase()) {
+        return false;
+    }
+
+    requests
+        .iter()
+        .any(|r| !SAFE_METHODS.contains(&r.method.to_uppercase().as_str()))
+}
+
+/// Fully resolved requests for one target, recorded before sending.
+///
+/// Replaying a plan skips URL rewriting inputs, header mutations, cookie
+/// options, and hooks, so the same bytes go out on every run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayPlan {
+    pub version: String,
+    pub ushio_version: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub target: String,
+    /// Requests with final URLs, headers, and bodies
+    pub requests: Vec<CapturedRequest>,
+}
+
+/// Resolve every request against `target` without sending anything.
+///
+/// Pre-request hooks run here, once per request, in capture order.
+pub async fn plan(
+    requests: &[CapturedRequest],
+    target: &str,
+    config: &ReplayConfig,
+) -> Result<ReplayPlan> {
+    let target_url = Url::parse(target).context("Invalid target URL")?;
+    let mut planned = Vec::with_capacity(requests.len());
+    for (index, request) in requests.iter().enumerate() {
+        let url = request_url(request, &target_url, config)?;
+        let (headers, body) = resolve_request(request, index, &url, config, None)
+            .await
+            .with_context(|| format!("Failed to plan request #{}", index))?;
+        planned.push(CapturedRequest {
+            headers,
+            body: body.map(|b| Body::Inline(b.into_owned())),
+            url,
+            ..request.clone()
+        });
+    }
+
+    Ok(ReplayPlan {
+        version: "1.0".to_string(),
+        ushio_version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: chrono::Utc::now(),
+        target: target.to_string(),
+        requests: planned,
+    })
+}
+
+/// Send a plan's requests exactly as recorded.
+///
+/// Request-shaping options in `config` are ignored; execution options
+/// (timeouts, concurrency, faults, ...) still apply.
+pub async fn replay_plan(
+    plan: &ReplayPlan,
+    config: ReplayConfig,
+    progress: Option<ProgressFn>,
+) -> Result<ReplaySession> {
+    let config = ReplayConfig {
+        header_mutations: vec![],
+        default_headers: vec![],
+        vars: vec![],
+        url_rewrites: vec![],
+        query: QueryNormalization::default(),
+        strip_cookies: false,
+        keep_host: false,
+        cookie_jar: false,
+        strip_bodies: false,
+        sticky_cookie: None,
+        pre_request_hook: None,
+        ..config
+    };
+    replay_with_progress(&plan.requests, &plan.target, config, progress).await
+}
+
+/// Save a replay plan to a file
+pub fn save_plan(plan: &ReplayPlan, path: &str) -> Result<()> {
+    let content = serde_json::to_string_pretty(plan)?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Load a replay plan from a file
+pub fn load_plan(path: &str) -> Result<ReplayPlan> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read plan {}", path))?;
+    let plan: ReplayPlan =
+        serde_json::from_str(&content).with_context(|| format!("Invalid replay plan {}", path))?;
+    if plan.version != "1.0" {
+        anyhow::bail!("Unsupported replay plan version '{}'", plan.version);
+    }
+    Ok(plan)
+}
+
+/// Point each result's `request_index` back at its position in the full
+/// capture, after replaying the filtered subset `original` describes
+pub fn restore_request_indices(session: &mut ReplaySession, original: &[usize]) {
+    for result in &mut session.results {
+        result.request_index = original_index(original, result.request_index);
+    }
+    if let Some(index) = session.aborted_at {
+        session.aborted_at = Some(original_index(original, index));
+    }
+}
+
+/// Capture index of position `index` in a filtered subset; unchanged when
+/// `original` doesn't cover it
+fn original_index(original: &[usize], index: usize) -> usize {
+    original.get(index).copied().unwrap_or(index)
+}
+
 /// Save a replay session to a file
 pub fn save_session(session: &ReplaySession, path: &str) -> Result<()> {
     let content = serde_json::to_string_pretty(session)?;
@@ -439,6 +2664,170 @@ pub fn load_session(path: &str) -> Result<ReplaySession> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_shuffle_order_is_seeded_permutation() {
+        let order = shuffle_order(50, 7);
+        assert_eq!(order, shuffle_order(50, 7));
+        assert_ne!(order, shuffle_order(50, 8));
+        assert_ne!(order, (0..50).collect::<Vec<_>>());
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..50).collect::<Vec<_>>());
+        assert!(shuffle_order(0, 7).is_empty());
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        assert_eq!(compression_ratio(250, 1000), Some(0.25));
+        assert_eq!(compression_ratio(1000, 1000), Some(1.0));
+        assert_eq!(compression_ratio(0, 0), None);
+    }
+
+    #[test]
+    fn test_timing_schedule_clamps_gaps() {
+        let requests: Vec<CapturedRequest> = [None, Some(100), Some(5000), None, Some(20)]
+            .into_iter()
+            .map(|delay_ms| CapturedRequest {
+                delay_ms,
+                ..Default::default()
+            })
+            .collect();
+        let ms = Duration::from_millis;
+
+        assert_eq!(
+            timing_schedule(&requests, None),
+            vec![ms(0), ms(100), ms(5100), ms(5100), ms(5120)]
+        );
+        assert_eq!(
+            timing_schedule(&requests, Some(ms(1000))),
+            vec![ms(0), ms(100), ms(1100), ms(1100), ms(1120)]
+        );
+    }
+
+    #[test]
+    fn test_parse_resolve() {
+        let (host, addr) = parse_resolve("Staging.example.com:443:10.0.0.7").unwrap();
+        assert_eq!(host, "staging.example.com");
+        assert_eq!(addr, "10.0.0.7:443".parse().unwrap());
+
+        let (_, addr) = parse_resolve("example.com:8443:[::1]").unwrap();
+        assert_eq!(addr, "[::1]:8443".parse().unwrap());
+
+        assert!(parse_resolve("example.com:443").is_err());
+        assert!(parse_resolve("example.com:https:10.0.0.7").is_err());
+        assert!(parse_resolve("example.com:443:not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_url_rewrite() {
+        let prefix = UrlRewrite::parse(r"s|^(https?://[^/]+)/api/|$1/v2/api/|").unwrap();
+        assert_eq!(
+            prefix.apply("https://staging.example.com/api/users?q=/api/"),
+            "https://staging.example.com/v2/api/users?q=/api/"
+        );
+
+        // sed-style groups, escaped delimiters, and the g flag
+        let sed = UrlRewrite::parse(r"s/\/v(\d)\//\/version\1\//g").unwrap();
+        assert_eq!(
+            sed.apply("http://h/v1/a/v2/b"),
+            "http://h/version1/a/version2/b"
+        );
+
+        assert!(UrlRewrite::parse("s/(unclosed/x/").is_err());
+        assert!(UrlRewrite::parse("s/a/b").is_err());
+        assert!(UrlRewrite::parse("s/a/b/x").is_err());
+        assert!(UrlRewrite::parse("/a/b/").is_err());
+    }
+
+    #[test]
+    fn test_authorization_values() {
+        assert_eq!(
+            basic_authorization("aladdin:opensesame").unwrap(),
+            "Basic YWxhZGRpbjpvcGVuc2VzYW1l"
+        );
+        assert!(basic_authorization("aladdin").is_err());
+
+        assert_eq!(bearer_authorization("abc").unwrap(), "Bearer abc");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "eyJ.token\n").unwrap();
+        let spec = format!("@{}", path.display());
+        assert_eq!(bearer_authorization(&spec).unwrap(), "Bearer eyJ.token");
+        assert!(bearer_authorization("@/nonexistent/token").is_err());
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let base = Duration::from_millis(100);
+        assert_eq!(retry_delay(base, 1), Duration::from_millis(100));
+        assert_eq!(retry_delay(base, 2), Duration::from_millis(200));
+        assert_eq!(retry_delay(base, 4), Duration::from_millis(800));
+        // Large attempt counts don't overflow
+        assert!(retry_delay(base, 100) >= retry_delay(base, 17));
+    }
+
+    #[test]
+    fn test_pacer_spaces_slots() {
+        let pacer = Pacer::per_second(10.0);
+        let start = Instant::now();
+        assert_eq!(pacer.reserve(start), start);
+        assert_eq!(pacer.reserve(start), start + Duration::from_millis(100));
+        assert_eq!(pacer.reserve(start), start + Duration::from_millis(200));
+        // After an idle gap the next request goes out immediately
+        let later = start + Duration::from_secs(5);
+        assert_eq!(pacer.reserve(later), later);
+    }
+
+    #[test]
+    fn test_latency_summary_percentiles() {
+        let latency = LatencySummary::from_durations((1..=100).rev().collect()).unwrap();
+        assert_eq!(latency.count, 100);
+        assert_eq!(latency.min_ms, 1);
+        assert_eq!(latency.p50_ms, 50);
+        assert_eq!(latency.p90_ms, 90);
+        assert_eq!(latency.p99_ms, 99);
+        assert_eq!(latency.max_ms, 100);
+        assert!(LatencySummary::from_durations(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_decode_body() {
+        use std::io::Write;
+
+        let text = b"hello hello hello";
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(text).unwrap();
+        let gz = gz.finish().unwrap();
+        assert_eq!(decode_body("gzip", &gz).as_deref(), Some(&text[..]));
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(text).unwrap();
+        assert_eq!(
+            decode_body("deflate", &zlib.finish().unwrap()).as_deref(),
+            Some(&text[..])
+        );
+        let mut raw =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        raw.write_all(text).unwrap();
+        assert_eq!(
+            decode_body("deflate", &raw.finish().unwrap()).as_deref(),
+            Some(&text[..])
+        );
+
+        assert_eq!(decode_body("identity", text), None);
+        assert_eq!(decode_body("zstd", text), None);
+        assert_eq!(decode_body("gzip", b"not gzip"), None);
+    }
+
+    #[test]
+    fn test_is_cache_hit() {
+        assert!(is_cache_hit("HIT"));
+        assert!(is_cache_hit("TCP_HIT"));
+        assert!(is_cache_hit("Hit from cloudfront"));
+        assert!(!is_cache_hit("MISS"));
+        assert!(!is_cache_hit("DYNAMIC"));
+    }
+
     #[test]
     fn test_rewrite_url() {
         let target = Url::parse("https://staging.example.com").unwrap();
@@ -446,6 +2835,32 @@ mod tests {
         assert_eq!(result, "https://staging.example.com/api/users?q=test");
     }
 
+    #[test]
+    fn test_query_normalization() {
+        let query = QueryNormalization {
+            sort: true,
+            drop: vec!["_ts".to_string()],
+        };
+        assert_eq!(
+            query.apply("https://h/search?q=a%20b&_ts=123&page=2&b=${id}#top"),
+            "https://h/search?b=${id}&page=2&q=a%20b#top"
+        );
+        // Dropping the only parameter drops the '?'
+        assert_eq!(query.apply("https://h/?_ts=1"), "https://h/");
+        // Repeated names keep their values sorted
+        assert_eq!(query.apply("https://h/?a=2&a=1"), "https://h/?a=1&a=2");
+        assert_eq!(query.apply("https://h/plain"), "https://h/plain");
+
+        let drop_only = QueryNormalization {
+            sort: false,
+            drop: vec!["_ts".to_string()],
+        };
+        assert_eq!(
+            drop_only.apply("https://h/?z=1&_ts=9&a=2"),
+            "https://h/?z=1&a=2"
+        );
+    }
+
     #[test]
     fn test_rewrite_url_with_port() {
         let target = Url::parse("https://staging.example.com:8443").unwrap();
@@ -457,13 +2872,79 @@ mod tests {
     fn test_apply_mutations_add() {
         let headers = vec![("Content-Type".to_string(), "application/json".to_string())];
         let mutations = vec![("Authorization".to_string(), "Bearer token".to_string())];
-        let result = apply_mutations(&headers, &mutations, false);
+        let result = apply_mutations(&headers, &mutations, false, false);
         assert_eq!(result.len(), 2);
         assert!(result
             .iter()
             .any(|(n, v)| n == "Authorization" && v == "Bearer token"));
     }
 
+    #[test]
+    fn test_repeated_headers_kept_in_order() {
+        let pairs = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+            list.iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect()
+        };
+        let headers = pairs(&[
+            ("Cookie", "a=1"),
+            ("Accept", "text/html"),
+            ("Cookie", "b=2"),
+            ("Accept", "*/*"),
+        ]);
+        let map = build_header_map(&headers).unwrap();
+        let cookies: Vec<_> = map.get_all("cookie").iter().collect();
+        assert_eq!(cookies, ["a=1", "b=2"]);
+        let accepts: Vec<_> = map.get_all("accept").iter().collect();
+        assert_eq!(accepts, ["text/html", "*/*"]);
+
+        // An override replaces every occurrence, at the first one's position
+        let result = apply_mutations(&headers, &pairs(&[("cookie", "c=3")]), false, false);
+        assert_eq!(
+            result,
+            pairs(&[
+                ("cookie", "c=3"),
+                ("Accept", "text/html"),
+                ("Accept", "*/*")
+            ])
+        );
+
+        let requests = vec![
+            CapturedRequest {
+                headers: headers.clone(),
+                ..Default::default()
+            },
+            CapturedRequest {
+                headers: pairs(&[("accept", "*/*")]),
+                ..Default::default()
+            },
+            CapturedRequest {
+                headers,
+                raw: Some("GET / HTTP/1.1\r\n\r\n".to_string()),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(mixed_case_header_requests(&requests, false), 2);
+        assert_eq!(mixed_case_header_requests(&requests, true), 1);
+    }
+
+    #[test]
+    fn test_fill_default_headers_keeps_captured_values() {
+        let mut headers = vec![("accept".to_string(), "text/html".to_string())];
+        let defaults = vec![
+            ("Accept".to_string(), "*/*".to_string()),
+            ("X-Replay".to_string(), "ci".to_string()),
+        ];
+        fill_default_headers(&mut headers, &defaults);
+        assert_eq!(
+            headers,
+            vec![
+                ("accept".to_string(), "text/html".to_string()),
+                ("X-Replay".to_string(), "ci".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_apply_mutations_remove() {
         let headers = vec![
@@ -471,7 +2952,7 @@ mod tests {
             ("X-Debug".to_string(), "true".to_string()),
         ];
         let mutations = vec![("X-Debug".to_string(), "".to_string())];
-        let result = apply_mutations(&headers, &mutations, false);
+        let result = apply_mutations(&headers, &mutations, false, false);
         assert_eq!(result.len(), 1);
         assert!(!result.iter().any(|(n, _)| n == "X-Debug"));
     }
@@ -482,8 +2963,147 @@ mod tests {
             ("Content-Type".to_string(), "application/json".to_string()),
             ("Cookie".to_string(), "session=abc123".to_string()),
         ];
-        let result = apply_mutations(&headers, &[], true);
+        let result = apply_mutations(&headers, &[], true, false);
         assert_eq!(result.len(), 1);
         assert!(!result.iter().any(|(n, _)| n.to_lowercase() == "cookie"));
     }
+
+    #[test]
+    fn test_sticky_cookie_merges() {
+        let mut headers = vec![("Cookie".to_string(), "session=abc; bucket=b".to_string())];
+        apply_sticky_cookie(&mut headers, "bucket", "a");
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].1, "session=abc; bucket=a");
+
+        let mut headers = vec![];
+        apply_sticky_cookie(&mut headers, "bucket", "a");
+        assert_eq!(
+            headers,
+            vec![("Cookie".to_string(), "bucket=a".to_string())]
+        );
+
+        // A stale value in a later Cookie line goes too
+        let cookie = |v: &str| ("Cookie".to_string(), v.to_string());
+        let mut headers = vec![
+            cookie("bucket=A"),
+            ("Accept".to_string(), "*/*".to_string()),
+            cookie("session=abc; bucket=A"),
+        ];
+        apply_sticky_cookie(&mut headers, "bucket", "B");
+        assert_eq!(
+            headers,
+            vec![
+                cookie("bucket=B"),
+                ("Accept".to_string(), "*/*".to_string()),
+                cookie("session=abc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncate_request_body() {
+        let text = CapturedRequest {
+            body: Some(Body::Inline("abcd".to_string())),
+            ..Default::default()
+        };
+        let mut request = Cow::Borrowed(&text);
+        assert!(truncate_request_body(&mut request, None));
+        assert_eq!(request.body, Some(Body::Inline("ab".to_string())));
+
+        let binary = CapturedRequest {
+            body_base64: Some(BASE64.encode([0u8, 0xff, 0xfe, 1])),
+            ..Default::default()
+        };
+        let mut request = Cow::Borrowed(&binary);
+        assert!(truncate_request_body(&mut request, None));
+        assert_eq!(request.body_base64, Some(BASE64.encode([0u8, 0xff])));
+
+        // Nothing to truncate, so the fault isn't reported as applied
+        let empty = CapturedRequest::default();
+        assert!(!truncate_request_body(&mut Cow::Borrowed(&empty), None));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration("250").unwrap(), Duration::from_millis(250));
+        assert!(parse_duration("fast").is_err());
+        assert!(parse_duration("5h").is_err());
+    }
+
+    #[test]
+    fn test_method_timeouts() {
+        let t = MethodTimeouts::parse("POST=60,put=500ms,default=10").unwrap();
+        assert_eq!(t.resolve("POST"), Some(Duration::from_secs(60)));
+        assert_eq!(t.resolve("post"), Some(Duration::from_secs(60)));
+        assert_eq!(t.resolve("PUT"), Some(Duration::from_millis(500)));
+        assert_eq!(t.resolve("GET"), Some(Duration::from_secs(10)));
+
+        let t = MethodTimeouts::parse("POST=60").unwrap();
+        assert_eq!(t.resolve("GET"), None);
+
+        assert!(MethodTimeouts::parse("POST").is_err());
+        assert!(MethodTimeouts::parse("POST=soon").is_err());
+    }
+
+    fn make_request(method: &str) -> CapturedRequest {
+        CapturedRequest {
+            method: method.to_string(),
+            url: "https://example.com/api".to_string(),
+            headers: vec![],
+            body: None,
+            expected_status: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_split_ratio_respected() {
+        let split = parse_split("https://old.example.com=90,https://new.example.com=10").unwrap();
+        let n = 10_000;
+        let to_new = (0..n)
+            .filter(|&i| select_split_target(i, &split) == 1)
+            .count();
+        // Expect ~10% with some slack for hash distribution
+        assert!((800..1200).contains(&to_new), "got {} of {}", to_new, n);
+        // Deterministic across calls
+        assert_eq!(
+            select_split_target(42, &split),
+            select_split_target(42, &split)
+        );
+    }
+
+    #[test]
+    fn test_parse_split_invalid() {
+        assert!(parse_split("https://a.example.com").is_err());
+        assert!(parse_split("https://a.example.com=x").is_err());
+        assert!(parse_split("https://a.example.com=0").is_err());
+    }
+
+    #[test]
+    fn test_production_guard() {
+        let reads = vec![make_request("GET"), make_request("HEAD")];
+        let writes = vec![make_request("GET"), make_request("POST")];
+        assert!(needs_production_confirmation(
+            &writes,
+            "https://prod.example.com",
+            "prod"
+        ));
+        assert!(!needs_production_confirmation(
+            &reads,
+            "https://prod.example.com",
+            "prod"
+        ));
+        assert!(!needs_production_confirmation(
+            &writes,
+            "https://staging.example.com",
+            "prod"
+        ));
+        assert!(!needs_production_confirmation(
+            &writes,
+            "https://prod.example.com",
+            ""
+        ));
+    }
 }
@@ -0,0 +1,145 @@
+//! `${name}` placeholder substitution (`--var`, `--var-file`)
+//!
+//! Lets one capture drive many environments: tenant IDs, tokens, and other
+//! environment-specific values are written as `${name}` in URLs, header
+//! values, and bodies and filled in at replay time. Placeholders without a
+//! value are left as they are, so bodies that happen to contain `${...}`
+//! (JavaScript template literals, shell snippets) pass through untouched.
+
+use anyhow::{bail, Context, Result};
+use std::borrow::Cow;
+
+/// Parse a `name=value` variable
+pub fn parse_var(spec: &str) -> Result<(String, String)> {
+    match spec.split_once('=') {
+        Some((name, value)) if valid_name(name) => Ok((name.to_string(), value.to_string())),
+        _ => bail!(
+            "Invalid variable '{}', expected name=value (name: letters, digits, '_', '-', '.')",
+            spec
+        ),
+    }
+}
+
+/// Load variables from a JSON object of names to strings, numbers, or booleans
+pub fn load_var_file(path: &str) -> Result<Vec<(String, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read variable file {}", path))?;
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+        .with_context(|| format!("Variable file {} must be a JSON object", path))?;
+
+    object
+        .into_iter()
+        .map(|(name, value)| {
+            if !valid_name(&name) {
+                bail!("Invalid variable name '{}' in {}", name, path);
+            }
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                other => bail!("Variable '{}' in {} is not a scalar: {}", name, path, other),
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Replace `${name}` placeholders. Later entries in `vars` win, so `--var`
+/// can override a `--var-file`.
+pub fn substitute<'a>(s: &'a str, vars: &[(String, String)]) -> Cow<'a, str> {
+    substitute_delimited(s, vars, "${", "}")
+}
+
+/// [`substitute`] for a parsed URL, where path braces are percent-encoded
+/// (`$%7Bname%7D`)
+pub fn substitute_url(url: &str, vars: &[(String, String)]) -> String {
+    let url = substitute(url, vars);
+    let url = substitute_delimited(&url, vars, "$%7B", "%7D").into_owned();
+    substitute_delimited(&url, vars, "$%7b", "%7d").into_owned()
+}
+
+fn substitute_delimited<'a>(
+    s: &'a str,
+    vars: &[(String, String)],
+    open: &str,
+    close: &str,
+) -> Cow<'a, str> {
+    if vars.is_empty() || !s.contains(open) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find(open) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + open.len()..];
+        let value = after.find(close).and_then(|end| {
+            let name = &after[..end];
+            let value = vars.iter().rev().find(|(n, _)| n == name)?;
+            Some((&value.1, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + close.len()..];
+            }
+            None => {
+                out.push_str(open);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_substitute_known_and_unknown() {
+        let v = vars(&[("tenant", "acme"), ("token", "t1"), ("token", "t2")]);
+        assert_eq!(
+            substitute("/t/${tenant}?k=${token}&x=${missing}", &v),
+            "/t/acme?k=t2&x=${missing}"
+        );
+        // Unterminated placeholders are kept
+        assert_eq!(substitute("a ${tenant", &v), "a ${tenant");
+        assert!(matches!(substitute("plain", &v), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_substitute_url_encoded_braces() {
+        let v = vars(&[("tenant", "acme")]);
+        let parsed = url::Url::parse("https://example.com/t/${tenant}/x?t=${tenant}").unwrap();
+        assert_eq!(
+            substitute_url(parsed.as_str(), &v),
+            "https://example.com/t/acme/x?t=acme"
+        );
+    }
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(
+            parse_var("api.key=a=b").unwrap(),
+            ("api.key".to_string(), "a=b".to_string())
+        );
+        assert!(parse_var("novalue").is_err());
+        assert!(parse_var("bad name=x").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Per-request timing breakdown (`replay --timing`)
+//!
+//! reqwest only reports when the response headers arrived. To split that
+//! into DNS, connect, and server time, the client gets a resolver and a
+//! connector layer that time themselves and report into a task-local set up
+//! around each `send()`. hyper drives a new connection from the task that
+//! asked for it, so the numbers land on the right request. Reused pooled
+//! connections skip both, and their DNS and connect times are absent.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Where a request's time went
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    /// DNS lookup; absent for IP targets, `--resolve` hosts, and reused connections
+    pub dns_ms: Option<u64>,
+    /// TCP connect and TLS handshake; absent for reused connections
+    pub connect_ms: Option<u64>,
+    /// From sending the request until the response headers arrived
+    pub ttfb_ms: u64,
+    /// Reading the response body
+    pub download_ms: u64,
+}
+
+impl Timing {
+    /// Split `headers_after` (send start to response headers) into phases
+    pub fn new(connect: ConnectPhases, headers_after: Duration, download: Duration) -> Self {
+        let setup = connect.dns.unwrap_or_default() + connect.connect.unwrap_or_default();
+        Self {
+            dns_ms: connect.dns.map(|d| d.as_millis() as u64),
+            connect_ms: connect.connect.map(|d| d.as_millis() as u64),
+            ttfb_ms: headers_after.saturating_sub(setup).as_millis() as u64,
+            download_ms: download.as_millis() as u64,
+        }
+    }
+}
+
+/// Connection setup measured while a request was being sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectPhases {
+    pub dns: Option<Duration>,
+    /// Connection setup after DNS
+    pub connect: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    dns: Cell<Option<Duration>>,
+    connector: Cell<Option<Duration>>,
+}
+
+tokio::task_local! {
+    static RECORDER: Recorder;
+}
+
+/// Run `fut` (a `send()`), collecting the connection setup it triggered
+pub async fn measure<F: Future>(fut: F) -> (F::Output, ConnectPhases) {
+    RECORDER
+        .scope(Recorder::default(), async {
+            let output = fut.await;
+            let phases = RECORDER.with(|r| {
+                let dns = r.dns.get();
+                ConnectPhases {
+                    dns,
+                    // The connector's time includes its DNS lookup
+                    connect: r
+                        .connector
+                        .get()
+                        .map(|c| c.saturating_sub(dns.unwrap_or_default())),
+                }
+            });
+            (output, phases)
+        })
+        .await
+}
+
+/// Resolver that reports how long each lookup took
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimedResolver;
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            let _ = RECORDER.try_with(|r| r.dns.set(Some(start.elapsed())));
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connector layer that reports how long each new connection took
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTimingLayer;
+
+impl<S> tower::Layer<S> for ConnectTimingLayer {
+    type Service = TimedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimedConnector(inner)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimedConnector<S>(S);
+
+impl<S, R> tower::Service<R> for TimedConnector<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.0.call(request);
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = connecting.await;
+            if result.is_ok() {
+                let _ = RECORDER.try_with(|r| r.connector.set(Some(start.elapsed())));
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_subtracts_setup() {
+        let ms = Duration::from_millis;
+        let phases = ConnectPhases {
+            dns: Some(ms(5)),
+            connect: Some(ms(20)),
+        };
+        let timing = Timing::new(phases, ms(100), ms(40));
+        assert_eq!(timing.dns_ms, Some(5));
+        assert_eq!(timing.connect_ms, Some(20));
+        assert_eq!(timing.ttfb_ms, 75);
+        assert_eq!(timing.download_ms, 40);
+
+        // Reused connection: all of it was waiting on the server
+        let timing = Timing::new(ConnectPhases::default(), ms(100), ms(0));
+        assert_eq!(
+            (timing.dns_ms, timing.connect_ms, timing.ttfb_ms),
+            (None, None, 100)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! TLS settings for replay: probed parameters (`--tls-info`) and client
+//! certificates (`--client-cert`, `--client-pkcs12`)
+//!
+//! reqwest doesn't expose the protocol version or cipher suite of its
+//! connections. Instead, each HTTPS host is probed once per session with a
+//! separate rustls handshake (TLS 1.2 and 1.3, ring cipher suites), and the
+//! result is recorded as `probe_tls_*`. The replay client runs on rustls
+//! with the same provider, so the probe makes the same offer as replayed
+//! requests; `--tls-info` is refused with a `--client-pkcs12` identity,
+//! which moves the replay onto native-tls.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio_rustls::rustls;
+
+/// What a TLS handshake settled on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsParams {
+    /// e.g. "TLS 1.3"
+    pub version: String,
+    /// IANA name, e.g. "TLS13_AES_256_GCM_SHA384"
+    pub cipher: String,
+}
+
+/// Handshake with `host` at `addr` and report the negotiated parameters.
+/// The connection is closed right after the handshake.
+pub async fn probe(
+    host: &str,
+    addr: SocketAddr,
+    timeout: Duration,
+    insecure: bool,
+) -> Result<TlsParams> {
+    let handshake = async {
+        let tcp = tokio::net::TcpStream::connect(addr)
+            .await
+            .context("Failed to connect")?;
+        let tls = crate::raw::connect_tls(host, tcp, insecure).await?;
+        let (_, connection) = tls.get_ref();
+        let version = connection
+            .protocol_version()
+            .context("Handshake finished without a protocol version")?;
+        let cipher = connection
+            .negotiated_cipher_suite()
+            .context("Handshake finished without a cipher suite")?;
+        Ok(TlsParams {
+            version: version_label(version),
+            cipher: format!("{:?}", cipher.suite()),
+        })
+    };
+    tokio::time::timeout(timeout, handshake)
+        .await
+        .context("TLS probe timed out")?
+}
+
+fn version_label(version: rustls::ProtocolVersion) -> String {
+    match version {
+        rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2".to_string(),
+        rustls::ProtocolVersion::TLSv1_3 => "TLS 1.3".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// A client certificate and private key for mutual TLS
+#[derive(Clone)]
+pub struct ClientIdentity {
+    identity: reqwest::Identity,
+    backend: Backend,
+}
+
+/// The TLS library an identity was loaded for. reqwest identities only
+/// work with the backend that parsed them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Rustls,
+    #[cfg(feature = "pkcs12")]
+    NativeTls,
+}
+
+/// Keeps the private key out of `{:?}` output
+impl std::fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ClientIdentity(..)")
+    }
+}
+
+impl ClientIdentity {
+    /// Load a PEM certificate (chain) and its PEM private key (PKCS#8,
+    /// PKCS#1 or SEC1)
+    pub fn from_pem_files(cert_path: &str, key_path: &str) -> Result<Self> {
+        let cert = std::fs::read(cert_path)
+            .with_context(|| format!("Failed to read client certificate {}", cert_path))?;
+        let key = std::fs::read(key_path)
+            .with_context(|| format!("Failed to read client key {}", key_path))?;
+        if !contains(&cert, b"-----BEGIN CERTIFICATE-----") {
+            anyhow::bail!("{} contains no PEM certificate", cert_path);
+        }
+        if !contains(&key, b"PRIVATE KEY-----") {
+            anyhow::bail!("{} contains no PEM private key", key_path);
+        }
+        let mut pem = cert;
+        pem.push(b'\n');
+        pem.extend_from_slice(&key);
+        let identity =
+            reqwest::Identity::from_pem(&pem).context("Invalid client certificate or key")?;
+        Self::checked(identity, Backend::Rustls)
+    }
+
+    /// Load a PKCS#12 (`.p12`/`.pfx`) bundle
+    #[cfg(feature = "pkcs12")]
+    pub fn from_pkcs12_file(path: &str, password: &str) -> Result<Self> {
+        let der =
+            std::fs::read(path).with_context(|| format!("Failed to read PKCS#12 file {}", path))?;
+        let identity = reqwest::Identity::from_pkcs12_der(&der, password)
+            .with_context(|| format!("Failed to open PKCS#12 file {} (wrong password?)", path))?;
+        Self::checked(identity, Backend::NativeTls)
+    }
+
+    /// Without the `pkcs12` feature no bundle can be opened
+    #[cfg(not(feature = "pkcs12"))]
+    pub fn from_pkcs12_file(_path: &str, _password: &str) -> Result<Self> {
+        anyhow::bail!(
+            "ushio was built without the 'pkcs12' feature; --client-pkcs12 is unavailable"
+        )
+    }
+
+    /// Build a client with the identity on the backend replay will use, so
+    /// a key that doesn't belong to the certificate fails before any request
+    /// is sent
+    fn checked(identity: reqwest::Identity, backend: Backend) -> Result<Self> {
+        let identity = Self { identity, backend };
+        client_builder(Some(&identity))
+            .build()
+            .context("Client certificate and key don't form a usable identity")?;
+        Ok(identity)
+    }
+}
+
+/// A client builder on the replay's TLS backend: rustls, unless a PKCS#12
+/// identity needs native-tls
+pub fn client_builder(identity: Option<&ClientIdentity>) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    let Some(identity) = identity else {
+        return builder.use_rustls_tls();
+    };
+    let builder = match identity.backend {
+        Backend::Rustls => builder.use_rustls_tls(),
+        #[cfg(feature = "pkcs12")]
+        Backend::NativeTls => builder.use_native_tls(),
+    };
+    builder.identity(identity.identity.clone())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_identity_from_pem_files() {
+        let certified = rcgen::generate_simple_self_signed(vec!["client".to_string()]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("client.crt");
+        let key = dir.path().join("client.key");
+        std::fs::write(&cert, certified.cert.pem()).unwrap();
+        std::fs::write(&key, certified.key_pair.serialize_pem()).unwrap();
+        let (cert, key) = (cert.to_str().unwrap(), key.to_str().unwrap());
+
+        let identity = ClientIdentity::from_pem_files(cert, key).unwrap();
+        assert_eq!(format!("{:?}", identity), "ClientIdentity(..)");
+
+        // Swapped arguments are caught before any request
+        let err = ClientIdentity::from_pem_files(key, cert).unwrap_err();
+        assert!(err.to_string().contains("no PEM certificate"), "{}", err);
+
+        // A key from another pair doesn't match the certificate
+        let other = rcgen::KeyPair::generate().unwrap();
+        let other_key = dir.path().join("other.key");
+        std::fs::write(&other_key, other.serialize_pem()).unwrap();
+        assert!(ClientIdentity::from_pem_files(cert, other_key.to_str().unwrap()).is_err());
+    }
+}
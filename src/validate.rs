@@ -0,0 +1,298 @@
+//! Capture file validation (`ushio validate`)
+//!
+//! Deserialization stops at the first problem and reports it as a byte
+//! offset. Validation walks the parsed JSON instead, so every problem in a
+//! hand-edited capture is listed with its location (`requests[3].method`),
+//! and also checks what only fails at replay time: methods, header names
+//! and values, URLs, and body files.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::capture::CapturedRequest;
+
+/// Fields of the capture root
+const ROOT_FIELDS: &[&str] = &["version", "source", "sources", "requests"];
+
+/// Fields of a captured request
+const REQUEST_FIELDS: &[&str] = &[
+    "method",
+    "url",
+    "headers",
+    "body",
+    "body_base64",
+    "expected_status",
+    "expected_headers",
+    "expected_body",
+    "name",
+    "description",
+    "raw",
+    "delay_ms",
+    "count",
+    "tags",
+];
+
+/// Request fields without a default
+const REQUIRED_REQUEST_FIELDS: &[&str] = &["method", "url", "headers"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The capture won't load, or the request would fail at replay time
+    Error,
+    /// Probably a mistake, but replay is unaffected (e.g. a misspelled
+    /// optional field, which is ignored)
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where the problem is, e.g. `requests[3].headers[0]`
+    pub location: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub path: String,
+    pub total_requests: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationReport {
+    pub fn error_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics.len() - self.error_count()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error_count() == 0
+    }
+
+    fn error(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, location.into(), message.into());
+    }
+
+    fn warning(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, location.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, location: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            location,
+            message,
+        });
+    }
+}
+
+/// Validate a capture file's contents. `path` selects JSON5 parsing for
+/// `.json5` files, as when loading, and is where relative body files are
+/// looked up from.
+pub fn validate_capture(content: &str, path: &str) -> ValidationReport {
+    let mut report = ValidationReport {
+        path: path.to_string(),
+        total_requests: 0,
+        diagnostics: Vec::new(),
+    };
+
+    let parsed: Result<serde_json::Value, String> = if path.ends_with(".json5") {
+        json5::from_str(content).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(content)
+            .or_else(|e| json5::from_str(content).map_err(|_| e.to_string()))
+    };
+    let root = match parsed {
+        Ok(root) => root,
+        Err(e) => {
+            report.error("(file)", format!("Not valid JSON: {}", e));
+            return report;
+        }
+    };
+    let Some(root) = root.as_object() else {
+        report.error(
+            "(root)",
+            "Expected an object with \"version\" and \"requests\"",
+        );
+        return report;
+    };
+
+    for key in root.keys() {
+        if !ROOT_FIELDS.contains(&key.as_str()) {
+            report.warning(key.as_str(), "Unknown field (ignored)");
+        }
+    }
+    match root.get("version") {
+        Some(serde_json::Value::String(version)) if version == "1.0" => {}
+        Some(serde_json::Value::String(version)) => report.error(
+            "version",
+            format!("Unsupported format version '{}' (supported: 1.0)", version),
+        ),
+        Some(other) => report.error("version", format!("Expected a string, got {}", other)),
+        None => report.error("version", "Missing required field"),
+    }
+
+    let requests = match root.get("requests") {
+        Some(serde_json::Value::Array(requests)) => requests,
+        Some(_) => {
+            report.error("requests", "Expected an array of requests");
+            return report;
+        }
+        None => {
+            report.error("requests", "Missing required field");
+            return report;
+        }
+    };
+    report.total_requests = requests.len();
+    let base = Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty());
+    for (i, request) in requests.iter().enumerate() {
+        validate_request(&mut report, &format!("requests[{}]", i), request, base);
+    }
+    report
+}
+
+fn validate_request(
+    report: &mut ValidationReport,
+    at: &str,
+    value: &serde_json::Value,
+    base: Option<&Path>,
+) {
+    let Some(object) = value.as_object() else {
+        report.error(at, "Expected a request object");
+        return;
+    };
+    for key in object.keys() {
+        if !REQUEST_FIELDS.contains(&key.as_str()) {
+            report.warning(format!("{}.{}", at, key), "Unknown field (ignored)");
+        }
+    }
+    let missing: Vec<&str> = REQUIRED_REQUEST_FIELDS
+        .iter()
+        .copied()
+        .filter(|field| !object.contains_key(*field))
+        .collect();
+    for field in &missing {
+        report.error(format!("{}.{}", at, field), "Missing required field");
+    }
+    if !missing.is_empty() {
+        return;
+    }
+
+    // Types next; the replay-time checks need a well-typed request
+    let request: CapturedRequest = match serde_json::from_value(value.clone()) {
+        Ok(request) => request,
+        Err(e) => {
+            report.error(at, e.to_string());
+            return;
+        }
+    };
+
+    if reqwest::Method::from_bytes(request.method.as_bytes()).is_err() {
+        report.error(
+            format!("{}.method", at),
+            format!("Invalid HTTP method '{}'", request.method),
+        );
+    }
+
+    match url::Url::parse(&request.url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+        Ok(url) => report.error(
+            format!("{}.url", at),
+            format!(
+                "Unsupported scheme '{}' (expected http or https)",
+                url.scheme()
+            ),
+        ),
+        Err(e) => report.error(
+            format!("{}.url", at),
+            format!("Invalid URL '{}': {}", request.url, e),
+        ),
+    }
+
+    for (j, header) in request.headers.iter().enumerate() {
+        if let Err(e) = crate::replay::build_header_map(std::slice::from_ref(header)) {
+            report.error(format!("{}.headers[{}]", at, j), e.to_string());
+        }
+    }
+
+    if let Some(body) = &request.body {
+        if let Err(e) = body.load(base) {
+            report.error(format!("{}.body", at), format!("{:#}", e));
+        }
+    }
+
+    if let Some(encoded) = &request.body_base64 {
+        use base64::Engine;
+        if let Err(e) = base64::engine::general_purpose::STANDARD.decode(encoded) {
+            report.error(
+                format!("{}.body_base64", at),
+                format!("Invalid base64: {}", e),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_every_problem_with_location() {
+        let content = r#"{
+            "version": "1.0",
+            "requests": [
+                {"method": "GET", "url": "https://example.com/", "headers": [], "body": null, "expected_status": 200},
+                {"method": "GE T", "url": "not a url", "headers": [["Bad Name", "x"], ["X-Ok", "a\nb"]], "expected_stauts": 200},
+                {"method": "POST", "headers": []},
+                {"method": "GET", "url": "ftp://example.com/", "headers": "nope"},
+                {"method": "POST", "url": "https://example.com/", "headers": [], "body": {"file": "no-such-body.json"}}
+            ]
+        }"#;
+        let report = validate_capture(content, "capture.json");
+        assert_eq!(report.total_requests, 5);
+        assert!(!report.is_valid());
+
+        let at = |location: &str| {
+            report
+                .diagnostics
+                .iter()
+                .find(|d| d.location == location)
+                .map(|d| d.severity)
+        };
+        assert_eq!(at("requests[1].method"), Some(Severity::Error));
+        assert_eq!(at("requests[1].url"), Some(Severity::Error));
+        assert_eq!(at("requests[1].headers[0]"), Some(Severity::Error));
+        assert_eq!(at("requests[1].headers[1]"), Some(Severity::Error));
+        assert_eq!(at("requests[1].expected_stauts"), Some(Severity::Warning));
+        assert_eq!(at("requests[2].url"), Some(Severity::Error));
+        assert_eq!(at("requests[3]"), Some(Severity::Error));
+        assert_eq!(at("requests[4].body"), Some(Severity::Error));
+        assert!(report
+            .diagnostics
+            .iter()
+            .all(|d| !d.location.starts_with("requests[0]")));
+    }
+
+    #[test]
+    fn test_valid_capture_and_unparseable_file() {
+        let content = r#"{"version": "1.0", "source": "x", "requests": [
+            {"method": "PATCH", "url": "http://localhost:8080/a?b=c", "headers": [["Accept", "*/*"]], "body": "{}", "expected_status": null}
+        ]}"#;
+        let report = validate_capture(content, "capture.json");
+        assert!(report.is_valid());
+        assert!(report.diagnostics.is_empty());
+
+        let report = validate_capture("{\"version\": ", "capture.json");
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(report.diagnostics[0].location, "(file)");
+    }
+}
@@ -0,0 +1,119 @@
+//! Capture file watching for `replay --watch`
+//!
+//! The file is polled for size and modification time rather than watched
+//! through OS notifications: it needs no platform backends, works on network
+//! and container-mounted filesystems, and a capture is a single file. Rapid
+//! successive writes (editor save sequences, a converter still writing) are
+//! debounced into one change.
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watched file is checked
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the file must stay unchanged before a change is reported
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// What is compared between polls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+}
+
+/// Current stamp of `path`, or `None` while it does not exist
+pub fn stamp(path: &Path) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+    })
+}
+
+/// Turns a stream of polled stamps into debounced change events
+#[derive(Debug)]
+pub struct Debouncer {
+    last_seen: Option<FileStamp>,
+    changed_at: Option<Instant>,
+    debounce: Duration,
+}
+
+impl Debouncer {
+    pub fn new(initial: Option<FileStamp>, debounce: Duration) -> Self {
+        Self {
+            last_seen: initial,
+            changed_at: None,
+            debounce,
+        }
+    }
+
+    /// Feed the latest stamp. Returns true once, when a change has settled
+    /// for the debounce window and the file exists again.
+    pub fn observe(&mut self, stamp: Option<FileStamp>, now: Instant) -> bool {
+        if stamp != self.last_seen {
+            self.last_seen = stamp;
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            Some(at) if self.last_seen.is_some() && now.duration_since(at) >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Wait until `path` changes and then stays unchanged for `debounce`
+pub async fn wait_for_change(path: &Path, poll_interval: Duration, debounce: Duration) {
+    let mut debouncer = Debouncer::new(stamp(path), debounce);
+    loop {
+        tokio::time::sleep(poll_interval).await;
+        if debouncer.observe(stamp(path), Instant::now()) {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(len: u64) -> Option<FileStamp> {
+        Some(FileStamp {
+            modified: None,
+            len,
+        })
+    }
+
+    #[test]
+    fn test_change_reported_after_debounce() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let mut d = Debouncer::new(at(1), Duration::from_millis(300));
+
+        assert!(!d.observe(at(1), ms(100)));
+        assert!(!d.observe(at(2), ms(200)));
+        // Still being written: the window restarts
+        assert!(!d.observe(at(3), ms(400)));
+        assert!(!d.observe(at(3), ms(600)));
+        assert!(d.observe(at(3), ms(700)));
+        // Reported once
+        assert!(!d.observe(at(3), ms(2000)));
+    }
+
+    #[test]
+    fn test_missing_file_waits_for_recreate() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let mut d = Debouncer::new(at(1), Duration::from_millis(300));
+
+        // Save-by-rename: the file briefly disappears
+        assert!(!d.observe(None, ms(100)));
+        assert!(!d.observe(None, ms(1000)));
+        assert!(!d.observe(at(5), ms(1100)));
+        assert!(d.observe(at(5), ms(1400)));
+    }
+}